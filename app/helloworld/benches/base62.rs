@@ -0,0 +1,45 @@
+//! base62 编解码吞吐：`cargo bench -p tinyid --bench base62`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use tinyid::generator::base62;
+
+fn sample_ids() -> Vec<u64> {
+    // 覆盖短ID和满 11 位的ID
+    (0..1024u64)
+        .map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (i % 40))
+        .collect()
+}
+
+fn bench_base62(c: &mut Criterion) {
+    let ids = sample_ids();
+    let encoded: Vec<String> = ids.iter().map(|&id| base62::encode(id)).collect();
+
+    let mut group = c.benchmark_group("base62");
+    group.throughput(Throughput::Elements(ids.len() as u64));
+    group.bench_function("encode_into", |b| {
+        let mut buf = [0u8; base62::MAX_LEN];
+        b.iter(|| {
+            for &id in &ids {
+                black_box(base62::encode_into(black_box(id), &mut buf));
+            }
+        })
+    });
+    group.bench_function("encode", |b| {
+        b.iter(|| {
+            for &id in &ids {
+                black_box(base62::encode(black_box(id)));
+            }
+        })
+    });
+    group.bench_function("decode", |b| {
+        b.iter(|| {
+            for s in &encoded {
+                black_box(base62::decode(black_box(s)).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_base62);
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 在编译期嵌入构建信息，供 `/version` 接口使用
+fn main() {
+    // git commit hash（不在 git 仓库中构建时回退为 unknown）
+    let git_sha =
+        run("git", &["rev-parse", "--short=12", "HEAD"]).unwrap_or_else(|| "unknown".into());
+    println!("cargo:rustc-env=TINYID_GIT_SHA={}", git_sha);
+
+    // 构建时间（Unix 秒），运行时再格式化为 RFC3339
+    let build_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=TINYID_BUILD_TIMESTAMP={}", build_timestamp);
+
+    // rustc 版本
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    let rustc_version = run(&rustc, &["--version"]).unwrap_or_else(|| "unknown".into());
+    println!("cargo:rustc-env=TINYID_RUSTC_VERSION={}", rustc_version);
+
+    // 仅在提交变化时重新执行
+    if let Some(git_dir) = run("git", &["rev-parse", "--git-dir"]) {
+        let head = Path::new(&git_dir).join("HEAD");
+        println!("cargo:rerun-if-changed={}", head.display());
+        if let Some(head_ref) = run("git", &["symbolic-ref", "-q", "HEAD"]) {
+            println!(
+                "cargo:rerun-if-changed={}",
+                Path::new(&git_dir).join(head_ref).display()
+            );
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+fn run(cmd: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(cmd).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let value = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
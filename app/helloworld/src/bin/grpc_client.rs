@@ -6,6 +6,7 @@ use tracing::{error, info};
 use tracing_subscriber::filter::EnvFilter;
 
 use shared::config::IdGeneratorRpcConfig;
+use shared::health::{health_checked_channel, HEALTH_CHECK_INTERVAL};
 use shared::proto::id_generator::{
     id_generator_service_client::IdGeneratorServiceClient, GenerateIdRequest,
 };
@@ -13,14 +14,20 @@ use shared::proto::id_generator::{
 pub fn new_id_generator_client(
     cfg: IdGeneratorRpcConfig,
 ) -> Result<IdGeneratorServiceClient<Channel>, Box<dyn std::error::Error>> {
-    let endpoints = cfg.rpc_cfg.addr.into_iter().map(|a| {
-        Channel::from_shared(a)
-            .unwrap()
-            .keep_alive_while_idle(true)
-            .keep_alive_timeout(Duration::from_secs(20))
-            .connect_timeout(Duration::from_secs(5))
-    });
-    let channel = Channel::balance_list(endpoints);
+    let endpoints = cfg
+        .rpc_cfg
+        .addr
+        .into_iter()
+        .map(|a| {
+            Channel::from_shared(a)
+                .unwrap()
+                .keep_alive_while_idle(true)
+                .keep_alive_timeout(Duration::from_secs(20))
+                .connect_timeout(Duration::from_secs(5))
+        })
+        .collect();
+    // 定期做健康检查，不健康的节点不参与负载均衡
+    let channel = health_checked_channel(endpoints, HEALTH_CHECK_INTERVAL);
     let client: IdGeneratorServiceClient<Channel> = IdGeneratorServiceClient::new(channel);
     Ok(client)
 }
@@ -50,7 +57,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             // 使用超时包装请求
             let result = timeout(Duration::from_secs(5), async {
                 let mut client_guard = client.lock().await;
-                let req = tonic::Request::new(GenerateIdRequest {});
+                let req = tonic::Request::new(GenerateIdRequest::default());
                 client_guard.generate_id(req).await
             })
             .await;
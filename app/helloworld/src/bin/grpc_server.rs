@@ -1,15 +1,21 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use shared::config::ServerConfig;
+use shared::health::{HealthService, HEALTH_CHECK_INTERVAL};
+use shared::metric;
+use shared::proto::health::health_server::HealthServer;
 use shared::proto::id_generator::id_generator_service_server::IdGeneratorServiceServer;
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use tonic::transport::Server;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use tinyid::biz::{HelloWorldUseCase, UserDemoUseCase};
-use tinyid::core::IDGenerator;
+use tinyid::biz::{HelloWorldUseCase, RecentErrors, UserDemoUseCase};
+use tinyid::core::{IDGenerator, IdAuditor, IdGeneratorRegistry};
 use tinyid::data::{new_user_client, HelloWorldRepoImpl};
+use tinyid::server::{grpc_trace_layer, GrpcMetricsLayer};
 use tinyid::service::HelloWorldService;
 
 #[tokio::main]
@@ -28,15 +34,62 @@ async fn main() -> Result<()> {
         vec!["[::1]:50051".to_string()],
     );
 
-    let (server, cleanup) = init_app(cfg.clone())?;
+    // 3. 启动 metrics 服务器，导出 gRPC 调用指标；绑定失败时按 metrics_required 决定是否退出
+    // 默认端口与 http-server 错开，两者可以在同一台机器上同时运行
+    let metrics_config =
+        metric::MetricsConfig::with_default_port(metric::DEFAULT_GRPC_METRICS_PORT);
+    let (metrics_server, app_metrics) = metric::init_metrics_with_config(metrics_config)
+        .map_err(|e| anyhow::anyhow!("Failed to initialize metrics: {}", e))?;
+    match metrics_server.bind().await {
+        Ok(listener) => {
+            tokio::spawn(async move {
+                if let Err(e) = metrics_server
+                    .serve_with_shutdown(listener, std::future::pending())
+                    .await
+                {
+                    warn!("Metrics server error: {}", e);
+                }
+            });
+        }
+        Err(e) if cfg.metrics_required => return Err(e),
+        Err(e) => error!("Metrics server disabled: {:#}", e),
+    }
+
+    let (server, cleanup) = init_app(cfg.clone(), Arc::clone(&app_metrics))?;
     let (tx, mut rx) = mpsc::unbounded_channel();
+    let health = HealthService::default();
+    let cancel_token = CancellationToken::new();
+
+    // 就绪检查失败（租约到期、时钟停滞等）时对外报告 NOT_SERVING
+    let ready_timeout = Duration::from_millis(cfg.health.ready_timeout_ms);
+    let updater = tokio::spawn(update_health_periodically(
+        server.clone(),
+        health.clone(),
+        ready_timeout,
+    ));
+    // 收到关闭信号后先置为 NOT_SERVING，等客户端下一轮探测摘除本节点后再停止服务
+    tokio::spawn({
+        let (health, cancel_token) = (health.clone(), cancel_token.clone());
+        async move {
+            shutdown_signal().await;
+            updater.abort();
+            let _ = updater.await;
+            health.set_serving(false);
+            info!(drain = ?HEALTH_CHECK_INTERVAL, "Marked NOT_SERVING, draining before shutdown");
+            tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+            cancel_token.cancel();
+        }
+    });
 
     for addr in cfg.grpc_addr {
         let addr = addr.parse()?;
         let tx = tx.clone();
         let srv = Server::builder()
+            .layer(grpc_trace_layer())
+            .layer(GrpcMetricsLayer::new(app_metrics.clone()))
             .add_service(IdGeneratorServiceServer::new(server.clone()))
-            .serve(addr);
+            .add_service(HealthServer::new(health.clone()))
+            .serve_with_shutdown(addr, cancel_token.clone().cancelled_owned());
         tokio::spawn(async move {
             if let Err(e) = srv.await {
                 error!("grpc server error: {}", e);
@@ -50,8 +103,50 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// 定期做一次就绪检查，把结果同步到 gRPC 健康状态
+async fn update_health_periodically(
+    server: HelloWorldService<HelloWorldRepoImpl, HelloWorldRepoImpl>,
+    health: HealthService,
+    timeout: Duration,
+) {
+    let mut ticker = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let serving = server.readiness(timeout).await.code.is_success();
+        if serving != health.is_serving() {
+            warn!(serving, "grpc health status changed");
+        }
+        health.set_serving(serving);
+    }
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigterm =
+            signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+        let mut sigint = signal(SignalKind::interrupt()).expect("Failed to install SIGINT handler");
+
+        tokio::select! {
+            _ = sigterm.recv() => info!("Received SIGTERM, shutting down..."),
+            _ = sigint.recv() => info!("Received SIGINT, shutting down..."),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install CTRL+C handler");
+        info!("Received CTRL+C, shutting down...");
+    }
+}
+
 fn init_app(
     cfg: ServerConfig,
+    app_metrics: Arc<metric::AppMetrics>,
 ) -> Result<(
     HelloWorldService<HelloWorldRepoImpl, HelloWorldRepoImpl>,
     impl FnOnce(),
@@ -60,18 +155,43 @@ fn init_app(
         return Err(anyhow::anyhow!("grpc_addr is empty"));
     }
     // data
-    let id_generator = IDGenerator::new(cfg.id_generator.clone()).unwrap();
+    let mut id_generator = IDGenerator::new(cfg.id_generator.clone())?
+        .with_cas_retry_counter(app_metrics.cas_retries.clone())
+        .with_sequence_wait_counter(app_metrics.sequence_waits.clone());
+    let auditor = match &cfg.audit {
+        Some(audit) => {
+            let auditor = Arc::new(IdAuditor::from_config(audit)?);
+            id_generator = id_generator.with_auditor(Arc::clone(&auditor));
+            let interval = Duration::from_millis(audit.flush_interval_ms.max(1));
+            let flusher = Arc::clone(&auditor);
+            tokio::spawn(async move { flusher.flush_periodically(interval).await });
+            Some(auditor)
+        }
+        None => None,
+    };
+    id_generator.log_startup_summary();
     let user_client = new_user_client(cfg.user_rpc.clone()).unwrap();
-    let hello_world_repo = Arc::new(HelloWorldRepoImpl::new(
-        Arc::new(id_generator),
-        user_client,
-    )?);
-    let hello_world_uc = Arc::new(HelloWorldUseCase::new(hello_world_repo.clone()));
-    let user_uc = Arc::new(UserDemoUseCase::new(hello_world_repo.clone()));
-    let service = HelloWorldService::new(hello_world_uc, user_uc);
-
-    let cleanup = || {
+    let namespaces = IdGeneratorRegistry::new(&cfg.namespaces, &id_generator)?;
+    let hello_world_repo = Arc::new(
+        HelloWorldRepoImpl::new(Arc::new(id_generator), user_client)?.with_namespaces(namespaces),
+    );
+    let recent_errors = Arc::new(RecentErrors::new(cfg.recent_errors_capacity));
+    let hello_world_uc = Arc::new(
+        HelloWorldUseCase::new(hello_world_repo.clone())
+            .with_recent_errors(recent_errors.clone())
+            .with_metrics(app_metrics),
+    );
+    let user_uc =
+        Arc::new(UserDemoUseCase::new(hello_world_repo.clone()).with_recent_errors(recent_errors));
+    let service = HelloWorldService::new(hello_world_uc, user_uc)
+        .with_grpc_default_deadline(cfg.grpc_default_deadline_ms.map(Duration::from_millis))
+        .with_max_concurrent_streams(cfg.grpc_max_concurrent_streams);
+
+    let cleanup = move || {
         info!("Cleaning up application resources");
+        if let Some(auditor) = auditor {
+            auditor.flush();
+        }
     };
 
     Ok((service, cleanup))
@@ -1,24 +1,47 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use shared::{config::ServerConfig, metric};
+use anyhow::Result;
+use shared::{
+    config::{RuntimeConfig, RuntimeConfigHandle, ServerConfig},
+    metric,
+};
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
-use tinyid::biz::{HelloWorldUseCase, UserDemoUseCase};
-use tinyid::core::IDGenerator;
+use tinyid::biz::{HelloWorldUseCase, RecentErrors, UserDemoUseCase};
+use tinyid::core::{Clock, IDGenerator, IdAuditor, IdGeneratorRegistry, SystemClock};
 use tinyid::data::{new_user_client, HelloWorldRepoImpl};
 use tinyid::server;
 
+/// 命令行用法：
+///   http-server [--config <path>]
+///   http-server --write-config-template <path>
+///   http-server validate-config --file <path>
 #[tokio::main]
 async fn main() -> Result<()> {
+    // 0. 解析命令行：生成配置模板、校验配置后直接退出
+    let cli = Cli::parse(std::env::args().skip(1))?;
+    if let Some(path) = &cli.write_config_template {
+        ServerConfig::write_template(path)?;
+        println!("config template written to {}", path);
+        return Ok(());
+    }
+    if cli.validate_config {
+        let path = cli
+            .config
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("validate-config requires --file <path>"))?;
+        return validate_config(path);
+    }
+
     // 1. 初始化环境变量
     shared::init_env();
 
     // 2. 初始化 tracing（统一入口）
     // very opinionated init of tracing, look at the source to make your own
 
-    shared::init_tracing()?;
+    let tracing_cleanup = shared::init_tracing()?;
 
     info!("TinyID HTTP Server starting...");
 
@@ -39,46 +62,200 @@ async fn main() -> Result<()> {
         signal_cancel_token.cancel();
     });
 
-    // 6. 启动 metrics 服务器
-    let metrics_handle = {
+    // 6. 加载配置
+    let mut cfg = match &cli.config {
+        Some(path) => ServerConfig::from_file(path)?,
+        None => ServerConfig::http_only(String::from("0.0.0.0"), 8080),
+    };
+    // TINYID_* 环境变量优先于配置文件
+    cfg.id_generator = cfg.id_generator.with_env()?;
+    reject_lease(&cfg)?;
+
+    // 7. 启动 metrics 服务器；绑定失败时按 metrics_required 决定退出还是不带指标继续运行
+    let metrics_listener = match metrics_server.bind().await {
+        Ok(listener) => Some(listener),
+        Err(e) if cfg.metrics_required => return Err(e),
+        Err(e) => {
+            error!("Metrics server disabled: {:#}", e);
+            None
+        }
+    };
+    let metrics_handle = metrics_listener.map(|listener| {
         let metrics_shutdown = metrics_cancel_token.cancelled_owned();
         tokio::spawn(async move {
-            if let Err(e) = metrics_server.start_with_shutdown(metrics_shutdown).await {
+            if let Err(e) = metrics_server
+                .serve_with_shutdown(listener, metrics_shutdown)
+                .await
+            {
                 error!("Metrics server error: {}", e);
             }
         })
-    };
+    });
 
-    // 7. 构建主应用服务器
-    let (app, cleanup) = init_app(
-        ServerConfig::new(String::from("0.0.0.0"), 8080, vec![]),
-        app_metrics,
-    )?;
+    // 8. 构建主应用服务器
+    let (app, cleanup) = init_app(cfg.clone(), app_metrics)?;
+    let app = app.with_telemetry_flusher(tracing_cleanup.flusher());
 
-    // 8. 启动主服务器
+    // 监听 SIGHUP 重新加载运行时配置（仅在指定了配置文件时）
+    if let Some(path) = cli.config.clone() {
+        tokio::spawn(reload_on_sighup(path, cfg, app.runtime.clone()));
+    }
+
+    // 9. 启动主服务器
     info!("Starting main HTTP server...");
     let server_result = app.run_with_shutdown(shutdown_future).await;
 
-    // 9. 等待 metrics 服务器关闭
-    info!("Waiting for metrics server to shutdown...");
-    if let Err(e) = metrics_handle.await {
-        warn!("Metrics server task error: {}", e);
+    // 10. 等待 metrics 服务器关闭
+    if let Some(metrics_handle) = metrics_handle {
+        info!("Waiting for metrics server to shutdown...");
+        if let Err(e) = metrics_handle.await {
+            warn!("Metrics server task error: {}", e);
+        }
     }
 
-    // 10. 清理资源
+    // 11. 清理资源
     info!("Cleaning up resources...");
     cleanup();
+    tracing_cleanup.cleanup();
 
-    // 11. 检查服务器错误
+    // 12. 检查服务器错误
     if let Err(e) = server_result {
-        error!("Server error: {}", e);
-        return Err(e.into());
+        let err = anyhow::Error::new(e);
+        error!("Server error: {:#}", err);
+        return Err(err);
     }
 
     info!("TinyID HTTP Server shutdown complete");
     Ok(())
 }
 
+#[derive(Debug, Default)]
+struct Cli {
+    /// 配置文件路径，不指定时使用默认配置
+    config: Option<String>,
+    /// 生成带注释的默认配置模板
+    write_config_template: Option<String>,
+    /// 只校验配置文件并输出报告，不启动服务
+    validate_config: bool,
+}
+
+impl Cli {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut cli = Cli::default();
+        while let Some(arg) = args.next() {
+            let slot = match arg.as_str() {
+                "validate-config" => {
+                    cli.validate_config = true;
+                    continue;
+                }
+                "--config" | "--file" => &mut cli.config,
+                "--write-config-template" => &mut cli.write_config_template,
+                other => anyhow::bail!("unknown argument: {}", other),
+            };
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("{} requires a path", arg))?;
+            *slot = Some(value);
+        }
+        Ok(cli)
+    }
+}
+
+/// 还没有可用的 worker id 协调器后端，配置 `[lease]` 时直接报错，
+/// 避免看起来启用了租约保护、实际上永远不会到期
+fn reject_lease(cfg: &ServerConfig) -> Result<()> {
+    if cfg.lease.is_some() {
+        anyhow::bail!(
+            "[lease] is not supported yet: no worker id coordinator backend is available, \
+             remove the [lease] section"
+        );
+    }
+    Ok(())
+}
+
+/// 加载并完整校验配置文件，输出通过/失败报告及推导值，失败时以非零状态退出
+fn validate_config(path: &str) -> Result<()> {
+    let mut cfg = ServerConfig::from_file(path)?;
+    cfg.id_generator = cfg.id_generator.with_env()?;
+    reject_lease(&cfg)?;
+    let now_millis = SystemClock.now_millis()?;
+    match IDGenerator::validate_config(&cfg.id_generator, now_millis) {
+        Ok(layout) => {
+            println!("config {} OK", path);
+            println!(
+                "  layout: {}-{}-{}-{} (environment_bits {}, version_bits {})",
+                layout.timestamp_bits,
+                layout.datacenter_id_bits,
+                layout.worker_id_bits,
+                layout.sequence_bits,
+                layout.environment_bits,
+                layout.version_bits
+            );
+            println!(
+                "  datacenter_id: {}/{}, worker_id: {}/{}",
+                layout.datacenter_id,
+                layout.max_datacenter_id,
+                layout.worker_id,
+                layout.max_worker_id
+            );
+            println!("  capacity_per_second: {}", layout.capacity_per_second);
+            println!(
+                "  exhausted_at: {}",
+                layout.exhausted_at.as_deref().unwrap_or("invalid")
+            );
+            Ok(())
+        }
+        Err(errors) => {
+            println!("config {} FAILED", path);
+            for error in &errors {
+                println!("  - {}", error);
+            }
+            anyhow::bail!("{} config error(s) in {}", errors.len(), path)
+        }
+    }
+}
+
+/// 收到 SIGHUP 时重新读取配置文件，只替换可热更新的部分
+#[cfg(unix)]
+async fn reload_on_sighup(path: String, initial: ServerConfig, runtime: RuntimeConfigHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sighup = match signal(SignalKind::hangup()) {
+        Ok(sighup) => sighup,
+        Err(e) => {
+            warn!("Failed to install SIGHUP handler: {}", e);
+            return;
+        }
+    };
+
+    while sighup.recv().await.is_some() {
+        let cfg = match ServerConfig::from_file(&path).and_then(|mut cfg| {
+            cfg.id_generator = cfg.id_generator.with_env()?;
+            Ok(cfg)
+        }) {
+            Ok(cfg) => cfg,
+            Err(e) => {
+                error!("Failed to reload config, keeping current values: {}", e);
+                continue;
+            }
+        };
+
+        // 位布局等结构性配置不能在运行中修改
+        if serde_json::to_value(&cfg.id_generator).ok()
+            != serde_json::to_value(&initial.id_generator).ok()
+        {
+            warn!("id_generator config changed on disk, ignored until restart");
+        }
+
+        let new_runtime = RuntimeConfig::from(&cfg);
+        info!(?new_runtime, "Runtime config reloaded");
+        runtime.store(new_runtime);
+    }
+}
+
+#[cfg(not(unix))]
+async fn reload_on_sighup(_path: String, _initial: ServerConfig, _runtime: RuntimeConfigHandle) {}
+
 async fn shutdown_signal() {
     #[cfg(unix)]
     {
@@ -109,14 +286,48 @@ fn init_app(
     app_metrics: Arc<metric::AppMetrics>,
 ) -> Result<(server::HttpServer, impl FnOnce())> {
     // data
-    let id_generator = IDGenerator::new(cfg.id_generator.clone()).unwrap();
+    let mut id_generator = IDGenerator::new(cfg.id_generator.clone())?
+        .with_cas_retry_counter(app_metrics.cas_retries.clone())
+        .with_sequence_wait_counter(app_metrics.sequence_waits.clone());
+    let auditor = match &cfg.audit {
+        Some(audit) => {
+            let auditor = Arc::new(IdAuditor::from_config(audit)?);
+            id_generator = id_generator.with_auditor(Arc::clone(&auditor));
+            let interval = Duration::from_millis(audit.flush_interval_ms.max(1));
+            let flusher = Arc::clone(&auditor);
+            tokio::spawn(async move { flusher.flush_periodically(interval).await });
+            Some(auditor)
+        }
+        None => None,
+    };
+    id_generator.log_startup_summary();
     let user_client = new_user_client(cfg.user_rpc.clone()).unwrap();
-    let hello_world_repo = Arc::new(HelloWorldRepoImpl::new(
-        Arc::new(id_generator),
-        user_client,
-    )?);
-    let hello_world_uc = Arc::new(HelloWorldUseCase::new(hello_world_repo.clone()));
-    let user_uc = Arc::new(UserDemoUseCase::new(hello_world_repo.clone()));
+    let namespaces = IdGeneratorRegistry::new(&cfg.namespaces, &id_generator)?;
+    let hello_world_repo = Arc::new(
+        HelloWorldRepoImpl::new(Arc::new(id_generator), user_client)?.with_namespaces(namespaces),
+    );
+    let recent_errors = Arc::new(RecentErrors::new(cfg.recent_errors_capacity));
+    let hello_world_uc = Arc::new(
+        HelloWorldUseCase::new(hello_world_repo.clone())
+            .with_recent_errors(recent_errors.clone())
+            .with_metrics(Arc::clone(&app_metrics)),
+    );
+    let user_uc =
+        Arc::new(UserDemoUseCase::new(hello_world_repo.clone()).with_recent_errors(recent_errors));
+    if let Some(secs) = cfg.generator_report_interval_secs.filter(|&secs| secs > 0) {
+        let usecase = Arc::clone(&hello_world_uc);
+        let metrics = Arc::clone(&app_metrics);
+        tokio::spawn(async move {
+            usecase
+                .report_health(Duration::from_secs(secs), metrics)
+                .await
+        });
+    }
+    if cfg.id_generator.state_file.is_some() {
+        let usecase = Arc::clone(&hello_world_uc);
+        let interval = Duration::from_millis(cfg.id_generator.state_persist_interval_ms.max(1));
+        tokio::spawn(async move { usecase.persist_state(interval).await });
+    }
     // TODO 优化这里的层级初始化问题。期望是每一个层级仅初始化一个上层即可，无需每次都来修改bin文件
 
     let server = server::HttpServer::new_with_metrics(
@@ -126,8 +337,11 @@ fn init_app(
         app_metrics,
     );
 
-    let cleanup = || {
+    let cleanup = move || {
         info!("Cleaning up application resources");
+        if let Some(auditor) = auditor {
+            auditor.flush();
+        }
     };
 
     Ok((server, cleanup))
@@ -1,26 +1,223 @@
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 // use anyhow::{Context, Result};
-use tracing::instrument;
+use shared::metric::AppMetrics;
+use tracing::{info, instrument, warn};
 
+use super::recent_errors::{ErrorSample, RecentErrors};
+use crate::core::{DecodedId, GeneratorStats, LayoutInfo};
 use crate::TinyIdError;
 
+/// `deadline` 为调用方的截止时间（如 gRPC 的 grpc-timeout），None 表示不限制
 pub trait HelloWorldRepo: Send + Sync + std::fmt::Debug {
-    fn generate_id(&self) -> impl std::future::Future<Output = Result<u64, TinyIdError>> + Send;
+    fn generate_id(
+        &self,
+        deadline: Option<Instant>,
+    ) -> impl std::future::Future<Output = Result<u64, TinyIdError>> + Send;
+
+    fn generate_ids(
+        &self,
+        count: usize,
+        deadline: Option<Instant>,
+    ) -> impl std::future::Future<Output = Result<Vec<u64>, TinyIdError>> + Send;
+
+    /// 按本实例的位布局拆解ID
+    fn decode_id(&self, id: u64) -> DecodedId;
+
+    /// 用指定命名空间的生成器生成ID，未配置的命名空间返回 `InvalidRequest`
+    fn generate_id_in(
+        &self,
+        namespace: &str,
+        deadline: Option<Instant>,
+    ) -> impl std::future::Future<Output = Result<u64, TinyIdError>> + Send;
+
+    /// 按指定命名空间的 epoch 和位布局拆解ID
+    fn decode_id_in(&self, namespace: &str, id: u64) -> Result<DecodedId, TinyIdError>;
+
+    /// 生效的位布局
+    fn layout(&self) -> LayoutInfo;
+
+    /// 生成器运行统计
+    fn stats(&self) -> GeneratorStats;
+
+    /// 为过去的某个时刻手动补录一个ID
+    fn mint_backfill_id(&self, timestamp: u64, sequence: u32) -> Result<u64, TinyIdError>;
+
+    /// 持久化生成器高水位（配置了 `state_file` 时）
+    fn save_state(&self) -> Result<(), TinyIdError>;
+
+    /// 运行时切换 worker id，返回切换前的 worker id
+    fn rotate_worker_id(
+        &self,
+        worker_id: u32,
+        deadline: Option<Instant>,
+    ) -> impl std::future::Future<Output = Result<u32, TinyIdError>> + Send;
 }
 
 #[derive(Debug, Clone)]
 pub struct HelloWorldUseCase<R: HelloWorldRepo> {
     hrepo: Arc<R>,
+    recent_errors: Arc<RecentErrors>,
+    // 配置后按实际生成的 ID 数更新 tinyid_ids_generated_total，失败计入失败数
+    metrics: Option<Arc<AppMetrics>>,
 }
 
 impl<R: HelloWorldRepo> HelloWorldUseCase<R> {
     pub fn new(hrepo: Arc<R>) -> Self {
-        Self { hrepo }
+        Self {
+            hrepo,
+            recent_errors: Arc::new(RecentErrors::default()),
+            metrics: None,
+        }
+    }
+
+    /// 使用外部的错误采样缓冲区（与其它 use case 共享）
+    pub fn with_recent_errors(mut self, recent_errors: Arc<RecentErrors>) -> Self {
+        self.recent_errors = recent_errors;
+        self
+    }
+
+    /// 把生成数量和失败数写入应用指标
+    pub fn with_metrics(mut self, metrics: Arc<AppMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// 记录一次生成结果
+    fn record_generation(&self, result: Result<u64, &TinyIdError>) {
+        match result {
+            Ok(count) => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.add_generated_ids(count);
+                }
+            }
+            Err(e) => {
+                self.recent_errors.record(e);
+                if let Some(metrics) = &self.metrics {
+                    metrics.increment_failed_requests();
+                }
+            }
+        }
+    }
+
+    /// 最近的错误样本，按时间倒序
+    pub fn recent_errors(&self) -> Vec<ErrorSample> {
+        self.recent_errors.snapshot()
+    }
+
+    #[instrument(skip(self))]
+    pub async fn generate_id(&self, deadline: Option<Instant>) -> Result<u64, TinyIdError> {
+        let result = self.hrepo.generate_id(deadline).await;
+        self.record_generation(result.as_ref().map(|_| 1));
+        result
+    }
+
+    #[instrument(skip(self))]
+    pub async fn generate_ids(
+        &self,
+        count: usize,
+        deadline: Option<Instant>,
+    ) -> Result<Vec<u64>, TinyIdError> {
+        let result = self.hrepo.generate_ids(count, deadline).await;
+        self.record_generation(result.as_ref().map(|ids| ids.len() as u64));
+        result
+    }
+
+    #[instrument(skip(self))]
+    pub async fn generate_id_in(
+        &self,
+        namespace: &str,
+        deadline: Option<Instant>,
+    ) -> Result<u64, TinyIdError> {
+        let result = self.hrepo.generate_id_in(namespace, deadline).await;
+        self.record_generation(result.as_ref().map(|_| 1));
+        result
+    }
+
+    pub fn decode_id(&self, id: u64) -> DecodedId {
+        self.hrepo.decode_id(id)
+    }
+
+    pub fn decode_id_in(&self, namespace: &str, id: u64) -> Result<DecodedId, TinyIdError> {
+        self.hrepo.decode_id_in(namespace, id)
+    }
+
+    pub fn layout(&self) -> LayoutInfo {
+        self.hrepo.layout()
+    }
+
+    pub fn stats(&self) -> GeneratorStats {
+        self.hrepo.stats()
+    }
+
+    pub fn mint_backfill_id(&self, timestamp: u64, sequence: u32) -> Result<u64, TinyIdError> {
+        self.hrepo
+            .mint_backfill_id(timestamp, sequence)
+            .inspect_err(|e| self.recent_errors.record(e))
+    }
+
+    pub fn save_state(&self) -> Result<(), TinyIdError> {
+        self.hrepo
+            .save_state()
+            .inspect_err(|e| self.recent_errors.record(e))
+    }
+
+    /// 按固定间隔持久化生成器高水位，失败只记录日志，下个周期重试
+    ///
+    /// 永不返回，由调用方 spawn。
+    pub async fn persist_state(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = self.save_state() {
+                warn!(error = %e, "failed to persist generator state");
+            }
+        }
+    }
+
+    /// 按固定间隔采样生成器状态，输出结构化日志并更新 gauge，不依赖请求量和抓取时机
+    ///
+    /// 永不返回，由调用方 spawn。
+    pub async fn report_health(&self, interval: Duration, metrics: Arc<AppMetrics>) {
+        let mut ticker = tokio::time::interval(interval);
+        // interval 的第一次 tick 立即返回，用作采样起点
+        ticker.tick().await;
+        let mut previous = self.stats();
+        let mut previous_at = Instant::now();
+        loop {
+            ticker.tick().await;
+            let current = self.stats();
+            let now = Instant::now();
+            let sample = current.sample_since(&previous, now - previous_at);
+            info!(
+                ids_per_second = sample.ids_per_second,
+                cas_retries_per_id = sample.cas_retries_per_id,
+                sequence_utilization = sample.sequence_utilization,
+                clock_backwards = sample.clock_backwards,
+                total_generated = current.total_generated,
+                "generator health"
+            );
+            metrics.record_generator_sample(
+                sample.ids_per_second,
+                sample.cas_retries_per_id,
+                sample.sequence_utilization,
+                current.clock_backwards,
+            );
+            previous = current;
+            previous_at = now;
+        }
     }
 
     #[instrument(skip(self))]
-    pub async fn generate_id(&self) -> Result<u64, TinyIdError> {
-        self.hrepo.generate_id().await
+    pub async fn rotate_worker_id(
+        &self,
+        worker_id: u32,
+        deadline: Option<Instant>,
+    ) -> Result<u32, TinyIdError> {
+        self.hrepo
+            .rotate_worker_id(worker_id, deadline)
+            .await
+            .inspect_err(|e| self.recent_errors.record(e))
     }
 }
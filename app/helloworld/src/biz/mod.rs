@@ -1,5 +1,7 @@
 pub mod hello_world;
+pub mod recent_errors;
 pub mod user_demo;
 
 pub use hello_world::{HelloWorldRepo, HelloWorldUseCase};
+pub use recent_errors::{ErrorSample, RecentErrors};
 pub use user_demo::{UserDemoRepo, UserDemoUseCase};
@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::Utc;
+#[cfg(feature = "otel")]
+use opentelemetry::trace::TraceContextExt;
+use serde::Serialize;
+#[cfg(feature = "otel")]
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::TinyIdError;
+
+/// 错误消息的最大长度，超出部分截断
+const MAX_MESSAGE_LEN: usize = 256;
+
+/// 一条错误样本
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorSample {
+    /// `TinyIdError` 的变体名
+    pub kind: &'static str,
+    /// 错误的顶层描述，不包含底层错误链（可能带有地址、路径等细节）
+    pub message: String,
+    /// 发生时间（Unix 毫秒）
+    pub timestamp_ms: i64,
+    /// 发生时所在请求的 trace id，不在 trace 中时为 None
+    pub trace_id: Option<String>,
+}
+
+/// 最近错误的环形缓冲区，供 `/admin/recent-errors` 排障使用
+///
+/// 只保留最近 `capacity` 条，容量为 0 时不记录。
+#[derive(Debug)]
+pub struct RecentErrors {
+    capacity: usize,
+    samples: Mutex<VecDeque<ErrorSample>>,
+}
+
+impl Default for RecentErrors {
+    fn default() -> Self {
+        Self::new(50)
+    }
+}
+
+impl RecentErrors {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// 记录一条错误，附带当前 span 的 trace id
+    pub fn record(&self, err: &TinyIdError) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut message = err.to_string();
+        if message.len() > MAX_MESSAGE_LEN {
+            let mut end = MAX_MESSAGE_LEN;
+            while !message.is_char_boundary(end) {
+                end -= 1;
+            }
+            message.truncate(end);
+        }
+        let sample = ErrorSample {
+            kind: err.kind(),
+            message,
+            timestamp_ms: Utc::now().timestamp_millis(),
+            trace_id: current_trace_id(),
+        };
+
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    /// 按时间倒序返回当前保留的样本
+    pub fn snapshot(&self) -> Vec<ErrorSample> {
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        samples.iter().rev().cloned().collect()
+    }
+}
+
+/// 当前 span 所在 trace 的 id
+#[cfg(feature = "otel")]
+fn current_trace_id() -> Option<String> {
+    let span_context = tracing::Span::current()
+        .context()
+        .span()
+        .span_context()
+        .clone();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+/// 未启用 otel feature 时没有 trace id
+#[cfg(not(feature = "otel"))]
+fn current_trace_id() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_latest_samples_newest_first() {
+        let errors = RecentErrors::new(2);
+        errors.record(&TinyIdError::InvalidRequest("first".to_string()));
+        errors.record(&TinyIdError::DeadlineExceeded);
+        errors.record(&TinyIdError::ClockMovedBackwards(5));
+
+        let samples = errors.snapshot();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].kind, "ClockMovedBackwards");
+        assert_eq!(samples[0].message, "Clock moved backwards by 5ms");
+        assert_eq!(samples[1].kind, "DeadlineExceeded");
+        assert!(samples[0].trace_id.is_none());
+    }
+
+    #[test]
+    fn test_redacts_sources_and_long_messages() {
+        let errors = RecentErrors::new(4);
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "/etc/secret/state");
+        errors.record(&TinyIdError::server("failed to persist state", io));
+        errors.record(&TinyIdError::InternalError("中".repeat(200)));
+
+        let samples = errors.snapshot();
+        assert_eq!(samples[1].message, "Server error: failed to persist state");
+        assert!(samples[0].message.len() <= MAX_MESSAGE_LEN);
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_recording() {
+        let errors = RecentErrors::new(0);
+        errors.record(&TinyIdError::DeadlineExceeded);
+        assert!(errors.snapshot().is_empty());
+    }
+}
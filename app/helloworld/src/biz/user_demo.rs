@@ -3,6 +3,7 @@ use std::sync::Arc;
 use shared::proto::user::User;
 use tracing::instrument;
 
+use super::recent_errors::RecentErrors;
 use crate::TinyIdError;
 
 pub trait UserDemoRepo: Send + Sync + std::fmt::Debug {
@@ -15,17 +16,30 @@ pub trait UserDemoRepo: Send + Sync + std::fmt::Debug {
 #[derive(Debug, Clone)]
 pub struct UserDemoUseCase<R: UserDemoRepo> {
     hrepo: Arc<R>,
+    recent_errors: Arc<RecentErrors>,
 }
 
 impl<R: UserDemoRepo> UserDemoUseCase<R> {
     pub fn new(hrepo: Arc<R>) -> Self {
-        Self { hrepo }
+        Self {
+            hrepo,
+            recent_errors: Arc::new(RecentErrors::default()),
+        }
+    }
+
+    /// 使用外部的错误采样缓冲区（与其它 use case 共享）
+    pub fn with_recent_errors(mut self, recent_errors: Arc<RecentErrors>) -> Self {
+        self.recent_errors = recent_errors;
+        self
     }
 }
 
 impl<R: UserDemoRepo> UserDemoUseCase<R> {
     #[instrument(skip(self))]
     pub async fn get_user(&self, id: u64) -> Result<User, TinyIdError> {
-        self.hrepo.get_user(id).await
+        self.hrepo
+            .get_user(id)
+            .await
+            .inspect_err(|e| self.recent_errors.record(e))
     }
 }
@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 服务版本号
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// 构建时的 git commit hash
+pub const GIT_SHA: &str = env!("TINYID_GIT_SHA");
+/// 构建时间（Unix 秒）
+pub const BUILD_TIMESTAMP: &str = env!("TINYID_BUILD_TIMESTAMP");
+/// 编译使用的 rustc 版本
+pub const RUSTC_VERSION: &str = env!("TINYID_RUSTC_VERSION");
+
+/// 构建信息，用于确认线上运行的具体构建
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BuildInfo {
+    pub version: String,
+    pub git_sha: String,
+    /// RFC3339 格式的构建时间
+    pub build_timestamp: String,
+    pub rustc_version: String,
+}
+
+impl BuildInfo {
+    /// 当前二进制的构建信息
+    pub fn current() -> Self {
+        let build_timestamp = BUILD_TIMESTAMP
+            .parse::<i64>()
+            .ok()
+            .and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0))
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_else(|| BUILD_TIMESTAMP.to_string());
+
+        Self {
+            version: VERSION.to_string(),
+            git_sha: GIT_SHA.to_string(),
+            build_timestamp,
+            rustc_version: RUSTC_VERSION.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info_current() {
+        let info = BuildInfo::current();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.git_sha.is_empty());
+        assert!(info.rustc_version.starts_with("rustc") || info.rustc_version == "unknown");
+        assert!(DateTime::parse_from_rfc3339(&info.build_timestamp).is_ok());
+    }
+}
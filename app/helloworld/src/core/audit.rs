@@ -0,0 +1,234 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use shared::config::{AuditConfig, AuditSinkKind};
+use tracing::warn;
+
+use crate::error::TinyIdError;
+
+/// 同一毫秒内连续发出的一段ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct IdRange {
+    pub worker_id: u32,
+    pub datacenter_id: u32,
+    /// Unix 毫秒时间戳
+    pub timestamp: u64,
+    pub start_seq: u64,
+    /// 包含在区间内
+    pub end_seq: u64,
+}
+
+/// 审计区间的输出位置
+pub trait AuditSink: std::fmt::Debug + Send + Sync {
+    fn write(&self, range: &IdRange);
+
+    /// 把缓冲的内容写到底层存储
+    fn flush(&self) {}
+}
+
+/// 每条区间一行 JSON 写到标准输出
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StdoutAuditSink;
+
+impl AuditSink for StdoutAuditSink {
+    fn write(&self, range: &IdRange) {
+        if let Ok(line) = serde_json::to_string(range) {
+            println!("{}", line);
+        }
+    }
+}
+
+/// 每条区间一行 JSON 追加写入文件
+#[derive(Debug)]
+pub struct FileAuditSink {
+    file: Mutex<BufWriter<File>>,
+}
+
+impl FileAuditSink {
+    pub fn open(path: &str) -> Result<Self, TinyIdError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| TinyIdError::server(format!("failed to open audit log {}", path), e))?;
+        Ok(Self {
+            file: Mutex::new(BufWriter::new(file)),
+        })
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn write(&self, range: &IdRange) {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        let written = serde_json::to_writer(&mut *file, range)
+            .map_err(std::io::Error::from)
+            .and_then(|()| file.write_all(b"\n"));
+        if let Err(e) = written {
+            warn!(error = %e, "failed to write audit log");
+        }
+    }
+
+    fn flush(&self) {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = file.flush() {
+            warn!(error = %e, "failed to flush audit log");
+        }
+    }
+}
+
+/// 把发出的ID合并成区间后写入 sink
+///
+/// 同一毫秒内首尾相接的预留合并为一条，毫秒切换或调用 `flush` 时写出；
+/// 并发预留的记录顺序可能与预留顺序不同，此时同一毫秒会拆成多条，但不会遗漏。
+#[derive(Debug)]
+pub struct IdAuditor {
+    sink: Arc<dyn AuditSink>,
+    pending: Mutex<Option<IdRange>>,
+}
+
+impl IdAuditor {
+    pub fn new(sink: Arc<dyn AuditSink>) -> Self {
+        Self {
+            sink,
+            pending: Mutex::new(None),
+        }
+    }
+
+    /// 按配置创建 sink，`sink = "file"` 但未配置 path 时返回错误
+    pub fn from_config(cfg: &AuditConfig) -> Result<Self, TinyIdError> {
+        let sink: Arc<dyn AuditSink> = match cfg.sink {
+            AuditSinkKind::Stdout => Arc::new(StdoutAuditSink),
+            AuditSinkKind::File => {
+                let path = cfg.path.as_deref().ok_or_else(|| {
+                    TinyIdError::ConfigError("audit.path is required for file sink".to_string())
+                })?;
+                Arc::new(FileAuditSink::open(path)?)
+            }
+        };
+        Ok(Self::new(sink))
+    }
+
+    /// 记录一段发出的ID
+    pub fn record(&self, range: IdRange) {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cur) = pending.as_mut() {
+            if cur.timestamp == range.timestamp
+                && cur.worker_id == range.worker_id
+                && cur.datacenter_id == range.datacenter_id
+                && cur.end_seq + 1 == range.start_seq
+            {
+                cur.end_seq = range.end_seq;
+                return;
+            }
+            self.sink.write(cur);
+        }
+        *pending = Some(range);
+    }
+
+    /// 写出尚未结束的区间
+    pub fn flush(&self) {
+        let pending = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        if let Some(range) = pending {
+            self.sink.write(&range);
+        }
+        self.sink.flush();
+    }
+
+    /// 按固定间隔写出尚未结束的区间，避免空闲时最后一段区间迟迟不落盘
+    ///
+    /// 永不返回，由调用方 spawn。
+    pub async fn flush_periodically(&self, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            self.flush();
+        }
+    }
+}
+
+impl Drop for IdAuditor {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct MemorySink(Mutex<Vec<IdRange>>);
+
+    impl AuditSink for MemorySink {
+        fn write(&self, range: &IdRange) {
+            self.0.lock().unwrap().push(*range);
+        }
+    }
+
+    fn range(timestamp: u64, start_seq: u64, end_seq: u64) -> IdRange {
+        IdRange {
+            worker_id: 1,
+            datacenter_id: 1,
+            timestamp,
+            start_seq,
+            end_seq,
+        }
+    }
+
+    #[test]
+    fn test_merges_contiguous_ranges_within_millisecond() {
+        let sink = Arc::new(MemorySink::default());
+        let auditor = IdAuditor::new(sink.clone());
+        auditor.record(range(100, 0, 0));
+        auditor.record(range(100, 1, 9));
+        auditor.record(range(100, 10, 10));
+        assert!(sink.0.lock().unwrap().is_empty());
+
+        // 换毫秒时写出上一段
+        auditor.record(range(101, 0, 3));
+        // 不连续的区间单独成段
+        auditor.record(range(101, 6, 7));
+        auditor.flush();
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            vec![range(100, 0, 10), range(101, 0, 3), range(101, 6, 7)]
+        );
+    }
+
+    #[test]
+    fn test_file_sink_appends_json_lines() {
+        let path = std::env::temp_dir()
+            .join(format!("tinyid-audit-{}.log", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned();
+        let cfg = AuditConfig {
+            sink: AuditSinkKind::File,
+            path: Some(path.clone()),
+            ..AuditConfig::default()
+        };
+        {
+            let auditor = IdAuditor::from_config(&cfg).unwrap();
+            auditor.record(range(100, 0, 4));
+            // drop 时写出
+        }
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            content,
+            "{\"worker_id\":1,\"datacenter_id\":1,\"timestamp\":100,\"start_seq\":0,\"end_seq\":4}\n"
+        );
+
+        let missing_path = AuditConfig {
+            sink: AuditSinkKind::File,
+            ..AuditConfig::default()
+        };
+        assert!(IdAuditor::from_config(&missing_path).is_err());
+    }
+}
@@ -1,196 +1,1144 @@
+use std::cell::RefCell;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use shared::config::IdGeneratorConfig;
-use tracing::{instrument, warn};
+use shared::config::{ClockRollbackPolicy, DatacenterId, Environment, IdGeneratorConfig, WorkerId};
+use tracing::{debug, field::Empty, info, instrument, warn, Span};
 
+use super::audit::{IdAuditor, IdRange};
 use crate::error::TinyIdError;
 
+/// 时间戳单位（毫秒）
+const TIME_UNIT_MS: u64 = 1;
+
+/// 单次调用 CAS 重试超过该次数时打印警告，用于发现争用热点
+const CAS_RETRY_WARN_THRESHOLD: u64 = 64;
+
+/// 序列耗尽或时钟回拨时每轮等待的时长
+const BACKOFF_INTERVAL: Duration = Duration::from_micros(200);
+
+/// 切换 worker id 后旧生成器的 `ts_seq` 取值，之后的生成调用返回 `GeneratorRetired`
+const RETIRED: u64 = u64::MAX;
+
+/// 时间源，默认为系统时钟；测试中可替换为固定或手动推进的时钟
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    /// 当前 Unix 毫秒时间戳
+    fn now_millis(&self) -> Result<u64, TinyIdError>;
+}
+
+/// 基于 `SystemTime` 的系统时钟
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> Result<u64, TinyIdError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| TinyIdError::InternalError(e.to_string()))?;
+        Ok(now.as_millis() as u64)
+    }
+}
+
+fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+thread_local! {
+    /// 当前线程尚未写入 `total_generated` 的计数（`total_generated_batch` > 1 时使用）
+    static PENDING_GENERATED: RefCell<PendingCount> = const { RefCell::new(PendingCount(None)) };
+}
+
+/// 线程本地累计的生成数量及其目标计数器，线程退出时补写
+struct PendingCount(Option<(Arc<AtomicU64>, u64)>);
+
+impl PendingCount {
+    fn flush(&mut self) {
+        if let Some((counter, count)) = self.0.take() {
+            counter.fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    /// 累加到 counter，满 batch 时写入；切换到另一个计数器前先写入旧的
+    fn add(&mut self, counter: &Arc<AtomicU64>, count: u64, batch: u64) {
+        match &mut self.0 {
+            Some((current, pending)) if Arc::ptr_eq(current, counter) => *pending += count,
+            _ => {
+                self.flush();
+                self.0 = Some((Arc::clone(counter), count));
+            }
+        }
+        if self
+            .0
+            .as_ref()
+            .is_some_and(|(_, pending)| *pending >= batch)
+        {
+            self.flush();
+        }
+    }
+}
+
+impl Drop for PendingCount {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// 一次生成调用在多轮等待之间保留的状态
+#[derive(Debug, Default)]
+struct RetryState {
+    cas_retries: u64,
+    // 每次调用只计一次时钟回拨
+    backwards_seen: bool,
+    backwards_since: Option<Instant>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct IDGenerator {
     cfg: IdGeneratorConfig,
+    // 按配置位宽校验过的节点ID
+    worker_id: WorkerId,
+    datacenter_id: DatacenterId,
+    // 启用环境标识位时写入ID的环境
+    environment: Option<Environment>,
     // 原子打包状态：(timestamp << sequence_bits) | sequence
     ts_seq: AtomicU64,
+    // 已写入 state_file 的预留上限，只发出时间戳小于它的ID；未配置 state_file 时为 u64::MAX
+    reserved_ts: AtomicU64,
+    // 串行化预留推进（写 state_file）
+    #[serde(skip)]
+    reserve_lock: Mutex<()>,
     start_time: SystemTime,
-    total_generated: AtomicU64,
+    // 成功生成的ID总数，按 total_generated_batch 批量累加
+    #[serde(skip)]
+    total_generated: Arc<AtomicU64>,
+    // 时钟不可用时生成的随机（非时间有序）ID数量
+    random_generated: AtomicU64,
+    // 遇到时钟回拨的生成调用次数
+    clock_backwards: AtomicU64,
+    // 配置了 random_seed 时使用的确定性随机数源，否则使用线程随机数
+    #[serde(skip)]
+    seeded_rng: Mutex<Option<StdRng>>,
+    // CAS 失败重试总数，可与 AppMetrics 共享以导出 tinyid_cas_retries_total
+    #[serde(skip)]
+    cas_retries: Arc<AtomicU64>,
+    // 序列号耗尽或时钟回拨时等待的次数，可与 AppMetrics 共享以导出 tinyid_sequence_waits_total
+    #[serde(skip)]
+    sequence_waits: Arc<AtomicU64>,
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>,
+    // 配置了审计日志时记录发出的ID区间，切换 worker id 后共用
+    #[serde(skip)]
+    auditor: Option<Arc<IdAuditor>>,
 }
 
 impl IDGenerator {
     pub fn new(cfg: IdGeneratorConfig) -> Result<Self> {
-        // 验证配置
-        if cfg.worker_id > cfg.max_worker_id {
-            return Err(anyhow::anyhow!("worker_id is too large"));
+        // 总位数超过 63 或 max_* 超出位宽时各字段会互相覆盖，生成的ID无法解析
+        cfg.validate()
+            .map_err(|e| TinyIdError::ConfigError(e.to_string()))?;
+        let mut cfg = cfg.resolve_layout();
+        if let Some(source) = cfg.worker_id_source {
+            cfg.worker_id = source.resolve(cfg.max_worker_id)?;
+            info!(
+                worker_id = cfg.worker_id.get(),
+                ?source,
+                "Resolved worker id"
+            );
         }
-        if cfg.datacenter_id > cfg.max_datacenter_id {
-            return Err(anyhow::anyhow!("datacenter_id is too large"));
+        // 反序列化得到的ID不知道位宽，这里按配置的最大值重新校验
+        let worker_id = WorkerId::new(cfg.worker_id.get(), cfg.max_worker_id)?;
+        let datacenter_id = DatacenterId::new(cfg.datacenter_id.get(), cfg.max_datacenter_id)?;
+        // max_* 可能与位宽不一致（如 datacenter_id_bits 设为 0 却保留默认的 max_datacenter_id），
+        // 超出位宽的部分会写进相邻字段，因此再按位宽校验一次
+        Self::check_fits_bits("datacenter_id", datacenter_id.get(), cfg.datacenter_id_bits)?;
+        Self::check_fits_bits("worker_id", worker_id.get(), cfg.worker_id_bits)?;
+        let environment = Self::resolve_environment(&cfg)?;
+        Self::check_version(&cfg)?;
+        // 至少保留一个序列号给在线生成
+        if cfg.backfill_sequences >= cfg.max_sequence {
+            return Err(TinyIdError::ConfigError(format!(
+                "backfill_sequences {} must be less than max_sequence {}",
+                cfg.backfill_sequences, cfg.max_sequence
+            ))
+            .into());
         }
+        let seeded_rng = Mutex::new(cfg.random_seed.map(StdRng::seed_from_u64));
+        let ts_seq = Self::load_state(&cfg)?;
+        // 恢复出的毫秒本身还没有预留，第一次发号时先写一次 state_file
+        let reserved_ts = if cfg.state_file.is_some() {
+            ts_seq >> cfg.sequence_bits
+        } else {
+            u64::MAX
+        };
 
         Ok(Self {
             cfg,
-            ts_seq: AtomicU64::new(0),
+            worker_id,
+            datacenter_id,
+            environment,
+            ts_seq: AtomicU64::new(ts_seq),
+            reserved_ts: AtomicU64::new(reserved_ts),
+            reserve_lock: Mutex::new(()),
             start_time: SystemTime::now(),
-            total_generated: AtomicU64::new(0),
+            total_generated: Arc::new(AtomicU64::new(0)),
+            random_generated: AtomicU64::new(0),
+            clock_backwards: AtomicU64::new(0),
+            seeded_rng,
+            cas_retries: Arc::new(AtomicU64::new(0)),
+            sequence_waits: Arc::new(AtomicU64::new(0)),
+            clock: system_clock(),
+            auditor: None,
         })
     }
 
+    /// 校验节点ID能放进对应位宽，位宽为 0 时只允许 0
+    fn check_fits_bits(name: &str, id: u32, bits: u32) -> Result<()> {
+        if u64::from(id) >> bits != 0 {
+            return Err(anyhow::anyhow!(
+                "{} {} does not fit in {} bits",
+                name,
+                id,
+                bits
+            ));
+        }
+        Ok(())
+    }
+
+    /// 校验环境标识位配置，未启用时返回 None
+    fn resolve_environment(cfg: &IdGeneratorConfig) -> Result<Option<Environment>> {
+        if cfg.environment_bits == 0 {
+            return Ok(None);
+        }
+        let total = Self::total_bits(cfg);
+        if total > 63 {
+            return Err(anyhow::anyhow!(
+                "total bits {} exceeds 63 with environment_bits {}",
+                total,
+                cfg.environment_bits
+            ));
+        }
+
+        let environment = match cfg.environment {
+            Some(environment) => environment,
+            None => Environment::from_env()?,
+        };
+        if environment.code() >> cfg.environment_bits != 0 {
+            return Err(anyhow::anyhow!(
+                "environment_bits {} is too small for {:?}",
+                cfg.environment_bits,
+                environment
+            ));
+        }
+        Ok(Some(environment))
+    }
+
+    /// 校验格式版本号配置：版本号要能放进 `version_bits`，启用后总位数不超过 63
+    fn check_version(cfg: &IdGeneratorConfig) -> Result<()> {
+        Self::check_fits_bits("version", cfg.version, cfg.version_bits)?;
+        let total = Self::total_bits(cfg);
+        if cfg.version_bits > 0 && total > 63 {
+            return Err(anyhow::anyhow!(
+                "total bits {} exceeds 63 with version_bits {}",
+                total,
+                cfg.version_bits
+            ));
+        }
+        Ok(())
+    }
+
+    /// 按 u64 求和，配置中任意大的位宽也不会溢出
+    fn total_bits(cfg: &IdGeneratorConfig) -> u64 {
+        [
+            cfg.version_bits,
+            cfg.timestamp_bits,
+            cfg.environment_bits,
+            cfg.datacenter_id_bits,
+            cfg.worker_id_bits,
+            cfg.sequence_bits,
+        ]
+        .into_iter()
+        .map(u64::from)
+        .sum()
+    }
+
+    /// 不启动服务完整校验配置，通过时返回生效的位布局及推导值，否则返回全部问题
+    ///
+    /// 在 `new` 的校验之外，还检查总位数、`max_*` 是否与位宽一致以及 epoch 是否合理
+    /// （不在未来、时间戳位尚未用尽），供部署前的 `validate-config` 使用。
+    pub fn validate_config(
+        cfg: &IdGeneratorConfig,
+        now_millis: u64,
+    ) -> Result<LayoutInfo, Vec<String>> {
+        let cfg = cfg.clone().resolve_layout();
+        let total = Self::total_bits(&cfg);
+        if total > 63 {
+            return Err(vec![format!("total bits {} exceeds 63", total)]);
+        }
+
+        let mut errors = Vec::new();
+        let mask = |bits: u32| (1u64 << bits) - 1;
+        for (name, max, bits) in [
+            ("max_sequence", cfg.max_sequence, cfg.sequence_bits),
+            ("max_worker_id", cfg.max_worker_id, cfg.worker_id_bits),
+            (
+                "max_datacenter_id",
+                cfg.max_datacenter_id,
+                cfg.datacenter_id_bits,
+            ),
+        ] {
+            if u64::from(max) != mask(bits) {
+                errors.push(format!(
+                    "{} {} does not match {} bits (expected {})",
+                    name,
+                    max,
+                    bits,
+                    mask(bits)
+                ));
+            }
+        }
+        // 位宽问题 `new` 会再报一次，已报告时不再调用
+        let fits_bits = errors.is_empty();
+        if cfg.epoch > now_millis {
+            errors.push(format!("epoch {} is in the future", cfg.epoch));
+        } else if now_millis - cfg.epoch > mask(cfg.timestamp_bits) {
+            errors.push(format!(
+                "timestamp_bits {} already exhausted since epoch {}",
+                cfg.timestamp_bits, cfg.epoch
+            ));
+        }
+
+        if !fits_bits {
+            return Err(errors);
+        }
+        match Self::new(cfg) {
+            Ok(generator) if errors.is_empty() => Ok(generator.layout_info()),
+            Ok(_) => Err(errors),
+            Err(e) => {
+                errors.push(e.to_string());
+                Err(errors)
+            }
+        }
+    }
+
+    /// 读取 `state_file` 中的高水位并打包成 `ts_seq`，文件不存在（首次启动）时从 0 开始
+    fn load_state(cfg: &IdGeneratorConfig) -> Result<u64> {
+        let Some(path) = &cfg.state_file else {
+            return Ok(0);
+        };
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(anyhow::anyhow!("failed to read state file {}: {}", path, e)),
+        };
+        let state: PersistedState = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("invalid state file {}: {}", path, e))?;
+
+        let seq_mask = (1u64 << cfg.sequence_bits) - 1;
+        if state.timestamp < cfg.epoch || state.next_sequence > seq_mask {
+            return Err(anyhow::anyhow!(
+                "state file {} does not match the configured layout: {:?}",
+                path,
+                state
+            ));
+        }
+        info!(
+            path = %path,
+            timestamp = state.timestamp,
+            next_sequence = state.next_sequence,
+            "Restored generator high-water mark"
+        );
+        Ok(((state.timestamp - cfg.epoch) << cfg.sequence_bits) | state.next_sequence)
+    }
+
+    /// 提前推进预留上限，配置了 `state_file` 时由后台任务按 `state_persist_interval_ms` 调用
+    ///
+    /// 保证预留至少覆盖到下一次调用，发号路径一般不需要自己写文件；尚未生成过ID时跳过。
+    pub fn save_state(&self) -> Result<(), TinyIdError> {
+        if self.cfg.state_file.is_none() {
+            return Ok(());
+        }
+        let cur = self.ts_seq.load(Ordering::Acquire);
+        if cur == 0 || cur == RETIRED {
+            return Ok(());
+        }
+        let cur_ts = cur >> self.cfg.sequence_bits;
+        let now = self.get_current_timestamp().unwrap_or(cur_ts);
+        self.ensure_reserved(cur_ts.max(now) + self.reserve_window())
+    }
+
+    /// 每次推进预留的时长，与持久化间隔相同
+    fn reserve_window(&self) -> u64 {
+        self.cfg.state_persist_interval_ms.max(1)
+    }
+
+    /// 发出时间戳为 `ts` 的ID之前调用：`ts` 不在预留范围内时先推进预留并写入 `state_file`
+    ///
+    /// 进程在任意时刻崩溃，重启后都从文件中的预留上限继续，不会与已发出的ID重复。
+    fn ensure_reserved(&self, ts: u64) -> Result<(), TinyIdError> {
+        if ts < self.reserved_ts.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let _guard = self.reserve_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if ts < self.reserved_ts.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let reserved = ts + self.reserve_window();
+        self.write_state(&PersistedState {
+            timestamp: reserved + self.cfg.epoch,
+            next_sequence: 0,
+        })?;
+        self.reserved_ts.store(reserved, Ordering::Release);
+        Ok(())
+    }
+
+    /// 先写临时文件并 fsync 再 rename，进程在写入中途退出也不会留下半个文件
+    fn write_state(&self, state: &PersistedState) -> Result<(), TinyIdError> {
+        use std::io::Write as _;
+
+        let Some(path) = &self.cfg.state_file else {
+            return Ok(());
+        };
+        let content = serde_json::to_vec(state)
+            .map_err(|e| TinyIdError::InternalError(format!("serialize state: {}", e)))?;
+
+        let tmp = format!("{}.tmp", path);
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(&content)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// 使用外部计数器统计 CAS 重试（如 `AppMetrics::cas_retries`）
+    pub fn with_cas_retry_counter(mut self, counter: Arc<AtomicU64>) -> Self {
+        self.cas_retries = counter;
+        self
+    }
+
+    /// 使用外部计数器统计序列耗尽和时钟回拨时的等待次数（如 `AppMetrics::sequence_waits`）
+    pub fn with_sequence_wait_counter(mut self, counter: Arc<AtomicU64>) -> Self {
+        self.sequence_waits = counter;
+        self
+    }
+
+    /// 替换时间源
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 按毫秒记录发出的ID区间，时钟不可用时的随机ID不是区间，不记录
+    pub fn with_auditor(mut self, auditor: Arc<IdAuditor>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    /// 记录同一毫秒内发出的序列号区间 [start_seq, end_seq]，`timestamp` 为相对 epoch 的毫秒数
+    fn audit(&self, timestamp: u64, start_seq: u64, end_seq: u64) {
+        if let Some(auditor) = &self.auditor {
+            auditor.record(IdRange {
+                worker_id: self.worker_id.get(),
+                datacenter_id: self.datacenter_id.get(),
+                timestamp: timestamp + self.cfg.epoch,
+                start_seq,
+                end_seq,
+            });
+        }
+    }
+
+    /// 累加生成计数，批量模式下先记在线程本地
+    fn record_generated(&self, count: u64) {
+        let batch = self.cfg.total_generated_batch as u64;
+        if batch <= 1 {
+            self.total_generated.fetch_add(count, Ordering::Relaxed);
+            return;
+        }
+        let recorded = PENDING_GENERATED.try_with(|pending| {
+            pending
+                .borrow_mut()
+                .add(&self.total_generated, count, batch)
+        });
+        // 线程正在退出，本地存储已销毁
+        if recorded.is_err() {
+            self.total_generated.fetch_add(count, Ordering::Relaxed);
+        }
+    }
+
+    /// CAS 失败重试总数
+    pub fn cas_retries(&self) -> u64 {
+        self.cas_retries.load(Ordering::Relaxed)
+    }
+
+    /// 序列号耗尽或时钟回拨时等待的次数，持续增长说明单个节点接近理论吞吐上限
+    pub fn sequence_waits(&self) -> u64 {
+        self.sequence_waits.load(Ordering::Relaxed)
+    }
+
+    /// 记录一次等待，每次进入 `BACKOFF_INTERVAL` 休眠前调用
+    fn record_sequence_wait(&self) {
+        self.sequence_waits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 累计一次调用的 CAS 重试次数，超过阈值时打印警告
+    fn record_cas_retries(&self, retries: u64) {
+        if retries == 0 {
+            return;
+        }
+        self.cas_retries.fetch_add(retries, Ordering::Relaxed);
+        if retries > CAS_RETRY_WARN_THRESHOLD {
+            warn!(retries, "High CAS retry count in a single generate call");
+        }
+    }
+
     #[instrument(skip(self))]
     pub fn next_id(&self) -> Result<u64, TinyIdError> {
         self.generate_id()
     }
 
+    /// 与 `next_id` 相同，同时返回写入ID的时间戳、序列号和节点ID，用于审计日志
+    #[instrument(skip(self))]
+    pub fn next_id_detailed(&self) -> Result<GeneratedId, TinyIdError> {
+        let id = self.generate_id()?;
+        let (timestamp_ms, sequence) = self.parse_id(id);
+        Ok(GeneratedId {
+            id,
+            timestamp_ms,
+            sequence,
+            worker_id: self.worker_id.get(),
+            datacenter_id: self.datacenter_id.get(),
+        })
+    }
+
+    /// 与 `next_id` 相同，但时钟回拨或序列耗尽需要等待时，超过 deadline 即返回错误
+    #[instrument(skip(self))]
+    pub fn next_id_before(&self, deadline: Option<Instant>) -> Result<u64, TinyIdError> {
+        self.generate_id_before(deadline)
+    }
+
+    /// 与 `next_id` 相同，但需要等待时让出 tokio 工作线程而不是阻塞线程
+    #[instrument(skip(self))]
+    pub async fn next_id_async(&self) -> Result<u64, TinyIdError> {
+        self.next_id_before_async(None).await
+    }
+
+    /// 与 `next_id_before` 相同，但需要等待时让出 tokio 工作线程而不是阻塞线程
+    #[instrument(skip(self))]
+    pub async fn next_id_before_async(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Result<u64, TinyIdError> {
+        let mut retry = RetryState::default();
+        loop {
+            if let Some(id) = self.try_generate_id(&mut retry, deadline)? {
+                return Ok(id);
+            }
+            self.record_sequence_wait();
+            tokio::time::sleep(BACKOFF_INTERVAL).await;
+        }
+    }
+
     fn generate_id(&self) -> Result<u64, TinyIdError> {
-        let seq_bits = self.cfg.sequence_bits as u32;
+        self.generate_id_before(None)
+    }
+
+    /// 已超过 deadline 时返回错误
+    fn check_deadline(deadline: Option<Instant>) -> Result<(), TinyIdError> {
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(TinyIdError::DeadlineExceeded);
+        }
+        Ok(())
+    }
+
+    /// 等待下一轮重试；已超过 deadline 时不再等待
+    fn backoff(deadline: Option<Instant>) -> Result<(), TinyIdError> {
+        Self::check_deadline(deadline)?;
+        std::thread::sleep(BACKOFF_INTERVAL);
+        Ok(())
+    }
+
+    /// 时钟回拨时检查还能否继续等待，回拨幅度或累计等待超过 `max_backwards_wait_ms` 时放弃
+    fn check_backwards_wait(
+        &self,
+        backwards: u64,
+        waiting_since: &mut Option<Instant>,
+        deadline: Option<Instant>,
+    ) -> Result<(), TinyIdError> {
+        let max_wait = self.cfg.max_backwards_wait_ms;
+        if max_wait > 0 {
+            let since = *waiting_since.get_or_insert_with(Instant::now);
+            if backwards > max_wait || since.elapsed() >= Duration::from_millis(max_wait) {
+                return Err(TinyIdError::ClockMovedBackwards(backwards));
+            }
+        }
+        Self::check_deadline(deadline)
+    }
+
+    /// 按 `clock_rollback` 处理一次时钟回拨
+    ///
+    /// 返回 true 表示沿用逻辑时钟继续分配，false 表示需要等待一轮后重新读取时钟。
+    fn handle_backwards(
+        &self,
+        backwards: u64,
+        waiting_since: &mut Option<Instant>,
+        deadline: Option<Instant>,
+    ) -> Result<bool, TinyIdError> {
+        match self.cfg.clock_rollback {
+            ClockRollbackPolicy::Wait => {
+                warn!("Clock moved backwards by {}ms, waiting", backwards);
+                self.check_backwards_wait(backwards, waiting_since, deadline)?;
+                Ok(false)
+            }
+            ClockRollbackPolicy::Error => {
+                warn!("Clock moved backwards by {}ms, rejecting", backwards);
+                Err(TinyIdError::ClockMovedBackwards(backwards))
+            }
+            ClockRollbackPolicy::Borrow => {
+                debug!("Clock moved backwards by {}ms, borrowing", backwards);
+                Ok(true)
+            }
+        }
+    }
+
+    fn generate_id_before(&self, deadline: Option<Instant>) -> Result<u64, TinyIdError> {
+        let mut retry = RetryState::default();
+        loop {
+            if let Some(id) = self.try_generate_id(&mut retry, deadline)? {
+                return Ok(id);
+            }
+            self.record_sequence_wait();
+            std::thread::sleep(BACKOFF_INTERVAL);
+        }
+    }
+
+    /// 尝试分配一个ID，CAS 冲突时立即重试；返回 None 表示需要等待一轮（序列耗尽或时钟回拨），
+    /// 由调用方决定阻塞线程还是让出异步任务
+    fn try_generate_id(
+        &self,
+        retry: &mut RetryState,
+        deadline: Option<Instant>,
+    ) -> Result<Option<u64>, TinyIdError> {
+        let seq_bits = self.cfg.sequence_bits;
         let seq_mask: u64 = (1u64 << self.cfg.sequence_bits) - 1;
-        let max_seq: u64 = self.cfg.max_sequence as u64;
+        let max_seq = self.live_max_sequence();
 
         loop {
-            let now = self.get_current_timestamp()?;
+            let now = match self.get_current_timestamp() {
+                Ok(now) => now,
+                Err(e) => return self.random_fallback(1, e).map(|ids| Some(ids[0])),
+            };
 
             let cur = self.ts_seq.load(Ordering::Acquire);
+            if cur == RETIRED {
+                return Err(TinyIdError::GeneratorRetired);
+            }
             let cur_ts = cur >> seq_bits;
             let cur_seq = cur & seq_mask;
 
             // 回拨
+            let mut borrowing = false;
             if now < cur_ts {
                 let backwards = cur_ts - now;
-                warn!("Clock moved backwards by {}ms, waiting", backwards);
-                std::thread::sleep(Duration::from_micros(200));
-                continue;
+                if !retry.backwards_seen {
+                    retry.backwards_seen = true;
+                    self.clock_backwards.fetch_add(1, Ordering::Relaxed);
+                }
+                if !self.handle_backwards(backwards, &mut retry.backwards_since, deadline)? {
+                    return Ok(None);
+                }
+                borrowing = true;
             }
 
-            if now == cur_ts {
-                // 同毫秒：CAS递增，不允许在同毫秒内序列回绕
-                if cur_seq >= max_seq {
-                    std::thread::sleep(Duration::from_micros(200));
-                    continue;
-                }
+            let ts = if now > cur_ts {
+                now
+            } else if cur_seq < max_seq {
+                // 同毫秒：CAS递增
+                self.ensure_reserved(cur_ts)?;
                 let next = (cur_ts << seq_bits) | (cur_seq + 1);
-                if let Ok(_) = self.ts_seq.compare_exchange_weak(
-                    cur,
-                    next,
-                    Ordering::AcqRel,
-                    Ordering::Acquire,
-                ) {
-                    let id = self.assemble_id(now, cur_seq as u32);
-                    self.total_generated.fetch_add(1, Ordering::Relaxed);
-                    return Ok(id);
+                if self
+                    .ts_seq
+                    .compare_exchange_weak(cur, next, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+                {
+                    let id = self.assemble_id(cur_ts, cur_seq as u32);
+                    self.audit(cur_ts, cur_seq, cur_seq);
+                    self.record_generated(1);
+                    self.record_cas_retries(retry.cas_retries);
+                    return Ok(Some(id));
                 }
+                retry.cas_retries += 1;
                 continue;
-            }
+            } else if borrowing {
+                // 借用逻辑时钟时序列用尽，直接占用下一毫秒
+                cur_ts + 1
+            } else {
+                // 不允许在同毫秒内序列回绕，等待下一毫秒
+                Self::check_deadline(deadline)?;
+                return Ok(None);
+            };
 
             // 新毫秒：切换到新毫秒并分配首个序列0
-            let next = (now << seq_bits) | 1; // 存1，返回0
-            if let Ok(_) =
-                self.ts_seq
-                    .compare_exchange(cur, next, Ordering::AcqRel, Ordering::Acquire)
+            self.ensure_reserved(ts)?;
+            let next = (ts << seq_bits) | 1; // 存1，返回0
+            if self
+                .ts_seq
+                .compare_exchange(cur, next, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
             {
-                let id = self.assemble_id(now, 0);
-                self.total_generated.fetch_add(1, Ordering::Relaxed);
-                return Ok(id);
+                let id = self.assemble_id(ts, 0);
+                self.audit(ts, 0, 0);
+                self.record_generated(1);
+                self.record_cas_retries(retry.cas_retries);
+                return Ok(Some(id));
             }
             // 失败则重试
+            retry.cas_retries += 1;
         }
     }
 
     /// 批量生成 count 个ID，采用CAS一次性预留序列区间，避免锁和逐个申请的开销
+    ///
+    /// 中途失败（时钟回拨等待超时、超过 deadline）时返回错误，已预留的ID直接丢弃，不返回部分结果。
     pub fn generate_ids_batch(&self, count: usize) -> Result<Vec<u64>, TinyIdError> {
-        let seq_bits = self.cfg.sequence_bits as u32;
+        self.generate_ids_batch_before(count, None)
+    }
+
+    /// 与 `generate_ids_batch` 相同，但等待重试时会检查 deadline
+    ///
+    /// span 上记录 CAS 失败重试次数 `cas_retries` 和跨越的毫秒数 `millis_spanned`，用于分析争用。
+    #[instrument(skip(self), fields(cas_retries = Empty, millis_spanned = Empty))]
+    pub fn generate_ids_batch_before(
+        &self,
+        count: usize,
+        deadline: Option<Instant>,
+    ) -> Result<Vec<u64>, TinyIdError> {
+        let seq_bits = self.cfg.sequence_bits;
         let seq_mask: u64 = (1u64 << self.cfg.sequence_bits) - 1;
-        let max_seq: u64 = self.cfg.max_sequence as u64;
+        let max_seq = self.live_max_sequence();
 
         let mut remaining: u64 = count as u64;
         let mut result = Vec::with_capacity(count);
+        let mut cas_retries: u64 = 0;
+        let mut backwards_seen = false;
+        let mut backwards_since = None;
+        let mut ts_range: Option<(u64, u64)> = None;
 
         while remaining > 0 {
-            let now = self.get_current_timestamp()?;
+            let now = match self.get_current_timestamp() {
+                Ok(now) => now,
+                Err(e) => {
+                    result.extend(self.random_fallback(remaining as usize, e)?);
+                    break;
+                }
+            };
             let cur = self.ts_seq.load(Ordering::Acquire);
+            if cur == RETIRED {
+                return Err(TinyIdError::GeneratorRetired);
+            }
             let cur_ts = cur >> seq_bits;
             let cur_seq = cur & seq_mask; // 已分配数量（下一序列号）
 
             // 时钟回拨
+            let mut borrowing = false;
             if now < cur_ts {
                 let backwards = cur_ts - now;
-                warn!("Clock moved backwards by {}ms, waiting", backwards);
-                std::thread::sleep(Duration::from_micros(200));
-                continue;
+                if !backwards_seen {
+                    backwards_seen = true;
+                    self.clock_backwards.fetch_add(1, Ordering::Relaxed);
+                }
+                if !self.handle_backwards(backwards, &mut backwards_since, deadline)? {
+                    self.record_sequence_wait();
+                    std::thread::sleep(BACKOFF_INTERVAL);
+                    continue;
+                }
+                borrowing = true;
             }
 
-            if now == cur_ts {
-                let available = if cur_seq >= max_seq {
-                    0
-                } else {
-                    max_seq - cur_seq
-                };
-                if available == 0 {
+            if now <= cur_ts {
+                let available = max_seq.saturating_sub(cur_seq);
+                if available > 0 {
+                    self.ensure_reserved(cur_ts)?;
+                    let take = remaining.min(available);
+                    let new_seq = cur_seq + take; // 预留 [cur_seq, new_seq)
+                    let next = (cur_ts << seq_bits) | new_seq;
+                    if self
+                        .ts_seq
+                        .compare_exchange_weak(cur, next, Ordering::AcqRel, Ordering::Acquire)
+                        .is_ok()
+                    {
+                        ts_range =
+                            Some(ts_range.map_or((cur_ts, cur_ts), |(first, _)| (first, cur_ts)));
+                        for s in cur_seq..new_seq {
+                            result.push(self.assemble_id(cur_ts, s as u32));
+                        }
+                        self.audit(cur_ts, cur_seq, new_seq - 1);
+                        self.record_generated(take);
+                        remaining -= take;
+                    } else {
+                        cas_retries += 1;
+                    }
+                    continue;
+                }
+                if !borrowing {
                     // 当前毫秒可用序列已满，等待下一毫秒
-                    std::thread::sleep(Duration::from_micros(200));
+                    self.record_sequence_wait();
+                    Self::backoff(deadline)?;
                     continue;
                 }
-                let take = remaining.min(available);
-                let new_seq = cur_seq + take; // 预留 [cur_seq, new_seq)
-                let next = (cur_ts << seq_bits) | new_seq;
-                if let Ok(_) = self.ts_seq.compare_exchange_weak(
-                    cur,
-                    next,
-                    Ordering::AcqRel,
-                    Ordering::Acquire,
-                ) {
-                    for s in cur_seq..new_seq {
-                        result.push(self.assemble_id(now, s as u32));
-                    }
-                    self.total_generated
-                        .fetch_add(take as u64, Ordering::Relaxed);
-                    remaining -= take;
+            }
+
+            // 切换到新毫秒：一次性预留一段；借用逻辑时钟时为最后一个毫秒的下一毫秒
+            let ts = if now > cur_ts { now } else { cur_ts + 1 };
+            self.ensure_reserved(ts)?;
+            // 与同毫秒分支一致只用到 max_seq - 1，存储的下一序列号不超过 max_seq，
+            // 不会进位到时间戳位
+            let take = remaining.min(max_seq);
+            let new_seq = take; // 存储为下一序列号
+            let next = (ts << seq_bits) | new_seq;
+            if self
+                .ts_seq
+                .compare_exchange(cur, next, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                ts_range = Some(ts_range.map_or((ts, ts), |(first, _)| (first, ts)));
+                for s in 0..take {
+                    result.push(self.assemble_id(ts, s as u32));
                 }
+                self.audit(ts, 0, take - 1);
+                self.record_generated(take);
+                remaining -= take;
             } else {
-                // 切换到新毫秒：一次性预留一段
-                let avail = max_seq + 1; // 该毫秒可用的总数 [0..=max_seq]
-                let take = remaining.min(avail);
-                let new_seq = take; // 存储为下一序列号
-                let next = (now << seq_bits) | new_seq;
-                if let Ok(_) =
-                    self.ts_seq
-                        .compare_exchange(cur, next, Ordering::AcqRel, Ordering::Acquire)
-                {
-                    for s in 0..take {
-                        result.push(self.assemble_id(now, s as u32));
-                    }
-                    self.total_generated
-                        .fetch_add(take as u64, Ordering::Relaxed);
-                    remaining -= take;
-                }
+                cas_retries += 1;
             }
         }
 
+        self.record_cas_retries(cas_retries);
+        let span = Span::current();
+        span.record("cas_retries", cas_retries);
+        span.record(
+            "millis_spanned",
+            ts_range.map_or(0, |(first, last)| last - first + 1),
+        );
+
         Ok(result)
     }
 
+    /// 在线生成可用的最大序列号，其上的序列号保留给补录
+    fn live_max_sequence(&self) -> u64 {
+        (self.cfg.max_sequence - self.cfg.backfill_sequences) as u64
+    }
+
+    /// 为过去的某个时刻手动补录一个ID
+    ///
+    /// `timestamp` 为 Unix 毫秒，`sequence` 为保留段内的偏移（0..backfill_sequences），
+    /// 使用本实例的数据中心/工作节点ID。补录ID只落在保留段，不会与在线生成冲突，
+    /// 但同一时刻、同一偏移重复补录会得到相同的ID，需要调用方自行分配偏移。
+    pub fn mint_backfill_id(&self, timestamp: u64, sequence: u32) -> Result<u64, TinyIdError> {
+        if self.cfg.backfill_sequences == 0 {
+            return Err(TinyIdError::InvalidRequest(
+                "backfill is disabled (id_generator.backfill_sequences = 0)".to_string(),
+            ));
+        }
+        if sequence >= self.cfg.backfill_sequences {
+            return Err(TinyIdError::InvalidRequest(format!(
+                "backfill sequence {} must be less than {}",
+                sequence, self.cfg.backfill_sequences
+            )));
+        }
+        if timestamp < self.cfg.epoch {
+            return Err(TinyIdError::InvalidRequest(format!(
+                "timestamp {}ms is before epoch {}ms",
+                timestamp, self.cfg.epoch
+            )));
+        }
+        if timestamp > self.clock.now_millis()? {
+            return Err(TinyIdError::InvalidRequest(format!(
+                "timestamp {}ms is in the future",
+                timestamp
+            )));
+        }
+        let offset = timestamp - self.cfg.epoch;
+        if offset >> self.cfg.timestamp_bits != 0 {
+            return Err(TinyIdError::InvalidRequest(format!(
+                "timestamp {}ms exceeds {} timestamp bits",
+                timestamp, self.cfg.timestamp_bits
+            )));
+        }
+        let sequence = self.live_max_sequence() as u32 + 1 + sequence;
+        self.audit(offset, sequence as u64, sequence as u64);
+        Ok(self.assemble_id(offset, sequence))
+    }
+
     fn get_current_timestamp(&self) -> Result<u64, TinyIdError> {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| TinyIdError::InternalError(e.to_string()))?;
+        let timestamp = self.clock.now_millis()?;
+        // 早于 epoch 说明系统时钟不可信，继续生成会得到错误的时间戳
+        if timestamp < self.cfg.epoch {
+            return Err(TinyIdError::InternalError(format!(
+                "system clock {}ms is before epoch {}ms",
+                timestamp, self.cfg.epoch
+            )));
+        }
+        Ok(timestamp - self.cfg.epoch)
+    }
 
-        let timestamp = now.as_millis() as u64;
-        Ok(timestamp.saturating_sub(self.cfg.epoch))
+    /// 时钟不可用时的兜底
+    ///
+    /// 开启 `random_fallback_on_clock_error` 后返回 63 位随机ID，否则原样返回错误。
+    /// 随机ID不含时间戳，不保证时间有序，只能尽力保证唯一。
+    fn random_fallback(&self, count: usize, err: TinyIdError) -> Result<Vec<u64>, TinyIdError> {
+        if !self.cfg.random_fallback_on_clock_error {
+            return Err(err);
+        }
+        warn!(error = %err, count, "Clock unavailable, falling back to random non-sortable ids");
+
+        let ids = self.random_ids(count);
+        self.random_generated
+            .fetch_add(count as u64, Ordering::Relaxed);
+        self.record_generated(count as u64);
+        Ok(ids)
+    }
+
+    /// 生成 count 个 63 位随机数，配置了种子时结果可复现
+    fn random_ids(&self, count: usize) -> Vec<u64> {
+        let mut seeded = self.seeded_rng.lock().unwrap_or_else(|e| e.into_inner());
+        let draw = |rng: &mut dyn rand::RngCore| {
+            (0..count)
+                .map(|_| rng.random::<u64>() & (i64::MAX as u64))
+                .collect()
+        };
+        match seeded.as_mut() {
+            Some(rng) => draw(rng),
+            None => draw(&mut rand::rng()),
+        }
+    }
+
+    /// 累计生成的ID数量；`total_generated_batch` > 1 时各线程未写入的部分不计入
+    pub fn total_generated(&self) -> u64 {
+        self.total_generated.load(Ordering::Relaxed)
+    }
+
+    /// 因时钟不可用而生成的随机ID数量，大于 0 说明部分ID不再时间有序
+    pub fn random_generated(&self) -> u64 {
+        self.random_generated.load(Ordering::Relaxed)
+    }
+
+    pub fn worker_id(&self) -> WorkerId {
+        self.worker_id
+    }
+
+    pub fn datacenter_id(&self) -> DatacenterId {
+        self.datacenter_id
+    }
+
+    /// 时间戳位用尽的时刻，之后生成的ID会溢出
+    pub fn exhaustion_time(&self) -> Option<DateTime<Utc>> {
+        let max_offset = (1u64 << self.cfg.timestamp_bits) - 1;
+        let millis = i64::try_from(self.cfg.epoch.checked_add(max_offset)?).ok()?;
+        Utc.timestamp_millis_opt(millis).single()
+    }
+
+    /// 当前配置下理论上每秒最多可生成的ID数量
+    ///
+    /// 在线生成只用到序列号 `0..live_max_sequence()`，每毫秒 `live_max_sequence()` 个。
+    pub fn capacity_per_second(&self) -> u64 {
+        self.live_max_sequence() * (1000 / TIME_UNIT_MS)
+    }
+
+    /// 生效的位布局及推导值
+    pub fn layout_info(&self) -> LayoutInfo {
+        let cfg = &self.cfg;
+        LayoutInfo {
+            timestamp_bits: cfg.timestamp_bits,
+            environment_bits: cfg.environment_bits,
+            datacenter_id_bits: cfg.datacenter_id_bits,
+            worker_id_bits: cfg.worker_id_bits,
+            sequence_bits: cfg.sequence_bits,
+            max_datacenter_id: cfg.max_datacenter_id,
+            max_worker_id: cfg.max_worker_id,
+            max_sequence: cfg.max_sequence,
+            backfill_sequences: cfg.backfill_sequences,
+            version_bits: cfg.version_bits,
+            version: cfg.version,
+            datacenter_id: self.datacenter_id.get(),
+            worker_id: self.worker_id.get(),
+            environment: self.environment,
+            epoch: cfg.epoch,
+            exhausted_at: self.exhaustion_time().map(|t| t.to_rfc3339()),
+            capacity_per_second: self.capacity_per_second(),
+        }
+    }
+
+    /// 运行统计
+    pub fn stats(&self) -> GeneratorStats {
+        GeneratorStats {
+            total_generated: self.total_generated(),
+            random_generated: self.random_generated(),
+            cas_retries: self.cas_retries(),
+            clock_backwards: self.clock_backwards.load(Ordering::Relaxed),
+            sequence_waits: self.sequence_waits(),
+            uptime_seconds: self.start_time.elapsed().map_or(0, |d| d.as_secs()),
+            capacity_per_second: self.capacity_per_second(),
+            worker_id: self.worker_id.get(),
+            datacenter_id: self.datacenter_id.get(),
+        }
+    }
+
+    /// 启动时输出一条生效位布局的摘要，包含到期时间等推导值，便于运维核对配置
+    pub fn log_startup_summary(&self) {
+        let cfg = &self.cfg;
+        let rfc3339 =
+            |t: Option<DateTime<Utc>>| t.map_or_else(|| "invalid".to_string(), |t| t.to_rfc3339());
+        let epoch = i64::try_from(cfg.epoch)
+            .ok()
+            .and_then(|ms| Utc.timestamp_millis_opt(ms).single());
+
+        info!(
+            layout = %format!(
+                "{}-{}-{}-{}",
+                cfg.timestamp_bits, cfg.datacenter_id_bits, cfg.worker_id_bits, cfg.sequence_bits
+            ),
+            environment_bits = cfg.environment_bits,
+            environment = ?self.environment,
+            version_bits = cfg.version_bits,
+            version = cfg.version,
+            datacenter_id = self.datacenter_id.get(),
+            worker_id = self.worker_id.get(),
+            max_datacenter_id = cfg.max_datacenter_id,
+            max_worker_id = cfg.max_worker_id,
+            max_sequence = cfg.max_sequence,
+            epoch = %rfc3339(epoch),
+            exhausted_at = %rfc3339(self.exhaustion_time()),
+            capacity_per_second = self.capacity_per_second(),
+            random_fallback_on_clock_error = cfg.random_fallback_on_clock_error,
+            "ID generator configured"
+        );
+    }
+
+    /// 停用本实例并构造使用新工作节点ID的生成器，用于运行时切换 worker id
+    ///
+    /// 先把 `ts_seq` 换成停用标记，此后本实例的生成调用都返回 `GeneratorRetired`，
+    /// 换出的值就是旧 worker id 最终发出的高水位。随后异步等待时钟越过该毫秒，
+    /// 新生成器从这个状态继续，保证切换前后不会在同一毫秒内出现两个 worker id 的序列。
+    /// 等待失败（超过 deadline、时钟不可用）时恢复本实例，可以继续使用。
+    pub async fn retire_into_worker_id(
+        &self,
+        worker_id: u32,
+        deadline: Option<Instant>,
+    ) -> Result<IDGenerator, TinyIdError> {
+        let worker_id = WorkerId::new(worker_id, self.cfg.max_worker_id)
+            .map_err(|_| TinyIdError::InvalidWorkerId(worker_id))?;
+
+        let state = self.ts_seq.swap(RETIRED, Ordering::AcqRel);
+        if state == RETIRED {
+            return Err(TinyIdError::GeneratorRetired);
+        }
+        if let Err(e) = self
+            .wait_past(state >> self.cfg.sequence_bits, deadline)
+            .await
+        {
+            self.ts_seq.store(state, Ordering::Release);
+            return Err(e);
+        }
+
+        let mut cfg = self.cfg.clone();
+        cfg.worker_id = worker_id;
+        Ok(Self {
+            cfg,
+            worker_id,
+            datacenter_id: self.datacenter_id,
+            environment: self.environment,
+            ts_seq: AtomicU64::new(state),
+            reserved_ts: AtomicU64::new(self.reserved_ts.load(Ordering::Acquire)),
+            reserve_lock: Mutex::new(()),
+            start_time: self.start_time,
+            // 共享计数器，各线程未写入的批量计数仍会累加到这里
+            total_generated: Arc::clone(&self.total_generated),
+            random_generated: AtomicU64::new(self.random_generated.load(Ordering::Relaxed)),
+            clock_backwards: AtomicU64::new(self.clock_backwards.load(Ordering::Relaxed)),
+            seeded_rng: Mutex::new(
+                self.seeded_rng
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .clone(),
+            ),
+            cas_retries: Arc::clone(&self.cas_retries),
+            sequence_waits: Arc::clone(&self.sequence_waits),
+            clock: Arc::clone(&self.clock),
+            auditor: self.auditor.clone(),
+        })
+    }
+
+    /// 让出 tokio 工作线程，直到时钟越过 `last_ts`
+    async fn wait_past(&self, last_ts: u64, deadline: Option<Instant>) -> Result<(), TinyIdError> {
+        while self.get_current_timestamp()? <= last_ts {
+            Self::check_deadline(deadline)?;
+            tokio::time::sleep(BACKOFF_INTERVAL).await;
+        }
+        Ok(())
     }
 
     fn assemble_id(&self, timestamp: u64, sequence: u32) -> u64 {
-        let timestamp_shift =
+        let environment_shift =
             self.cfg.datacenter_id_bits + self.cfg.worker_id_bits + self.cfg.sequence_bits;
+        let timestamp_shift = environment_shift + self.cfg.environment_bits;
         let datacenter_id_shift = self.cfg.worker_id_bits + self.cfg.sequence_bits;
         let worker_id_shift = self.cfg.sequence_bits;
-
-        timestamp << timestamp_shift
-            | (self.cfg.datacenter_id as u64) << datacenter_id_shift
-            | (self.cfg.worker_id as u64) << worker_id_shift
+        let version_shift = timestamp_shift + self.cfg.timestamp_bits;
+        let environment = self.environment.map_or(0, Environment::code);
+
+        (self.cfg.version as u64) << version_shift
+            | timestamp << timestamp_shift
+            | environment << environment_shift
+            | (self.datacenter_id.get() as u64) << datacenter_id_shift
+            | (self.worker_id.get() as u64) << worker_id_shift
             | sequence as u64
     }
 
+    /// 按当前位布局拆解ID
+    ///
+    /// 启用环境标识位时，ID中的环境与本实例不一致会标记 `environment_mismatch`，
+    /// 例如生产环境据此拒绝在预发环境生成的ID。启用格式版本位时，
+    /// 版本号与配置不一致会标记 `version_mismatch`，此时其余字段按当前布局解读并不可信。
+    pub fn decode_id(&self, id: u64) -> DecodedId {
+        let cfg = &self.cfg;
+        let mask = |bits: u32| (1u64 << bits) - 1;
+        let sequence = id & mask(cfg.sequence_bits);
+        let worker_id = (id >> cfg.sequence_bits) & mask(cfg.worker_id_bits);
+        let datacenter_id =
+            (id >> (cfg.sequence_bits + cfg.worker_id_bits)) & mask(cfg.datacenter_id_bits);
+        let environment_shift = cfg.datacenter_id_bits + cfg.worker_id_bits + cfg.sequence_bits;
+        let environment_code = (id >> environment_shift) & mask(cfg.environment_bits);
+        let version_shift = environment_shift + cfg.environment_bits + cfg.timestamp_bits;
+        let version = (id >> version_shift) & mask(cfg.version_bits);
+        let (timestamp, _) = self.parse_id(id);
+
+        let environment = self
+            .environment
+            .and_then(|_| Environment::from_code(environment_code));
+        DecodedId {
+            id,
+            timestamp,
+            datacenter_id: datacenter_id as u32,
+            worker_id: worker_id as u32,
+            sequence: sequence as u32,
+            environment,
+            environment_mismatch: self.environment.is_some() && environment != self.environment,
+            backfill: sequence > self.live_max_sequence(),
+            version: (cfg.version_bits > 0).then_some(version as u32),
+            version_mismatch: cfg.version_bits > 0 && version != u64::from(cfg.version),
+        }
+    }
+
     fn parse_id(&self, id: u64) -> (u64, u32) {
-        let timestamp_shift =
-            self.cfg.datacenter_id_bits + self.cfg.worker_id_bits + self.cfg.sequence_bits;
+        let timestamp_shift = self.cfg.datacenter_id_bits
+            + self.cfg.worker_id_bits
+            + self.cfg.sequence_bits
+            + self.cfg.environment_bits;
 
         let timestamp = (id >> timestamp_shift) & ((1 << self.cfg.timestamp_bits) - 1);
         let sequence = id & ((1 << self.cfg.sequence_bits) - 1);
@@ -199,6 +1147,163 @@ impl IDGenerator {
     }
 }
 
+/// 持久化到 `state_file` 的生成状态
+///
+/// 写入的是预留上限：上次运行发出的ID都早于 (`timestamp`, `next_sequence`)，
+/// 重启后从这里继续。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct PersistedState {
+    /// 可用的第一个毫秒（Unix 毫秒）
+    timestamp: u64,
+    /// 该毫秒内下一个可用的序列号
+    next_sequence: u64,
+}
+
+/// `IDGenerator::layout_info` 的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayoutInfo {
+    pub timestamp_bits: u32,
+    pub environment_bits: u32,
+    pub datacenter_id_bits: u32,
+    pub worker_id_bits: u32,
+    pub sequence_bits: u32,
+    pub max_datacenter_id: u32,
+    pub max_worker_id: u32,
+    pub max_sequence: u32,
+    /// 每毫秒保留给补录的序列号数量
+    pub backfill_sequences: u32,
+    /// ID格式版本号位数，0 表示不启用
+    pub version_bits: u32,
+    /// 写入ID的格式版本号
+    pub version: u32,
+    pub datacenter_id: u32,
+    pub worker_id: u32,
+    pub environment: Option<Environment>,
+    /// 起始时间戳（Unix 毫秒）
+    pub epoch: u64,
+    /// 时间戳位用尽的时刻（RFC3339）
+    pub exhausted_at: Option<String>,
+    /// 理论上每秒最多可生成的ID数量
+    pub capacity_per_second: u64,
+}
+
+/// `IDGenerator::stats` 的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratorStats {
+    pub total_generated: u64,
+    pub random_generated: u64,
+    pub cas_retries: u64,
+    /// 遇到时钟回拨的生成调用次数
+    pub clock_backwards: u64,
+    /// 序列号耗尽或时钟回拨时等待的次数
+    pub sequence_waits: u64,
+    pub uptime_seconds: u64,
+    /// 理论上每秒最多可生成的ID数量
+    pub capacity_per_second: u64,
+    /// 当前生效的节点ID，切换 worker id 后随之变化
+    pub worker_id: u32,
+    pub datacenter_id: u32,
+}
+
+impl GeneratorStats {
+    /// 与上一次统计比较，得到这段时间内的生成速率等健康指标
+    pub fn sample_since(&self, previous: &GeneratorStats, elapsed: Duration) -> GeneratorSample {
+        let generated = self
+            .total_generated
+            .saturating_sub(previous.total_generated);
+        let retries = self.cas_retries.saturating_sub(previous.cas_retries);
+        let secs = elapsed.as_secs_f64();
+        let ids_per_second = if secs > 0.0 {
+            generated as f64 / secs
+        } else {
+            0.0
+        };
+        GeneratorSample {
+            ids_per_second,
+            cas_retries_per_id: if generated > 0 {
+                retries as f64 / generated as f64
+            } else {
+                0.0
+            },
+            sequence_utilization: if self.capacity_per_second > 0 {
+                ids_per_second / self.capacity_per_second as f64
+            } else {
+                0.0
+            },
+            clock_backwards: self
+                .clock_backwards
+                .saturating_sub(previous.clock_backwards),
+        }
+    }
+}
+
+/// `GeneratorStats::sample_since` 的结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneratorSample {
+    pub ids_per_second: f64,
+    /// 平均每个ID的 CAS 重试次数
+    pub cas_retries_per_id: f64,
+    /// 实际速率 / 理论容量
+    pub sequence_utilization: f64,
+    /// 采样周期内遇到时钟回拨的次数
+    pub clock_backwards: u64,
+}
+
+/// `IDGenerator::next_id_detailed` 的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeneratedId {
+    pub id: u64,
+    /// 写入ID的时间（Unix 毫秒）
+    pub timestamp_ms: u64,
+    pub sequence: u32,
+    pub worker_id: u32,
+    pub datacenter_id: u32,
+}
+
+/// `IDGenerator::decode_id` 的结果
+///
+/// `Display` 输出 `ts=.. dc=.. w=.. seq=..` 形式的紧凑文本，可直接用作日志/tracing 字段。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DecodedId {
+    pub id: u64,
+    /// 生成时间（Unix 毫秒）
+    pub timestamp: u64,
+    pub datacenter_id: u32,
+    pub worker_id: u32,
+    pub sequence: u32,
+    /// 未启用环境标识位或编码无法识别时为 None
+    pub environment: Option<Environment>,
+    /// ID中的环境与本实例不一致
+    pub environment_mismatch: bool,
+    /// 序列号落在补录保留段，即手动补录而非在线生成的ID
+    pub backfill: bool,
+    /// ID中的格式版本号，未启用版本位时为 None
+    pub version: Option<u32>,
+    /// ID的格式版本与当前配置不一致，应拒绝而不是按当前布局解读
+    pub version_mismatch: bool,
+}
+
+impl DecodedId {
+    /// 序列化为 JSON，字段与 HTTP 拆解接口一致
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("DecodedId is always serializable")
+    }
+}
+
+impl std::fmt::Display for DecodedId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ts={} dc={} w={} seq={}",
+            self.timestamp, self.datacenter_id, self.worker_id, self.sequence
+        )?;
+        if let Some(version) = self.version {
+            write!(f, " v={}", version)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -207,12 +1312,12 @@ mod tests {
     use std::thread;
     use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-    use shared::config::IdGeneratorConfig;
+    use shared::config::{DatacenterId, IdGeneratorConfig, WorkerId};
 
     fn create_test_config() -> IdGeneratorConfig {
         IdGeneratorConfig {
-            worker_id: 1,
-            datacenter_id: 1,
+            worker_id: WorkerId::new(1, (1 << 5) - 1).unwrap(),
+            datacenter_id: DatacenterId::new(1, (1 << 5) - 1).unwrap(),
             sequence_bits: 12,
             worker_id_bits: 5,
             datacenter_id_bits: 5,
@@ -221,18 +1326,32 @@ mod tests {
             max_sequence: (1 << 12) - 1,
             max_worker_id: (1 << 5) - 1,
             max_datacenter_id: (1 << 5) - 1,
+            random_fallback_on_clock_error: false,
+            max_backwards_wait_ms: 0,
+            clock_rollback: ClockRollbackPolicy::Wait,
+            random_seed: None,
+            layout: None,
+            environment_bits: 0,
+            environment: None,
+            backfill_sequences: 0,
+            total_generated_batch: 1,
+            version_bits: 0,
+            version: 0,
+            state_file: None,
+            state_persist_interval_ms: 1000,
+            worker_id_source: None,
         }
     }
 
     fn create_invalid_worker_id_config() -> IdGeneratorConfig {
         let mut cfg = create_test_config();
-        cfg.worker_id = cfg.max_worker_id + 1; // 超过最大值
+        cfg.worker_id = WorkerId::new(cfg.max_worker_id + 1, u32::MAX).unwrap(); // 超过最大值
         cfg
     }
 
     fn create_invalid_datacenter_id_config() -> IdGeneratorConfig {
         let mut cfg = create_test_config();
-        cfg.datacenter_id = cfg.max_datacenter_id + 1; // 超过最大值
+        cfg.datacenter_id = DatacenterId::new(cfg.max_datacenter_id + 1, u32::MAX).unwrap(); // 超过最大值
         cfg
     }
 
@@ -243,8 +1362,8 @@ mod tests {
         assert!(result.is_ok());
 
         let generator = result.unwrap();
-        assert_eq!(generator.cfg.worker_id, 1);
-        assert_eq!(generator.cfg.datacenter_id, 1);
+        assert_eq!(generator.cfg.worker_id.get(), 1);
+        assert_eq!(generator.cfg.datacenter_id.get(), 1);
     }
 
     #[test]
@@ -286,8 +1405,8 @@ mod tests {
         let datacenter_id = ((id >> (generator.cfg.sequence_bits + generator.cfg.worker_id_bits))
             & datacenter_id_mask) as u32;
 
-        assert_eq!(worker_id, generator.cfg.worker_id);
-        assert_eq!(datacenter_id, generator.cfg.datacenter_id);
+        assert_eq!(worker_id, generator.cfg.worker_id.get());
+        assert_eq!(datacenter_id, generator.cfg.datacenter_id.get());
         assert!(sequence <= generator.cfg.max_sequence);
     }
 
@@ -320,6 +1439,89 @@ mod tests {
         }
     }
 
+    /// 拆解ID各部分，便于定位重复ID的来源
+    fn decode(generator: &IDGenerator, id: u64) -> String {
+        let cfg = &generator.cfg;
+        let sequence = id & ((1 << cfg.sequence_bits) - 1);
+        let worker_id = (id >> cfg.sequence_bits) & ((1 << cfg.worker_id_bits) - 1);
+        let datacenter_id =
+            (id >> (cfg.sequence_bits + cfg.worker_id_bits)) & ((1 << cfg.datacenter_id_bits) - 1);
+        let (timestamp, _) = generator.parse_id(id);
+        format!(
+            "id={} timestamp={} datacenter_id={} worker_id={} sequence={}",
+            id, timestamp, datacenter_id, worker_id, sequence
+        )
+    }
+
+    /// 多线程交替调用 `generate_id` 与 `generate_ids_batch`，断言没有重复ID
+    ///
+    /// 每个线程共生成 `per_thread` 个ID，批量区间预留的 bug 往往只在与单个生成交错时暴露。
+    fn assert_no_duplicates(generator: Arc<IDGenerator>, threads: usize, per_thread: usize) {
+        let seen = Arc::new(Mutex::new(HashSet::with_capacity(threads * per_thread)));
+
+        let handles: Vec<_> = (0..threads)
+            .map(|t| {
+                let generator = Arc::clone(&generator);
+                let seen = Arc::clone(&seen);
+                thread::spawn(move || {
+                    let mut generated = 0;
+                    let mut round = 0;
+                    while generated < per_thread {
+                        // 奇偶线程错开单个/批量的节奏，批量大小也逐轮变化
+                        let ids = if (t + round) % 2 == 0 {
+                            vec![generator.generate_id().unwrap()]
+                        } else {
+                            let count = (round % 7 + 1) * 37;
+                            generator
+                                .generate_ids_batch(count.min(per_thread - generated))
+                                .unwrap()
+                        };
+                        generated += ids.len();
+                        round += 1;
+
+                        let mut seen = seen.lock().unwrap();
+                        for id in ids {
+                            if !seen.insert(id) {
+                                panic!("duplicate id: {}", decode(&generator, id));
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(seen.lock().unwrap().len(), threads * per_thread);
+    }
+
+    #[test]
+    fn test_no_duplicates_interleaved_single_and_batch() {
+        let generator = Arc::new(IDGenerator::new(create_test_config()).unwrap());
+        assert_no_duplicates(generator, 8, 5000);
+    }
+
+    #[test]
+    fn test_cas_retries_shared_counter() {
+        let counter = Arc::new(AtomicU64::new(0));
+        let generator = Arc::new(
+            IDGenerator::new(create_test_config())
+                .unwrap()
+                .with_cas_retry_counter(counter.clone()),
+        );
+        assert_no_duplicates(generator.clone(), 8, 2_000);
+
+        // 争用下的重试次数不确定，只校验计数器是同一份
+        assert_eq!(counter.load(Ordering::Relaxed), generator.cas_retries());
+        let before = generator.cas_retries();
+        generator.record_cas_retries(CAS_RETRY_WARN_THRESHOLD + 1);
+        assert_eq!(
+            counter.load(Ordering::Relaxed),
+            before + CAS_RETRY_WARN_THRESHOLD + 1
+        );
+    }
+
     #[test]
     fn test_concurrent_id_generation() {
         let cfg = create_test_config();
@@ -401,8 +1603,8 @@ mod tests {
             & datacenter_id_mask) as u32;
 
         assert_eq!(extracted_sequence, sequence);
-        assert_eq!(extracted_worker_id, generator.cfg.worker_id);
-        assert_eq!(extracted_datacenter_id, generator.cfg.datacenter_id);
+        assert_eq!(extracted_worker_id, generator.cfg.worker_id.get());
+        assert_eq!(extracted_datacenter_id, generator.cfg.datacenter_id.get());
     }
 
     #[test]
@@ -471,6 +1673,100 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ids_fit_configured_total_bits() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        // 节点ID取各自位宽的最大值，任何移位/掩码错误都会溢出到相邻字段或更高位
+        let narrow = IdGeneratorConfig {
+            worker_id: WorkerId::new(15, 15).unwrap(),
+            datacenter_id: DatacenterId::new(3, 3).unwrap(),
+            sequence_bits: 8,
+            worker_id_bits: 4,
+            datacenter_id_bits: 2,
+            timestamp_bits: 38,
+            epoch: now - 24 * 60 * 60 * 1000,
+            max_sequence: (1 << 8) - 1,
+            max_worker_id: (1 << 4) - 1,
+            max_datacenter_id: (1 << 2) - 1,
+            ..create_test_config()
+        };
+        let versioned = IdGeneratorConfig {
+            worker_id: WorkerId::new(31, 31).unwrap(),
+            datacenter_id: DatacenterId::new(31, 31).unwrap(),
+            timestamp_bits: 37,
+            epoch: now - 24 * 60 * 60 * 1000,
+            environment_bits: 2,
+            environment: Some(Environment::Prod),
+            version_bits: 2,
+            version: 3,
+            ..create_test_config()
+        };
+        let configs = [
+            IdGeneratorConfig::default(),
+            create_test_config(),
+            narrow,
+            versioned,
+        ];
+
+        for cfg in configs {
+            let total_bits = IDGenerator::total_bits(&cfg);
+            let max_id = (1u64 << total_bits) - 1;
+            let generator = IDGenerator::new(cfg.clone()).unwrap();
+
+            for id in generator.generate_ids_batch(20_000).unwrap() {
+                assert!(
+                    id <= max_id,
+                    "id {:#x} exceeds {} configured bits",
+                    id,
+                    total_bits
+                );
+                assert_eq!(id >> total_bits, 0, "unused high bits set in {:#x}", id);
+
+                let decoded = generator.decode_id(id);
+                assert_eq!(decoded.worker_id, cfg.worker_id.get());
+                assert_eq!(decoded.datacenter_id, cfg.datacenter_id.get());
+                assert!(!decoded.version_mismatch);
+                assert!(!decoded.environment_mismatch);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_id_round_trips_assemble_id() {
+        let cfg = create_test_config();
+        let generator = IDGenerator::new(cfg.clone()).unwrap();
+
+        for _ in 0..1000 {
+            let id = generator.next_id().unwrap();
+            let decoded = generator.decode_id(id);
+            assert_eq!(decoded.worker_id, cfg.worker_id.get());
+            assert_eq!(decoded.datacenter_id, cfg.datacenter_id.get());
+            assert!(decoded.timestamp > cfg.epoch);
+            assert_eq!(
+                generator.assemble_id(decoded.timestamp - cfg.epoch, decoded.sequence),
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn test_next_id_detailed_matches_decode() {
+        let generator = IDGenerator::new(create_test_config()).unwrap();
+
+        for _ in 0..1000 {
+            let detailed = generator.next_id_detailed().unwrap();
+            let decoded = generator.decode_id(detailed.id);
+            assert_eq!(detailed.timestamp_ms, decoded.timestamp);
+            assert_eq!(detailed.sequence, decoded.sequence);
+            assert_eq!(detailed.worker_id, decoded.worker_id);
+            assert_eq!(detailed.datacenter_id, decoded.datacenter_id);
+        }
+        assert_eq!(generator.stats().total_generated, 1000);
+    }
+
     #[test]
     fn test_default_config_validity() {
         let default_cfg = IdGeneratorConfig::default();
@@ -509,4 +1805,1028 @@ mod tests {
         assert!(id2 > 0);
         assert_ne!(id1, id2);
     }
+
+    /// 构造一个 epoch 在未来的配置，模拟系统时钟不可用
+    fn create_broken_clock_config() -> IdGeneratorConfig {
+        let mut cfg = create_test_config();
+        cfg.epoch = u64::MAX;
+        cfg
+    }
+
+    #[test]
+    fn test_clock_unavailable_without_fallback() {
+        let generator = IDGenerator::new(create_broken_clock_config()).unwrap();
+
+        assert!(generator.generate_id().is_err());
+        assert!(generator.generate_ids_batch(3).is_err());
+        assert_eq!(generator.random_generated(), 0);
+    }
+
+    #[test]
+    fn test_clock_unavailable_random_fallback() {
+        let mut cfg = create_broken_clock_config();
+        cfg.random_fallback_on_clock_error = true;
+        let generator = IDGenerator::new(cfg).unwrap();
+
+        let id = generator.generate_id().unwrap();
+        assert!(id <= i64::MAX as u64);
+
+        let ids = generator.generate_ids_batch(100).unwrap();
+        assert_eq!(ids.len(), 100);
+        assert!(ids.iter().all(|&id| id <= i64::MAX as u64));
+        assert_eq!(generator.random_generated(), 101);
+    }
+
+    #[test]
+    fn test_random_fallback_with_seed_is_deterministic() {
+        let mut cfg = create_broken_clock_config();
+        cfg.random_fallback_on_clock_error = true;
+        cfg.random_seed = Some(42);
+
+        let mut expected = StdRng::seed_from_u64(42);
+        let expected: Vec<u64> = (0..4)
+            .map(|_| expected.random::<u64>() & (i64::MAX as u64))
+            .collect();
+
+        let generator = IDGenerator::new(cfg.clone()).unwrap();
+        assert_eq!(generator.generate_id().unwrap(), expected[0]);
+        assert_eq!(generator.generate_ids_batch(3).unwrap(), expected[1..]);
+
+        // 相同种子的新实例从头复现
+        let replay = IDGenerator::new(cfg).unwrap();
+        assert_eq!(replay.generate_ids_batch(4).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_deadline_aborts_clock_backwards_wait() {
+        let generator = IDGenerator::new(create_test_config()).unwrap();
+        // 模拟时钟回拨：已分配状态领先当前时间 10 秒
+        let ahead = generator.get_current_timestamp().unwrap() + 10_000;
+        generator
+            .ts_seq
+            .store(ahead << generator.cfg.sequence_bits, Ordering::Release);
+
+        let deadline = Some(Instant::now() + Duration::from_millis(20));
+        let started = Instant::now();
+        assert!(matches!(
+            generator.next_id_before(deadline),
+            Err(TinyIdError::DeadlineExceeded)
+        ));
+        assert!(matches!(
+            generator.generate_ids_batch_before(10, deadline),
+            Err(TinyIdError::DeadlineExceeded)
+        ));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_decoded_id_display_hash_and_json() {
+        let generator = IDGenerator::new(create_test_config()).unwrap();
+        let id = generator.next_id().unwrap();
+        let decoded = generator.decode_id(id);
+
+        assert_eq!(
+            decoded.to_string(),
+            format!("ts={} dc=1 w=1 seq={}", decoded.timestamp, decoded.sequence)
+        );
+        let set: HashSet<DecodedId> = [decoded.clone(), generator.decode_id(id)].into();
+        assert_eq!(set.len(), 1);
+
+        let json = decoded.to_json();
+        assert_eq!(json["id"], id);
+        assert_eq!(json["worker_id"], 1);
+        assert_eq!(serde_json::from_value::<DecodedId>(json).unwrap(), decoded);
+    }
+
+    #[test]
+    fn test_max_backwards_wait_bounds_retry_loop() {
+        let cfg = IdGeneratorConfig {
+            max_backwards_wait_ms: 50,
+            ..create_test_config()
+        };
+        let generator = IDGenerator::new(cfg).unwrap();
+        let set_ahead = |millis: u64| {
+            let ahead = generator.get_current_timestamp().unwrap() + millis;
+            generator
+                .ts_seq
+                .store(ahead << generator.cfg.sequence_bits, Ordering::Release);
+        };
+
+        // 回拨幅度超过上限：不等待，直接失败
+        set_ahead(10_000);
+        let started = Instant::now();
+        assert!(matches!(
+            generator.next_id(),
+            Err(TinyIdError::ClockMovedBackwards(_))
+        ));
+        assert!(matches!(
+            generator.generate_ids_batch(10_000),
+            Err(TinyIdError::ClockMovedBackwards(_))
+        ));
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        // 回拨幅度在上限内：等时钟追上后正常生成
+        set_ahead(5);
+        assert_eq!(generator.generate_ids_batch(100).unwrap().len(), 100);
+    }
+
+    fn create_environment_config(environment: Environment) -> IdGeneratorConfig {
+        IdGeneratorConfig {
+            layout: Some("39-3-7-12".parse().unwrap()),
+            environment_bits: 2,
+            environment: Some(environment),
+            ..create_test_config()
+        }
+    }
+
+    #[test]
+    fn test_environment_bits_round_trip() {
+        let prod = IDGenerator::new(create_environment_config(Environment::Prod)).unwrap();
+        let staging = IDGenerator::new(create_environment_config(Environment::Staging)).unwrap();
+
+        let prod_id = prod.next_id().unwrap();
+        let staging_id = staging.next_id().unwrap();
+
+        let decoded = prod.decode_id(prod_id);
+        assert_eq!(decoded.environment, Some(Environment::Prod));
+        assert!(!decoded.environment_mismatch);
+        assert_eq!(decoded.worker_id, prod.worker_id.get());
+        assert_eq!(decoded.datacenter_id, prod.datacenter_id.get());
+        assert!(
+            decoded
+                .timestamp
+                .abs_diff(prod.get_current_timestamp().unwrap() + prod.cfg.epoch)
+                < 1000
+        );
+
+        let decoded = prod.decode_id(staging_id);
+        assert_eq!(decoded.environment, Some(Environment::Staging));
+        assert!(decoded.environment_mismatch);
+    }
+
+    #[test]
+    fn test_environment_bits_validation() {
+        // 默认 41-3-7-12 已占满 63 位
+        let cfg = IdGeneratorConfig {
+            environment_bits: 2,
+            environment: Some(Environment::Dev),
+            ..create_test_config()
+        };
+        assert!(IDGenerator::new(cfg).is_err());
+
+        let cfg = IdGeneratorConfig {
+            environment_bits: 1,
+            ..create_environment_config(Environment::Prod)
+        };
+        assert!(IDGenerator::new(cfg).is_err());
+
+        // 未启用时不标记环境
+        let generator = IDGenerator::new(create_test_config()).unwrap();
+        let decoded = generator.decode_id(generator.next_id().unwrap());
+        assert_eq!(decoded.environment, None);
+        assert!(!decoded.environment_mismatch);
+    }
+
+    #[test]
+    fn test_new_rejects_invalid_bit_layout() {
+        // 默认布局 41+5+5+12 = 63 位，加上符号位正好 64 位
+        let generator = IDGenerator::new(create_test_config()).unwrap();
+        assert_eq!(IDGenerator::total_bits(&generator.cfg), 63);
+        // 比 63 位窄的布局同样合法
+        let narrow = IdGeneratorConfig {
+            timestamp_bits: 40,
+            ..create_test_config()
+        };
+        assert!(IDGenerator::new(narrow).is_ok());
+
+        for cfg in [
+            // 总位数 64，占用符号位
+            IdGeneratorConfig {
+                timestamp_bits: 42,
+                ..create_test_config()
+            },
+            // max_worker_id 超出 5 位，会覆盖数据中心字段
+            IdGeneratorConfig {
+                max_worker_id: 64,
+                ..create_test_config()
+            },
+            // 没有给在线生成留下序列号
+            IdGeneratorConfig {
+                backfill_sequences: 4095,
+                ..create_test_config()
+            },
+        ] {
+            let err = IDGenerator::new(cfg).unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<TinyIdError>(),
+                    Some(TinyIdError::ConfigError(_))
+                ),
+                "{:#}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_config() {
+        let cfg = create_test_config();
+        let now = cfg.epoch + 1000;
+        let layout = IDGenerator::validate_config(&cfg, now).unwrap();
+        assert_eq!(layout.capacity_per_second, 4095 * 1000);
+        assert!(layout.exhausted_at.is_some());
+
+        // epoch 在未来，且 max_worker_id 超出位宽：一次报告全部问题
+        let cfg = IdGeneratorConfig {
+            max_worker_id: 64,
+            ..create_test_config()
+        };
+        let errors = IDGenerator::validate_config(&cfg, cfg.epoch - 1).unwrap_err();
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[0].contains("max_worker_id"));
+        assert!(errors[1].contains("future"));
+
+        let cfg = IdGeneratorConfig {
+            timestamp_bits: 10,
+            ..create_test_config()
+        };
+        let errors = IDGenerator::validate_config(&cfg, cfg.epoch + 1024).unwrap_err();
+        assert!(errors[0].contains("exhausted"));
+
+        let cfg = IdGeneratorConfig {
+            timestamp_bits: 42,
+            ..create_test_config()
+        };
+        let errors = IDGenerator::validate_config(&cfg, now).unwrap_err();
+        assert_eq!(errors, vec!["total bits 64 exceeds 63".to_string()]);
+
+        // `new` 本身的校验错误也会报告
+        let cfg = IdGeneratorConfig {
+            backfill_sequences: 4096,
+            ..create_test_config()
+        };
+        let errors = IDGenerator::validate_config(&cfg, now).unwrap_err();
+        assert!(errors[0].contains("backfill_sequences"));
+    }
+
+    fn create_version_config(version: u32) -> IdGeneratorConfig {
+        IdGeneratorConfig {
+            layout: Some("39-3-7-12".parse().unwrap()),
+            version_bits: 2,
+            version,
+            ..create_test_config()
+        }
+    }
+
+    #[test]
+    fn test_version_bits_round_trip() {
+        let v1 = IDGenerator::new(create_version_config(1)).unwrap();
+        let v2 = IDGenerator::new(create_version_config(2)).unwrap();
+
+        let v1_id = v1.next_id().unwrap();
+        // 版本位紧挨符号位
+        assert_eq!(v1_id >> 61, 1);
+
+        let decoded = v1.decode_id(v1_id);
+        assert_eq!(decoded.version, Some(1));
+        assert!(!decoded.version_mismatch);
+        assert_eq!(decoded.worker_id, v1.worker_id.get());
+        assert!(
+            decoded
+                .timestamp
+                .abs_diff(v1.get_current_timestamp().unwrap() + v1.cfg.epoch)
+                < 1000
+        );
+
+        let decoded = v2.decode_id(v1_id);
+        assert_eq!(decoded.version, Some(1));
+        assert!(decoded.version_mismatch);
+
+        // 启用版本位之前（同样的位布局）生成的ID版本号为 0
+        let legacy = IDGenerator::new(IdGeneratorConfig {
+            version_bits: 0,
+            version: 0,
+            ..create_version_config(0)
+        })
+        .unwrap();
+        let decoded = v1.decode_id(legacy.next_id().unwrap());
+        assert_eq!(decoded.version, Some(0));
+        assert!(decoded.version_mismatch);
+    }
+
+    #[test]
+    fn test_version_bits_validation() {
+        // 默认 41-3-7-12 已占满 63 位
+        let cfg = IdGeneratorConfig {
+            version_bits: 1,
+            ..create_test_config()
+        };
+        assert!(IDGenerator::new(cfg).is_err());
+
+        // 版本号放不进位宽
+        assert!(IDGenerator::new(create_version_config(4)).is_err());
+        let cfg = IdGeneratorConfig {
+            version: 1,
+            ..create_test_config()
+        };
+        assert!(IDGenerator::new(cfg).is_err());
+
+        let generator = IDGenerator::new(create_test_config()).unwrap();
+        let decoded = generator.decode_id(generator.next_id().unwrap());
+        assert_eq!(decoded.version, None);
+        assert!(!decoded.version_mismatch);
+    }
+
+    /// 手动推进的时钟，未调用 `advance` 前始终停在同一毫秒
+    #[derive(Debug)]
+    struct MockClock(AtomicU64);
+
+    impl MockClock {
+        fn new(millis: u64) -> Arc<Self> {
+            Arc::new(Self(AtomicU64::new(millis)))
+        }
+
+        fn advance(&self, millis: u64) {
+            self.0.fetch_add(millis, Ordering::SeqCst);
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now_millis(&self) -> Result<u64, TinyIdError> {
+            Ok(self.0.load(Ordering::SeqCst))
+        }
+    }
+
+    fn temp_state_file() -> String {
+        std::env::temp_dir()
+            .join(format!("tinyid-state-{}.json", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// 读出 state_file 中的预留上限（Unix 毫秒）
+    fn persisted_timestamp(path: &str) -> u64 {
+        let content = std::fs::read_to_string(path).unwrap();
+        serde_json::from_str::<PersistedState>(&content)
+            .unwrap()
+            .timestamp
+    }
+
+    #[test]
+    fn test_state_file_restores_high_water_mark() {
+        let path = temp_state_file();
+        let cfg = IdGeneratorConfig {
+            state_file: Some(path.clone()),
+            state_persist_interval_ms: 100,
+            ..create_test_config()
+        };
+        let pinned = cfg.epoch + 1_000;
+
+        // 首次启动没有状态文件，从 0 开始；第一次发号前先写入预留
+        let before = IDGenerator::new(cfg.clone())
+            .unwrap()
+            .with_clock(MockClock::new(pinned) as Arc<dyn Clock>);
+        let issued = before.generate_ids_batch(10).unwrap();
+        assert_eq!(persisted_timestamp(&path), pinned + 100);
+        before.save_state().unwrap();
+        assert_eq!(persisted_timestamp(&path), pinned + 200);
+
+        // 重启后从预留上限继续，不会重复
+        let after = IDGenerator::new(cfg.clone())
+            .unwrap()
+            .with_clock(MockClock::new(pinned + 200) as Arc<dyn Clock>);
+        let next = after.next_id().unwrap();
+        assert!(!issued.contains(&next));
+        assert_eq!(after.parse_id(next), (pinned + 200, 0));
+
+        // 重启时时钟回拨到预留之前：等待而不是重新从该毫秒的序列 0 开始
+        let rewound = IDGenerator::new(cfg)
+            .unwrap()
+            .with_clock(MockClock::new(pinned - 5) as Arc<dyn Clock>);
+        let deadline = Some(Instant::now() + Duration::from_millis(20));
+        assert!(matches!(
+            rewound.next_id_before(deadline),
+            Err(TinyIdError::DeadlineExceeded)
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_state_file_restores_after_crash_mid_interval() {
+        let path = temp_state_file();
+        let cfg = IdGeneratorConfig {
+            state_file: Some(path.clone()),
+            state_persist_interval_ms: 100,
+            ..create_test_config()
+        };
+        let start = cfg.epoch + 1_000;
+
+        let clock = MockClock::new(start);
+        let crashed = IDGenerator::new(cfg.clone())
+            .unwrap()
+            .with_clock(clock.clone() as Arc<dyn Clock>);
+        crashed.save_state().unwrap();
+        let mut issued = crashed.generate_ids_batch(10).unwrap();
+        // 两次持久化之间继续发号，越过预留时发号路径自己推进
+        for _ in 0..5 {
+            clock.advance(60);
+            issued.push(crashed.next_id().unwrap());
+            issued.extend(crashed.generate_ids_batch(3).unwrap());
+        }
+        let last = *issued.iter().max().unwrap();
+        let (last_ts, _) = crashed.parse_id(last);
+        let reserved = persisted_timestamp(&path);
+        assert!(reserved > last_ts);
+        // 没有机会再调用 save_state 就退出
+        drop(crashed);
+
+        // 重启时时钟停在崩溃时刻：预留范围内的毫秒都可能已经用过，必须等到预留上限
+        let clock = MockClock::new(last_ts);
+        let restarted = Arc::new(
+            IDGenerator::new(cfg)
+                .unwrap()
+                .with_clock(clock.clone() as Arc<dyn Clock>),
+        );
+        let deadline = Some(Instant::now() + Duration::from_millis(20));
+        assert!(matches!(
+            restarted.next_id_before(deadline),
+            Err(TinyIdError::DeadlineExceeded)
+        ));
+
+        clock.advance(reserved - last_ts);
+        let next = restarted.next_id().unwrap();
+        assert!(next > last);
+        assert_eq!(restarted.parse_id(next), (reserved, 0));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_state_file_future_timestamp_waits_for_clock() {
+        let path = temp_state_file();
+        let cfg = IdGeneratorConfig {
+            state_file: Some(path.clone()),
+            ..create_test_config()
+        };
+        let now = cfg.epoch + 1_000;
+        let persisted = now + 50;
+        // 上次运行写下的高水位领先当前时钟 50ms，且该毫秒的序列已用尽
+        std::fs::write(
+            &path,
+            format!(
+                r#"{{"timestamp":{},"next_sequence":{}}}"#,
+                persisted, cfg.max_sequence
+            ),
+        )
+        .unwrap();
+
+        let clock = MockClock::new(now);
+        let generator = Arc::new(
+            IDGenerator::new(cfg)
+                .unwrap()
+                .with_clock(clock.clone() as Arc<dyn Clock>),
+        );
+        let waiter = {
+            let generator = Arc::clone(&generator);
+            thread::spawn(move || generator.next_id().unwrap())
+        };
+        thread::sleep(Duration::from_millis(20));
+        assert!(!waiter.is_finished());
+
+        // 时钟追到高水位仍不够，必须越过它
+        clock.advance(50);
+        thread::sleep(Duration::from_millis(20));
+        assert!(!waiter.is_finished());
+        clock.advance(1);
+
+        let id = waiter.join().unwrap();
+        assert_eq!(generator.parse_id(id), (persisted + 1, 0));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_state_file_rejects_invalid_content() {
+        let path = temp_state_file();
+        let cfg = IdGeneratorConfig {
+            state_file: Some(path.clone()),
+            ..create_test_config()
+        };
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(IDGenerator::new(cfg.clone()).is_err());
+
+        // 早于 epoch，说明状态文件来自其它布局
+        std::fs::write(&path, r#"{"timestamp":1,"next_sequence":0}"#).unwrap();
+        assert!(IDGenerator::new(cfg.clone()).is_err());
+
+        // 尚未生成过ID时不写文件
+        std::fs::remove_file(&path).unwrap();
+        IDGenerator::new(cfg).unwrap().save_state().unwrap();
+        assert!(!std::path::Path::new(&path).exists());
+    }
+
+    #[test]
+    fn test_sequence_rollover_waits_for_next_millisecond() {
+        let cfg = create_test_config();
+        let pinned = cfg.epoch + 1_000;
+        let clock = MockClock::new(pinned);
+        let generator = Arc::new(
+            IDGenerator::new(cfg)
+                .unwrap()
+                .with_clock(clock.clone() as Arc<dyn Clock>),
+        );
+        let max_seq = generator.cfg.max_sequence;
+
+        // 时钟停在同一毫秒，不断申请直到序列号耗尽；耗尽后只能等待，不能回绕出重复ID
+        let mut seen = HashSet::new();
+        loop {
+            let deadline = Some(Instant::now() + Duration::from_millis(20));
+            match generator.next_id_before(deadline) {
+                Ok(id) => {
+                    let (ts, seq) = generator.parse_id(id);
+                    assert_eq!(ts, pinned);
+                    assert_eq!(seq as usize, seen.len());
+                    assert!(seen.insert(id));
+                }
+                Err(TinyIdError::DeadlineExceeded) => break,
+                Err(e) => panic!("unexpected error: {e}"),
+            }
+            assert!(seen.len() <= max_seq as usize + 1);
+        }
+        assert!(seen.len() >= max_seq as usize);
+        let deadline = Some(Instant::now() + Duration::from_millis(20));
+        assert!(matches!(
+            generator.generate_ids_batch_before(2, deadline),
+            Err(TinyIdError::DeadlineExceeded)
+        ));
+
+        // 无 deadline 的调用会阻塞，直到时钟进入下一毫秒
+        let waiter = {
+            let generator = Arc::clone(&generator);
+            thread::spawn(move || generator.next_id().unwrap())
+        };
+        thread::sleep(Duration::from_millis(20));
+        assert!(!waiter.is_finished());
+        clock.advance(1);
+
+        let id = waiter.join().unwrap();
+        assert_eq!(generator.parse_id(id), (pinned + 1, 0));
+        assert!(seen.insert(id));
+        assert_eq!(
+            generator.total_generated.load(Ordering::Relaxed),
+            seen.len() as u64
+        );
+    }
+
+    #[test]
+    fn test_clock_rollback_policies() {
+        let pinned = create_test_config().epoch + 1_000;
+        let rolled_back = |policy: ClockRollbackPolicy| {
+            let clock = MockClock::new(pinned);
+            let cfg = IdGeneratorConfig {
+                clock_rollback: policy,
+                ..create_test_config()
+            };
+            let generator = IDGenerator::new(cfg)
+                .unwrap()
+                .with_clock(clock.clone() as Arc<dyn Clock>);
+            let first = generator.next_id().unwrap();
+            // 模拟 NTP 把时钟往回拨 5ms
+            clock.0.fetch_sub(5, Ordering::SeqCst);
+            (generator, first)
+        };
+
+        // error：立即失败，不等待
+        let (generator, _) = rolled_back(ClockRollbackPolicy::Error);
+        let started = Instant::now();
+        assert!(matches!(
+            generator.next_id(),
+            Err(TinyIdError::ClockMovedBackwards(5))
+        ));
+        assert!(matches!(
+            generator.generate_ids_batch(10),
+            Err(TinyIdError::ClockMovedBackwards(5))
+        ));
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        // wait：等待时钟追上，有 deadline 时超时退出
+        let (generator, _) = rolled_back(ClockRollbackPolicy::Wait);
+        let deadline = Some(Instant::now() + Duration::from_millis(20));
+        assert!(matches!(
+            generator.next_id_before(deadline),
+            Err(TinyIdError::DeadlineExceeded)
+        ));
+
+        // borrow：沿用上次的时间戳继续分配，序列用尽后直接占用下一毫秒
+        let (generator, first) = rolled_back(ClockRollbackPolicy::Borrow);
+        let max_seq = generator.cfg.max_sequence as usize;
+        let mut ids = vec![first];
+        ids.push(generator.next_id().unwrap());
+        assert_eq!(generator.parse_id(ids[1]), (pinned, 1));
+        ids.extend(generator.generate_ids_batch(max_seq + 10).unwrap());
+        ids.push(generator.next_id().unwrap());
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(generator.parse_id(ids[max_seq - 1]).0, pinned);
+        assert_eq!(generator.parse_id(ids[max_seq]), (pinned + 1, 0));
+        assert_eq!(generator.stats().clock_backwards, 3);
+    }
+
+    #[tokio::test]
+    async fn test_next_id_async_yields_while_waiting() {
+        let cfg = create_test_config();
+        let pinned = cfg.epoch + 1_000;
+        let clock = MockClock::new(pinned);
+        let generator = Arc::new(
+            IDGenerator::new(cfg)
+                .unwrap()
+                .with_clock(clock.clone() as Arc<dyn Clock>),
+        );
+        let per_millis = generator.live_max_sequence() as usize;
+
+        // 单线程运行时上申请两毫秒的量：后一半要等时钟推进，而推进时钟的任务也在同一线程上，
+        // 等待时阻塞线程就会卡死
+        let tasks: Vec<_> = (0..per_millis * 2)
+            .map(|_| {
+                let generator = Arc::clone(&generator);
+                tokio::spawn(async move { generator.next_id_async().await.unwrap() })
+            })
+            .collect();
+        let advancer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            clock.advance(1);
+        });
+
+        let mut seen = HashSet::new();
+        for task in tasks {
+            assert!(seen.insert(task.await.unwrap()));
+        }
+        advancer.await.unwrap();
+        assert_eq!(seen.len(), per_millis * 2);
+    }
+
+    #[test]
+    fn test_sequence_waits_counted_on_saturation() {
+        let cfg = create_test_config();
+        let pinned = cfg.epoch + 1_000;
+        let clock = MockClock::new(pinned);
+        let counter = Arc::new(AtomicU64::new(0));
+        let generator = IDGenerator::new(cfg)
+            .unwrap()
+            .with_clock(clock.clone() as Arc<dyn Clock>)
+            .with_sequence_wait_counter(Arc::clone(&counter));
+        let per_millis = generator.live_max_sequence() as usize;
+
+        for _ in 0..per_millis {
+            generator.next_id().unwrap();
+        }
+        assert_eq!(generator.stats().sequence_waits, 0);
+
+        // 时钟停住，当前毫秒序列已用尽，只能等到 deadline
+        let deadline = Instant::now() + Duration::from_millis(5);
+        assert!(matches!(
+            generator.next_id_before(Some(deadline)),
+            Err(TinyIdError::DeadlineExceeded)
+        ));
+        let waits = generator.stats().sequence_waits;
+        assert!(waits > 0);
+        assert_eq!(counter.load(Ordering::Relaxed), waits);
+
+        // 批量路径同样计数
+        let deadline = Instant::now() + Duration::from_millis(5);
+        assert!(generator
+            .generate_ids_batch_before(1, Some(deadline))
+            .is_err());
+        assert!(generator.sequence_waits() > waits);
+
+        // 推进时钟后不再等待
+        clock.advance(1);
+        let waits = generator.sequence_waits();
+        generator.next_id().unwrap();
+        assert_eq!(generator.sequence_waits(), waits);
+    }
+
+    #[test]
+    fn test_degenerate_batch_sizes() {
+        let cfg = create_test_config();
+        let pinned = cfg.epoch + 1_000;
+        let new_generator = || {
+            IDGenerator::new(cfg.clone())
+                .unwrap()
+                .with_clock(MockClock::new(pinned) as Arc<dyn Clock>)
+        };
+
+        // count=0 返回空列表，不推进状态也不计数
+        let generator = new_generator();
+        let before = generator.ts_seq.load(Ordering::Acquire);
+        assert!(generator.generate_ids_batch(0).unwrap().is_empty());
+        assert_eq!(generator.ts_seq.load(Ordering::Acquire), before);
+        assert_eq!(generator.stats().total_generated, 0);
+
+        // count=1 与 next_id 生成的ID完全一致
+        let batch = generator.generate_ids_batch(1).unwrap();
+        let expected = new_generator().next_id().unwrap();
+        assert_eq!(batch, vec![expected]);
+        assert_eq!(generator.stats().total_generated, 1);
+    }
+
+    #[derive(Debug, Default)]
+    struct MemoryAuditSink(Mutex<Vec<IdRange>>);
+
+    impl crate::core::AuditSink for MemoryAuditSink {
+        fn write(&self, range: &IdRange) {
+            self.0.lock().unwrap().push(*range);
+        }
+    }
+
+    #[test]
+    fn test_auditor_records_minted_ranges() {
+        let cfg = create_test_config();
+        let pinned = cfg.epoch + 1_000;
+        let clock = MockClock::new(pinned);
+        let sink = Arc::new(MemoryAuditSink::default());
+        let auditor = Arc::new(IdAuditor::new(sink.clone()));
+        let generator = IDGenerator::new(cfg)
+            .unwrap()
+            .with_clock(clock.clone() as Arc<dyn Clock>)
+            .with_auditor(Arc::clone(&auditor));
+
+        generator.generate_ids_batch(5).unwrap();
+        generator.next_id().unwrap();
+        clock.advance(1);
+        generator.next_id().unwrap();
+        auditor.flush();
+
+        let range = |timestamp, start_seq, end_seq| IdRange {
+            worker_id: 1,
+            datacenter_id: 1,
+            timestamp,
+            start_seq,
+            end_seq,
+        };
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            vec![range(pinned, 0, 5), range(pinned + 1, 0, 0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retire_into_worker_id_waits_past_last_timestamp() {
+        let generator = IDGenerator::new(create_test_config()).unwrap();
+        let last = generator.next_id().unwrap();
+        let (last_ts, _) = generator.parse_id(last);
+
+        let rotated = generator.retire_into_worker_id(2, None).await.unwrap();
+        assert_eq!(rotated.worker_id().get(), 2);
+        assert!(rotated.get_current_timestamp().unwrap() + rotated.cfg.epoch > last_ts);
+
+        let id = rotated.next_id().unwrap();
+        assert!(id > last);
+        assert_eq!(rotated.decode_id(id).worker_id, 2);
+
+        // 旧实例停用后不再发号
+        assert!(matches!(
+            generator.next_id(),
+            Err(TinyIdError::GeneratorRetired)
+        ));
+        assert!(matches!(
+            generator.generate_ids_batch(3),
+            Err(TinyIdError::GeneratorRetired)
+        ));
+
+        assert!(matches!(
+            rotated.retire_into_worker_id(1 << 5, None).await,
+            Err(TinyIdError::InvalidWorkerId(32))
+        ));
+        assert!(rotated.next_id().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_retire_into_worker_id_restores_on_deadline() {
+        let cfg = create_test_config();
+        let pinned = cfg.epoch + 1_000;
+        let generator = IDGenerator::new(cfg)
+            .unwrap()
+            .with_clock(MockClock::new(pinned) as Arc<dyn Clock>);
+        let last = generator.next_id().unwrap();
+
+        // 时钟不前进，等待超过 deadline 后旧实例恢复发号
+        let deadline = Some(Instant::now() + Duration::from_millis(5));
+        assert!(matches!(
+            generator.retire_into_worker_id(2, deadline).await,
+            Err(TinyIdError::DeadlineExceeded)
+        ));
+        let id = generator.next_id().unwrap();
+        assert!(id > last);
+        assert_eq!(generator.decode_id(id).worker_id, 1);
+    }
+
+    #[test]
+    fn test_batch_spanning_milliseconds_is_unique() {
+        let generator = IDGenerator::new(create_test_config()).unwrap();
+        let count = 3 * (generator.cfg.max_sequence as usize + 1);
+        let ids = generator.generate_ids_batch(count).unwrap();
+        assert_eq!(ids.len(), count);
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_capacity_per_second() {
+        let generator = IDGenerator::new(create_test_config()).unwrap();
+        assert_eq!(generator.capacity_per_second(), 4095 * 1000);
+        assert_eq!(generator.layout_info().capacity_per_second, 4095 * 1000);
+
+        let cfg = IdGeneratorConfig {
+            layout: Some("45-3-7-8".parse().unwrap()),
+            ..create_test_config()
+        };
+        assert_eq!(
+            IDGenerator::new(cfg).unwrap().capacity_per_second(),
+            255_000
+        );
+
+        generator.generate_ids_batch(10).unwrap();
+        let stats = generator.stats();
+        assert_eq!(stats.total_generated, 10);
+        assert_eq!(stats.capacity_per_second, 4095 * 1000);
+    }
+
+    #[test]
+    fn test_mint_backfill_id() {
+        let cfg = create_test_config();
+        let epoch = cfg.epoch;
+        let disabled = IDGenerator::new(cfg.clone()).unwrap();
+        assert!(matches!(
+            disabled.mint_backfill_id(epoch + 1_000, 0),
+            Err(TinyIdError::InvalidRequest(_))
+        ));
+
+        let now = epoch + 10_000;
+        let generator = IDGenerator::new(IdGeneratorConfig {
+            backfill_sequences: 16,
+            ..cfg
+        })
+        .unwrap()
+        .with_clock(MockClock::new(now));
+        assert_eq!(generator.capacity_per_second(), (4095 - 16) * 1000);
+
+        let id = generator.mint_backfill_id(now - 5_000, 3).unwrap();
+        let decoded = generator.decode_id(id);
+        assert_eq!(decoded.timestamp, now - 5_000);
+        assert_eq!(decoded.sequence, 4095 - 16 + 1 + 3);
+        assert_eq!(decoded.worker_id, 1);
+        assert!(decoded.backfill);
+
+        // 在线生成的ID不会落在保留段
+        let live = generator.generate_ids_batch(4000).unwrap();
+        assert!(live.iter().all(|&id| !generator.decode_id(id).backfill));
+        assert!(!generator.decode_id(generator.next_id().unwrap()).backfill);
+
+        for (timestamp, sequence) in [(now - 5_000, 16), (epoch - 1, 0), (now + 1, 0)] {
+            assert!(matches!(
+                generator.mint_backfill_id(timestamp, sequence),
+                Err(TinyIdError::InvalidRequest(_))
+            ));
+        }
+
+        let cfg = IdGeneratorConfig {
+            backfill_sequences: 4095,
+            ..create_test_config()
+        };
+        assert!(IDGenerator::new(cfg).is_err());
+    }
+
+    #[test]
+    fn test_total_generated_batched() {
+        let generator = Arc::new(
+            IDGenerator::new(IdGeneratorConfig {
+                total_generated_batch: 100,
+                ..create_test_config()
+            })
+            .unwrap(),
+        );
+
+        // 250 个中只有满批量的 200 个已写入共享计数
+        let counted = {
+            let generator = Arc::clone(&generator);
+            thread::spawn(move || {
+                for _ in 0..250 {
+                    generator.next_id().unwrap();
+                }
+                generator.stats().total_generated
+            })
+            .join()
+            .unwrap()
+        };
+        assert_eq!(counted, 200);
+        // 线程退出时补写剩余部分
+        assert_eq!(generator.stats().total_generated, 250);
+
+        generator.generate_ids_batch(150).unwrap();
+        assert_eq!(generator.stats().total_generated, 400);
+    }
+
+    #[test]
+    fn test_zero_datacenter_bits_round_trip() {
+        // 10 位工作节点、无数据中心位
+        let cfg = IdGeneratorConfig {
+            layout: Some("41-0-10-12".parse().unwrap()),
+            worker_id: WorkerId::new(1023, 1023).unwrap(),
+            datacenter_id: DatacenterId::new(0, 0).unwrap(),
+            ..create_test_config()
+        };
+        let generator = IDGenerator::new(cfg).unwrap();
+        assert_eq!(generator.layout_info().max_datacenter_id, 0);
+
+        let ids = generator.generate_ids_batch(10).unwrap();
+        for (i, &id) in ids.iter().enumerate() {
+            let decoded = generator.decode_id(id);
+            assert_eq!(decoded.datacenter_id, 0);
+            assert_eq!(decoded.worker_id, 1023);
+            assert_eq!(decoded.sequence, i as u32);
+            assert!(
+                decoded
+                    .timestamp
+                    .abs_diff(generator.clock.now_millis().unwrap())
+                    < 1000
+            );
+        }
+
+        // 数据中心位为 0 时只允许 datacenter_id = 0
+        let cfg = IdGeneratorConfig {
+            layout: Some("41-0-10-12".parse().unwrap()),
+            datacenter_id: DatacenterId::new(1, 1).unwrap(),
+            ..create_test_config()
+        };
+        assert!(IDGenerator::new(cfg).is_err());
+
+        // 只改位宽、保留旧的 max_datacenter_id 也会被拒绝
+        let cfg = IdGeneratorConfig {
+            datacenter_id_bits: 0,
+            worker_id_bits: 10,
+            ..create_test_config()
+        };
+        assert!(IDGenerator::new(cfg).is_err());
+    }
+
+    #[test]
+    fn test_clock_backwards_counted_once_per_call() {
+        let generator = IDGenerator::new(create_test_config()).unwrap();
+        let ahead = generator.get_current_timestamp().unwrap() + 10_000;
+        generator
+            .ts_seq
+            .store(ahead << generator.cfg.sequence_bits, Ordering::Release);
+
+        let deadline = Some(Instant::now() + Duration::from_millis(5));
+        assert!(generator.next_id_before(deadline).is_err());
+        assert!(generator.generate_ids_batch_before(2, deadline).is_err());
+        assert_eq!(generator.stats().clock_backwards, 2);
+    }
+
+    #[test]
+    fn test_stats_sample_since() {
+        let previous = GeneratorStats {
+            total_generated: 1_000,
+            random_generated: 0,
+            cas_retries: 10,
+            clock_backwards: 1,
+            sequence_waits: 0,
+            uptime_seconds: 10,
+            capacity_per_second: 4_000,
+            worker_id: 1,
+            datacenter_id: 1,
+        };
+        let current = GeneratorStats {
+            total_generated: 3_000,
+            cas_retries: 110,
+            clock_backwards: 3,
+            uptime_seconds: 12,
+            ..previous.clone()
+        };
+
+        let sample = current.sample_since(&previous, Duration::from_secs(2));
+        assert_eq!(sample.ids_per_second, 1_000.0);
+        assert_eq!(sample.cas_retries_per_id, 0.05);
+        assert_eq!(sample.sequence_utilization, 0.25);
+        assert_eq!(sample.clock_backwards, 2);
+
+        let idle = previous.sample_since(&previous, Duration::from_secs(2));
+        assert_eq!(idle.ids_per_second, 0.0);
+        assert_eq!(idle.cas_retries_per_id, 0.0);
+    }
+
+    #[test]
+    fn test_exhaustion_time() {
+        let generator = IDGenerator::new(create_test_config()).unwrap();
+        // 2022-01-01 起 41 位毫秒约 69.7 年
+        let exhausted = generator.exhaustion_time().unwrap();
+        assert_eq!(exhausted.to_rfc3339(), "2091-09-07T15:47:35.551+00:00");
+        generator.log_startup_summary();
+
+        let cfg = IdGeneratorConfig {
+            epoch: u64::MAX,
+            ..create_test_config()
+        };
+        assert_eq!(IDGenerator::new(cfg).unwrap().exhaustion_time(), None);
+    }
 }
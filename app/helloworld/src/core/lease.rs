@@ -0,0 +1,322 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use shared::config::{LeaseConfig, LeaseExpiryAction, ServerConfig, WorkerId};
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::TinyIdError;
+
+/// worker id 协调器
+///
+/// 接入 etcd/ZooKeeper 等外部协调服务时实现该 trait；`renew` 在 worker id
+/// 已被其它节点接手时必须返回错误，否则租约到期后会恢复发号而产生重复ID。
+pub trait WorkerIdCoordinator: Send + Sync {
+    /// 启动时租用一个 worker id
+    fn acquire(&self, ttl: Duration) -> impl Future<Output = Result<u32, TinyIdError>> + Send;
+
+    /// 续期已租到的 worker id
+    fn renew(
+        &self,
+        worker_id: u32,
+        ttl: Duration,
+    ) -> impl Future<Output = Result<(), TinyIdError>> + Send;
+}
+
+/// 固定 worker id 的协调器，续期总是成功，相当于没有外部协调服务
+#[derive(Debug, Clone, Copy)]
+pub struct StaticCoordinator {
+    worker_id: u32,
+}
+
+impl StaticCoordinator {
+    pub fn new(worker_id: u32) -> Self {
+        Self { worker_id }
+    }
+}
+
+impl WorkerIdCoordinator for StaticCoordinator {
+    async fn acquire(&self, _ttl: Duration) -> Result<u32, TinyIdError> {
+        Ok(self.worker_id)
+    }
+
+    async fn renew(&self, _worker_id: u32, _ttl: Duration) -> Result<(), TinyIdError> {
+        Ok(())
+    }
+}
+
+/// 已租到的 worker id 及其到期时间
+///
+/// 协调器不可达时继续使用已租到的 worker id，到期后 `check` 返回 `LeaseExpired`，
+/// 续期恢复后自动恢复服务。
+#[derive(Debug)]
+pub struct WorkerLease {
+    worker_id: u32,
+    ttl: Duration,
+    renew_interval: Duration,
+    on_expiry: LeaseExpiryAction,
+    start: Instant,
+    // 相对 start 的到期时刻（毫秒），只会向后推
+    expires_at_ms: AtomicU64,
+    // 已记录过到期日志，避免每个请求都打一条
+    expired_logged: AtomicBool,
+}
+
+impl WorkerLease {
+    /// 向协调器租用 worker id，租约从发出请求的时刻开始计算
+    pub async fn acquire<C: WorkerIdCoordinator>(
+        coordinator: &C,
+        cfg: &LeaseConfig,
+    ) -> Result<Self, TinyIdError> {
+        cfg.validate()
+            .map_err(|e| TinyIdError::ConfigError(e.to_string()))?;
+        let ttl = Duration::from_millis(cfg.ttl_ms);
+        let start = Instant::now();
+        let worker_id = coordinator.acquire(ttl).await?;
+        info!(worker_id, ttl_ms = cfg.ttl_ms, "Worker id leased");
+        Ok(Self {
+            worker_id,
+            ttl,
+            renew_interval: Duration::from_millis(cfg.renew_interval_ms),
+            on_expiry: cfg.on_expiry,
+            start,
+            expires_at_ms: AtomicU64::new(cfg.ttl_ms),
+            expired_logged: AtomicBool::new(false),
+        })
+    }
+
+    pub fn worker_id(&self) -> u32 {
+        self.worker_id
+    }
+
+    /// 距离到期的剩余时间，已到期时为 0
+    pub fn remaining(&self) -> Duration {
+        let expires_at = Duration::from_millis(self.expires_at_ms.load(Ordering::Acquire));
+        expires_at.saturating_sub(self.start.elapsed())
+    }
+
+    /// 租约仍有效时返回 Ok，到期后返回 `LeaseExpired`
+    pub fn check(&self) -> Result<(), TinyIdError> {
+        if !self.remaining().is_zero() {
+            return Ok(());
+        }
+        if !self.expired_logged.swap(true, Ordering::Relaxed) {
+            error!(
+                worker_id = self.worker_id,
+                "worker id lease expired, rejecting id generation"
+            );
+        }
+        Err(TinyIdError::LeaseExpired(self.worker_id))
+    }
+
+    /// 续期成功后把到期时间推到 `sent_at + ttl`
+    ///
+    /// 从发出请求而不是收到响应的时刻算起，协调器那边的租约不会早于本地到期。
+    fn extend(&self, sent_at: Instant) {
+        let expires_at = (sent_at - self.start + self.ttl).as_millis() as u64;
+        self.expires_at_ms.fetch_max(expires_at, Ordering::AcqRel);
+        if self.expired_logged.swap(false, Ordering::Relaxed) {
+            info!(
+                worker_id = self.worker_id,
+                "worker id lease renewed after expiry"
+            );
+        }
+    }
+
+    /// 按 `renew_interval_ms` 续期，单次续期最多等待一个续期间隔
+    ///
+    /// 续期失败只记录日志，下个周期重试；`on_expiry = "exit"` 时租约到期后返回，
+    /// 由调用方退出进程，否则永不返回。由调用方 spawn。
+    pub async fn renew_periodically<C: WorkerIdCoordinator>(&self, coordinator: &C) -> TinyIdError {
+        let mut ticker = tokio::time::interval(self.renew_interval);
+        // interval 的第一次 tick 立即返回，刚租到时不需要续期
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let sent_at = Instant::now();
+            let renewed = tokio::time::timeout(
+                self.renew_interval,
+                coordinator.renew(self.worker_id, self.ttl),
+            )
+            .await;
+            match renewed {
+                Ok(Ok(())) => self.extend(sent_at),
+                Ok(Err(e)) => warn!(
+                    worker_id = self.worker_id,
+                    remaining_ms = self.remaining().as_millis() as u64,
+                    error = %e,
+                    "failed to renew worker id lease"
+                ),
+                Err(_) => warn!(
+                    worker_id = self.worker_id,
+                    remaining_ms = self.remaining().as_millis() as u64,
+                    "worker id lease renewal timed out"
+                ),
+            }
+            if let Err(e) = self.check() {
+                if self.on_expiry == LeaseExpiryAction::Exit {
+                    return e;
+                }
+            }
+        }
+    }
+}
+
+/// 按 `lease` 配置租用 worker id，写回 `id_generator.worker_id` 并启动续期任务
+///
+/// 未配置 `lease` 时返回 None；`on_expiry = "exit"` 时租约到期后退出进程。
+/// 不能与 `worker_id_source` 同时配置，否则生成器会改用环境中解析出的 worker id，
+/// 而不是协调器分配的那个。
+pub async fn start_lease<C: WorkerIdCoordinator + 'static>(
+    coordinator: C,
+    cfg: &mut ServerConfig,
+) -> Result<Option<Arc<WorkerLease>>, TinyIdError> {
+    let Some(lease_cfg) = &cfg.lease else {
+        return Ok(None);
+    };
+    if let Some(source) = cfg.id_generator.worker_id_source {
+        return Err(TinyIdError::ConfigError(format!(
+            "lease cannot be combined with id_generator.worker_id_source = {:?}",
+            source
+        )));
+    }
+    let lease = Arc::new(WorkerLease::acquire(&coordinator, lease_cfg).await?);
+    cfg.id_generator.worker_id = WorkerId::new(lease.worker_id(), cfg.id_generator.max_worker_id)
+        .map_err(|e| TinyIdError::ConfigError(e.to_string()))?;
+    let renewing = Arc::clone(&lease);
+    tokio::spawn(async move {
+        let e = renewing.renew_periodically(&coordinator).await;
+        error!(error = %e, "exiting after worker id lease expired");
+        std::process::exit(1);
+    });
+    Ok(Some(lease))
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::config::WorkerIdSource;
+
+    use super::*;
+
+    /// 可以切换为不可达的协调器
+    #[derive(Debug, Default)]
+    struct FlakyCoordinator {
+        unreachable: AtomicBool,
+        renewals: AtomicU64,
+    }
+
+    impl WorkerIdCoordinator for FlakyCoordinator {
+        async fn acquire(&self, _ttl: Duration) -> Result<u32, TinyIdError> {
+            Ok(7)
+        }
+
+        async fn renew(&self, _worker_id: u32, _ttl: Duration) -> Result<(), TinyIdError> {
+            if self.unreachable.load(Ordering::Relaxed) {
+                return Err(TinyIdError::InternalError("coordinator unreachable".into()));
+            }
+            self.renewals.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    fn lease_config(on_expiry: LeaseExpiryAction) -> LeaseConfig {
+        LeaseConfig {
+            ttl_ms: 3_000,
+            renew_interval_ms: 1_000,
+            on_expiry,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lease_survives_blip_and_rejects_after_expiry() {
+        let coordinator = Arc::new(FlakyCoordinator::default());
+        let lease = Arc::new(
+            WorkerLease::acquire(&*coordinator, &lease_config(LeaseExpiryAction::Reject))
+                .await
+                .unwrap(),
+        );
+        assert_eq!(lease.worker_id(), 7);
+        let renewer = {
+            let (lease, coordinator) = (Arc::clone(&lease), Arc::clone(&coordinator));
+            tokio::spawn(async move { lease.renew_periodically(&*coordinator).await })
+        };
+
+        tokio::time::sleep(Duration::from_millis(2_500)).await;
+        assert_eq!(coordinator.renewals.load(Ordering::Relaxed), 2);
+
+        // 协调器短暂不可达：已租到的 worker id 继续可用
+        coordinator.unreachable.store(true, Ordering::Relaxed);
+        tokio::time::sleep(Duration::from_millis(2_000)).await;
+        assert!(lease.check().is_ok());
+
+        // 最后一次成功续期在 2s，3s 后到期
+        tokio::time::sleep(Duration::from_millis(1_000)).await;
+        assert!(matches!(lease.check(), Err(TinyIdError::LeaseExpired(7))));
+
+        // 协调器恢复并确认仍持有该 worker id 后恢复服务
+        coordinator.unreachable.store(false, Ordering::Relaxed);
+        tokio::time::sleep(Duration::from_millis(1_000)).await;
+        assert!(lease.check().is_ok());
+        assert!(!renewer.is_finished());
+        renewer.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lease_exit_returns_after_expiry() {
+        let coordinator = FlakyCoordinator::default();
+        coordinator.unreachable.store(true, Ordering::Relaxed);
+        let lease = WorkerLease::acquire(&coordinator, &lease_config(LeaseExpiryAction::Exit))
+            .await
+            .unwrap();
+
+        let started = Instant::now();
+        let err = lease.renew_periodically(&coordinator).await;
+        assert!(matches!(err, TinyIdError::LeaseExpired(7)));
+        assert_eq!(started.elapsed(), Duration::from_millis(3_000));
+    }
+
+    #[tokio::test]
+    async fn test_start_lease_writes_leased_worker_id() {
+        let mut cfg = ServerConfig::default_for_test();
+        assert!(start_lease(StaticCoordinator::new(3), &mut cfg)
+            .await
+            .unwrap()
+            .is_none());
+
+        cfg.lease = Some(LeaseConfig::default());
+        let lease = start_lease(StaticCoordinator::new(3), &mut cfg)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(lease.worker_id(), 3);
+        assert_eq!(cfg.id_generator.worker_id.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_start_lease_rejects_worker_id_source() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.lease = Some(LeaseConfig::default());
+        cfg.id_generator.worker_id_source = Some(WorkerIdSource::FromHostname);
+        let err = start_lease(StaticCoordinator::new(3), &mut cfg)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TinyIdError::ConfigError(_)));
+        // 没有写入租到的 worker id
+        assert_eq!(cfg.id_generator.worker_id.get(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_lease_config_is_rejected() {
+        let cfg = LeaseConfig {
+            ttl_ms: 1_000,
+            renew_interval_ms: 1_000,
+            ..LeaseConfig::default()
+        };
+        let err = WorkerLease::acquire(&StaticCoordinator::new(1), &cfg)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TinyIdError::ConfigError(_)));
+    }
+}
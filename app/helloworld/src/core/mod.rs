@@ -1,3 +1,13 @@
+pub mod audit;
+#[allow(clippy::module_inception)]
 pub mod core;
+pub mod lease;
+pub mod registry;
 
-pub use core::IDGenerator;
+pub use audit::{AuditSink, FileAuditSink, IdAuditor, IdRange, StdoutAuditSink};
+pub use core::{
+    Clock, DecodedId, GeneratedId, GeneratorSample, GeneratorStats, IDGenerator, LayoutInfo,
+    SystemClock,
+};
+pub use lease::{StaticCoordinator, WorkerIdCoordinator, WorkerLease};
+pub use registry::IdGeneratorRegistry;
@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::Result;
+use shared::config::{IdGeneratorConfig, WorkerId};
+use tracing::warn;
+
+use super::core::IDGenerator;
+use crate::TinyIdError;
+
+/// 按命名空间管理的生成器
+///
+/// 每个命名空间保存自己完整的 `IdGeneratorConfig`（含 epoch 和位布局），
+/// 拆解ID时必须用生成它的命名空间，不能套用全局 epoch。
+/// worker id 和 datacenter id 沿用本节点默认生成器解析（或租到）的值，
+/// 命名空间配置中的这两个字段和 `worker_id_source` 不生效。
+#[derive(Debug, Default)]
+pub struct IdGeneratorRegistry {
+    generators: BTreeMap<String, Arc<IDGenerator>>,
+}
+
+impl IdGeneratorRegistry {
+    /// 为每个命名空间创建生成器，节点ID取自 `node`；任一配置不合法时报错并带上命名空间名
+    pub fn new(configs: &BTreeMap<String, IdGeneratorConfig>, node: &IDGenerator) -> Result<Self> {
+        let (worker_id, datacenter_id) = (node.worker_id(), node.datacenter_id());
+        let mut generators = BTreeMap::new();
+        for (namespace, cfg) in configs {
+            if cfg.worker_id != worker_id || cfg.datacenter_id != datacenter_id {
+                warn!(
+                    namespace,
+                    worker_id = worker_id.get(),
+                    datacenter_id = datacenter_id.get(),
+                    "namespace node ids are ignored, using the node's ids"
+                );
+            }
+            let cfg = IdGeneratorConfig {
+                worker_id,
+                datacenter_id,
+                worker_id_source: None,
+                ..cfg.clone()
+            };
+            let generator = IDGenerator::new(cfg)
+                .map_err(|e| anyhow::anyhow!("namespace {}: {:#}", namespace, e))?;
+            generators.insert(namespace.clone(), Arc::new(generator));
+        }
+        Ok(Self { generators })
+    }
+
+    pub fn get(&self, namespace: &str) -> Option<&Arc<IDGenerator>> {
+        self.generators.get(namespace)
+    }
+
+    /// 已配置的命名空间，按名称排序
+    pub fn namespaces(&self) -> impl Iterator<Item = &str> {
+        self.generators.keys().map(String::as_str)
+    }
+
+    /// 新的 worker id 在每个命名空间的位宽内，切换前先检查，避免只切换了一部分
+    pub fn check_worker_id(&self, worker_id: u32) -> Result<(), TinyIdError> {
+        for generator in self.generators.values() {
+            WorkerId::new(worker_id, generator.layout_info().max_worker_id)
+                .map_err(|_| TinyIdError::InvalidWorkerId(worker_id))?;
+        }
+        Ok(())
+    }
+
+    /// 把所有命名空间切换到新的 worker id，返回切换后的注册表
+    ///
+    /// 某个命名空间等待时钟失败时保留它原来的生成器（已恢复可用），返回的注册表
+    /// 总是可以直接换上，同时返回第一个错误；再次切换到同一 worker id 即可补齐。
+    pub async fn retire_into_worker_id(
+        &self,
+        worker_id: u32,
+        deadline: Option<Instant>,
+    ) -> (Self, Result<(), TinyIdError>) {
+        let mut generators = BTreeMap::new();
+        let mut result = Ok(());
+        for (namespace, generator) in &self.generators {
+            let generator = match generator.retire_into_worker_id(worker_id, deadline).await {
+                Ok(rotated) => Arc::new(rotated),
+                Err(e) => {
+                    warn!(namespace = %namespace, error = %e, "failed to rotate namespace worker id");
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                    Arc::clone(generator)
+                }
+            };
+            generators.insert(namespace.clone(), generator);
+        }
+        (Self { generators }, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::config::{DatacenterId, WorkerIdSource};
+
+    use super::*;
+
+    fn config_with_epoch(epoch: u64) -> IdGeneratorConfig {
+        IdGeneratorConfig {
+            epoch,
+            ..IdGeneratorConfig::default()
+        }
+    }
+
+    fn node() -> IDGenerator {
+        IDGenerator::new(IdGeneratorConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_namespaces_keep_their_own_epoch() {
+        let legacy_epoch = 1577836800000; // 2020-01-01 00:00:00 UTC
+        let current_epoch = 1735689600000; // 2025-01-01 00:00:00 UTC
+        let configs = BTreeMap::from([
+            ("legacy".to_string(), config_with_epoch(legacy_epoch)),
+            ("orders".to_string(), config_with_epoch(current_epoch)),
+        ]);
+        let registry = IdGeneratorRegistry::new(&configs, &node()).unwrap();
+        assert_eq!(
+            registry.namespaces().collect::<Vec<_>>(),
+            ["legacy", "orders"]
+        );
+
+        let legacy = registry.get("legacy").unwrap();
+        let orders = registry.get("orders").unwrap();
+        assert_eq!(legacy.layout_info().epoch, legacy_epoch);
+        assert_eq!(orders.layout_info().epoch, current_epoch);
+
+        // 同一毫秒生成的ID，各自按自己的 epoch 拆解出相同的时间
+        let legacy_id = legacy.next_id().unwrap();
+        let orders_id = orders.next_id().unwrap();
+        let legacy_ts = legacy.decode_id(legacy_id).timestamp;
+        let orders_ts = orders.decode_id(orders_id).timestamp;
+        assert!(legacy_ts.abs_diff(orders_ts) < 1_000);
+        // 套用另一个命名空间的 epoch 会差出整整 5 年
+        let wrong_ts = orders.decode_id(legacy_id).timestamp;
+        assert!(wrong_ts - legacy_ts >= current_epoch - legacy_epoch - 1_000);
+
+        assert!(registry.get("missing").is_none());
+    }
+
+    #[test]
+    fn test_invalid_namespace_config_names_the_namespace() {
+        let cfg = IdGeneratorConfig {
+            sequence_bits: 40,
+            ..IdGeneratorConfig::default()
+        };
+        let configs = BTreeMap::from([("broken".to_string(), cfg)]);
+        let err = IdGeneratorRegistry::new(&configs, &node()).unwrap_err();
+        assert!(err.to_string().contains("namespace broken"));
+    }
+
+    #[test]
+    fn test_namespaces_inherit_node_ids() {
+        let node = IDGenerator::new(IdGeneratorConfig {
+            worker_id: WorkerId::new(9, 127).unwrap(),
+            datacenter_id: DatacenterId::new(2, 7).unwrap(),
+            ..IdGeneratorConfig::default()
+        })
+        .unwrap();
+        // 多个副本共用同一份配置文件时，命名空间里写死的节点ID会在副本间重复
+        let shared = IdGeneratorConfig {
+            worker_id_source: Some(WorkerIdSource::FromHostname),
+            ..config_with_epoch(1577836800000)
+        };
+        let configs = BTreeMap::from([("legacy".to_string(), shared)]);
+        let registry = IdGeneratorRegistry::new(&configs, &node).unwrap();
+
+        let legacy = registry.get("legacy").unwrap();
+        let decoded = legacy.decode_id(legacy.next_id().unwrap());
+        assert_eq!(decoded.worker_id, 9);
+        assert_eq!(decoded.datacenter_id, 2);
+    }
+
+    #[tokio::test]
+    async fn test_rotate_namespaces() {
+        let narrow = IdGeneratorConfig {
+            layout: Some("43-3-5-12".parse().unwrap()),
+            ..IdGeneratorConfig::default()
+        };
+        let configs = BTreeMap::from([
+            ("legacy".to_string(), config_with_epoch(1577836800000)),
+            ("narrow".to_string(), narrow),
+        ]);
+        let registry = IdGeneratorRegistry::new(&configs, &node()).unwrap();
+        // narrow 只有 5 位 worker id，整体拒绝，不会只切换一部分
+        assert!(matches!(
+            registry.check_worker_id(100),
+            Err(TinyIdError::InvalidWorkerId(100))
+        ));
+        registry.check_worker_id(5).unwrap();
+
+        let (rotated, result) = registry.retire_into_worker_id(5, None).await;
+        result.unwrap();
+        for namespace in ["legacy", "narrow"] {
+            let old = registry.get(namespace).unwrap();
+            assert!(matches!(old.next_id(), Err(TinyIdError::GeneratorRetired)));
+            let new = rotated.get(namespace).unwrap();
+            assert_eq!(new.decode_id(new.next_id().unwrap()).worker_id, 5);
+        }
+    }
+}
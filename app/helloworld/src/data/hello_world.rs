@@ -1,14 +1,22 @@
+use std::future::Future;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
+use arc_swap::ArcSwap;
 use shared::proto::user::{user_demo_client::UserDemoClient, GetUserRequest, User};
-use tonic::{transport::Channel, Request};
+use tonic::{transport::Channel, Code, Request};
 use tracing::{error, instrument};
 
 use crate::biz::{HelloWorldRepo, UserDemoRepo};
-use crate::core::IDGenerator;
+use crate::core::{
+    DecodedId, GeneratorStats, IDGenerator, IdGeneratorRegistry, LayoutInfo, WorkerLease,
+};
 use crate::TinyIdError;
 
+/// 旧生成器停用后重新加载的间隔
+const RETIRED_RETRY_INTERVAL: Duration = Duration::from_micros(200);
+
 /// 高性能ID生成器
 ///
 /// 基于雪花算法的优化版本，支持：
@@ -20,14 +28,108 @@ use crate::TinyIdError;
 /// - 本地缓存
 #[derive(Debug, Clone)]
 pub struct HelloWorldRepoImpl {
-    ig: Arc<IDGenerator>,
+    // 切换 worker id 时整体替换生成器；拿着旧实例的请求会收到 GeneratorRetired 并重新加载
+    ig: Arc<ArcSwap<IDGenerator>>,
+    // 串行化 worker id 切换，等待时钟期间不阻塞工作线程
+    rotate_lock: Arc<tokio::sync::Mutex<()>>,
+    // 按命名空间独立配置的生成器，与默认生成器一起切换 worker id
+    namespaces: Arc<ArcSwap<IdGeneratorRegistry>>,
+    // 从协调器租到的 worker id，到期后拒绝生成
+    lease: Option<Arc<WorkerLease>>,
     user_client: UserDemoClient<Channel>,
 }
 
 impl HelloWorldRepo for HelloWorldRepoImpl {
     #[instrument(skip(self))]
-    async fn generate_id(&self) -> Result<u64, TinyIdError> {
-        self.ig.next_id()
+    async fn generate_id(&self, deadline: Option<Instant>) -> Result<u64, TinyIdError> {
+        self.check_lease()?;
+        self.retry_retired(
+            deadline,
+            || Ok(self.generator()),
+            |ig| async move { ig.next_id_before_async(deadline).await },
+        )
+        .await
+    }
+
+    #[instrument(skip(self))]
+    async fn generate_ids(
+        &self,
+        count: usize,
+        deadline: Option<Instant>,
+    ) -> Result<Vec<u64>, TinyIdError> {
+        self.check_lease()?;
+        self.retry_retired(
+            deadline,
+            || Ok(self.generator()),
+            |ig| async move { ig.generate_ids_batch_before(count, deadline) },
+        )
+        .await
+    }
+
+    fn decode_id(&self, id: u64) -> DecodedId {
+        self.generator().decode_id(id)
+    }
+
+    #[instrument(skip(self))]
+    async fn generate_id_in(
+        &self,
+        namespace: &str,
+        deadline: Option<Instant>,
+    ) -> Result<u64, TinyIdError> {
+        self.check_lease()?;
+        self.retry_retired(
+            deadline,
+            || self.namespace(namespace),
+            |ig| async move { ig.next_id_before_async(deadline).await },
+        )
+        .await
+    }
+
+    fn decode_id_in(&self, namespace: &str, id: u64) -> Result<DecodedId, TinyIdError> {
+        Ok(self.namespace(namespace)?.decode_id(id))
+    }
+
+    fn layout(&self) -> LayoutInfo {
+        self.generator().layout_info()
+    }
+
+    fn stats(&self) -> GeneratorStats {
+        self.generator().stats()
+    }
+
+    fn mint_backfill_id(&self, timestamp: u64, sequence: u32) -> Result<u64, TinyIdError> {
+        self.check_lease()?;
+        self.generator().mint_backfill_id(timestamp, sequence)
+    }
+
+    fn save_state(&self) -> Result<(), TinyIdError> {
+        self.generator().save_state()
+    }
+
+    #[instrument(skip(self))]
+    async fn rotate_worker_id(
+        &self,
+        worker_id: u32,
+        deadline: Option<Instant>,
+    ) -> Result<u32, TinyIdError> {
+        // 租到的 worker id 由协调器分配，手动切换会和其它节点冲突
+        if let Some(lease) = &self.lease {
+            return Err(TinyIdError::InvalidRequest(format!(
+                "worker id {} is leased from the coordinator and cannot be rotated manually",
+                lease.worker_id()
+            )));
+        }
+        let _guard = self.rotate_lock.lock().await;
+        let namespaces = self.namespaces.load_full();
+        namespaces.check_worker_id(worker_id)?;
+        let current = self.generator();
+        let rotated = current.retire_into_worker_id(worker_id, deadline).await?;
+        self.ig.store(Arc::new(rotated));
+        // 命名空间沿用节点的 worker id，一起切换
+        let (rotated, result) = namespaces.retire_into_worker_id(worker_id, deadline).await;
+        self.namespaces.store(Arc::new(rotated));
+        result?;
+        Ok(current.worker_id().get())
     }
 }
 
@@ -40,25 +142,83 @@ impl UserDemoRepo for HelloWorldRepoImpl {
             .get_user(Request::new(GetUserRequest { id }))
             .await;
         match resp {
-            Ok(resp) => resp
-                .into_inner()
-                .user
-                .ok_or(TinyIdError::UserServiceError("user not found".to_string())),
+            Ok(resp) => resp.into_inner().user.ok_or(TinyIdError::UserNotFound(id)),
+            Err(e) if e.code() == Code::NotFound => Err(TinyIdError::UserNotFound(id)),
             Err(e) => {
                 error!("get user failed: {}", e);
-                Err(TinyIdError::UserServiceError(e.to_string()))
+                Err(TinyIdError::user_service("get user failed", e))
             }
         }
     }
 }
 
-impl<'a> HelloWorldRepoImpl {
+impl HelloWorldRepoImpl {
     pub fn new(generator: Arc<IDGenerator>, user_client: UserDemoClient<Channel>) -> Result<Self> {
         Ok(Self {
-            ig: generator,
+            ig: Arc::new(ArcSwap::new(generator)),
+            rotate_lock: Arc::new(tokio::sync::Mutex::new(())),
+            namespaces: Arc::new(ArcSwap::from_pointee(IdGeneratorRegistry::default())),
+            lease: None,
             user_client,
         })
     }
+
+    /// 挂载按命名空间配置的生成器，应由 `IdGeneratorRegistry::new` 以默认生成器为节点创建
+    pub fn with_namespaces(mut self, namespaces: IdGeneratorRegistry) -> Self {
+        self.namespaces = Arc::new(ArcSwap::from_pointee(namespaces));
+        self
+    }
+
+    /// 生成前检查 worker id 租约，租约到期后拒绝生成
+    pub fn with_lease(mut self, lease: Arc<WorkerLease>) -> Self {
+        self.lease = Some(lease);
+        self
+    }
+
+    fn check_lease(&self) -> Result<(), TinyIdError> {
+        match &self.lease {
+            Some(lease) => lease.check(),
+            None => Ok(()),
+        }
+    }
+
+    fn namespace(&self, namespace: &str) -> Result<Arc<IDGenerator>, TinyIdError> {
+        self.namespaces
+            .load()
+            .get(namespace)
+            .cloned()
+            .ok_or_else(|| TinyIdError::InvalidRequest(format!("unknown namespace: {}", namespace)))
+    }
+
+    /// 当前生效的生成器
+    fn generator(&self) -> Arc<IDGenerator> {
+        self.ig.load_full()
+    }
+
+    /// 切换 worker id 期间旧实例已停用、新实例尚未换上，短暂等待后重新 `load` 并重试
+    async fn retry_retired<T, L, F, Fut>(
+        &self,
+        deadline: Option<Instant>,
+        load: L,
+        f: F,
+    ) -> Result<T, TinyIdError>
+    where
+        L: Fn() -> Result<Arc<IDGenerator>, TinyIdError>,
+        F: Fn(Arc<IDGenerator>) -> Fut,
+        Fut: Future<Output = Result<T, TinyIdError>>,
+    {
+        loop {
+            match f(load()?).await {
+                Err(TinyIdError::GeneratorRetired) => {
+                    if deadline.is_some_and(|d| Instant::now() >= d) {
+                        return Err(TinyIdError::DeadlineExceeded);
+                    }
+                    tokio::time::sleep(RETIRED_RETRY_INTERVAL).await;
+                }
+                result => return result,
+            }
+        }
+    }
 }
 
 // #[cfg(test)]
@@ -1,12 +1,32 @@
+use std::sync::Arc;
+
+#[cfg(all(feature = "http", feature = "grpc"))]
+use axum::response::IntoResponse;
 use thiserror::Error;
 
+#[cfg(all(feature = "http", feature = "grpc"))]
+use crate::service::response::{ErrCode, Response};
+
+/// 底层错误源
+///
+/// `TinyIdError` 需要实现 `Clone`，因此使用 `Arc` 而不是 `Box` 来保存原始错误，
+/// 以便 `anyhow`/日志能够通过 `source()` 展示完整的错误链。
+pub type ErrorSource = Arc<dyn std::error::Error + Send + Sync + 'static>;
+
 #[derive(Error, Debug, Clone)]
 pub enum TinyIdError {
     #[error("ID generation failed: {0}")]
     IdGenerationFailed(String),
 
-    #[error("User service error: {0}")]
-    UserServiceError(String),
+    #[error("User service error: {message}")]
+    UserServiceError {
+        message: String,
+        #[source]
+        source: Option<ErrorSource>,
+    },
+
+    #[error("User not found: {0}")]
+    UserNotFound(u64),
 
     #[error("Internal error: {0}")]
     InternalError(String),
@@ -26,69 +46,170 @@ pub enum TinyIdError {
     #[error("configuration error:{0}")]
     ConfigError(String),
 
-    #[error("Server error: {0}")]
-    ServerError(String),
+    #[error("Server error: {message}")]
+    ServerError {
+        message: String,
+        #[source]
+        source: Option<ErrorSource>,
+    },
+
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+
+    #[error("ID generator retired after worker id rotation")]
+    GeneratorRetired,
+
+    #[error("Worker id {0} lease expired, refusing to generate ids")]
+    LeaseExpired(u32),
+
+    #[error("Batch too large: requested {count}, max {max}")]
+    BatchTooLarge { count: usize, max: usize },
+
+    #[error("I/O error")]
+    Io(#[source] Arc<std::io::Error>),
 }
 
+impl TinyIdError {
+    /// 构造携带底层错误的 `ServerError`
+    pub fn server(
+        message: impl Into<String>,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        TinyIdError::ServerError {
+            message: message.into(),
+            source: Some(Arc::new(source)),
+        }
+    }
+
+    /// 变体名，用于日志和错误采样中的错误分类
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TinyIdError::IdGenerationFailed(_) => "IdGenerationFailed",
+            TinyIdError::UserServiceError { .. } => "UserServiceError",
+            TinyIdError::UserNotFound(_) => "UserNotFound",
+            TinyIdError::InternalError(_) => "InternalError",
+            TinyIdError::ClockMovedBackwards(_) => "ClockMovedBackwards",
+            TinyIdError::InvalidRequest(_) => "InvalidRequest",
+            TinyIdError::InvalidWorkerId(_) => "InvalidWorkerId",
+            TinyIdError::InvalidDatacenterId(_) => "InvalidDatacenterId",
+            TinyIdError::ConfigError(_) => "ConfigError",
+            TinyIdError::ServerError { .. } => "ServerError",
+            TinyIdError::DeadlineExceeded => "DeadlineExceeded",
+            TinyIdError::GeneratorRetired => "GeneratorRetired",
+            TinyIdError::LeaseExpired(_) => "LeaseExpired",
+            TinyIdError::BatchTooLarge { .. } => "BatchTooLarge",
+            TinyIdError::Io(_) => "Io",
+        }
+    }
+
+    /// 构造携带底层错误的 `UserServiceError`
+    pub fn user_service(
+        message: impl Into<String>,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        TinyIdError::UserServiceError {
+            message: message.into(),
+            source: Some(Arc::new(source)),
+        }
+    }
+}
+
+#[cfg(all(feature = "http", feature = "grpc"))]
+impl TinyIdError {
+    /// 对应的业务错误码，HTTP 状态码由错误码决定
+    pub fn err_code(&self) -> ErrCode {
+        match self {
+            TinyIdError::InvalidRequest(_)
+            | TinyIdError::InvalidWorkerId(_)
+            | TinyIdError::InvalidDatacenterId(_) => ErrCode::BadRequest,
+            TinyIdError::BatchTooLarge { .. } => ErrCode::PayloadTooLarge,
+            TinyIdError::DeadlineExceeded => ErrCode::GatewayTimeout,
+            TinyIdError::GeneratorRetired | TinyIdError::LeaseExpired(_) => {
+                ErrCode::ServiceUnavailable
+            }
+            TinyIdError::UserNotFound(_) => ErrCode::NotFound,
+            TinyIdError::UserServiceError { .. } => ErrCode::BadGateway,
+            TinyIdError::ConfigError(_) => ErrCode::ConfigError,
+            TinyIdError::IdGenerationFailed(_)
+            | TinyIdError::ClockMovedBackwards(_)
+            | TinyIdError::ServerError { .. }
+            | TinyIdError::InternalError(_)
+            | TinyIdError::Io(_) => ErrCode::InternalServerError,
+        }
+    }
+}
+
+/// 与各接口的 `Response<T>` 使用同一种错误结构
+#[cfg(all(feature = "http", feature = "grpc"))]
+impl IntoResponse for TinyIdError {
+    fn into_response(self) -> axum::response::Response {
+        Response::<()>::failed(self.err_code(), Some(self.to_string())).into_response()
+    }
+}
+
+#[cfg(all(feature = "http", feature = "grpc"))]
 impl From<TinyIdError> for axum::response::Response<axum::body::Body> {
     fn from(err: TinyIdError) -> Self {
-        use axum::response::{IntoResponse, Json};
-        use shared::proto::ApiResponse;
-
-        let (status, code, message) = match err {
-            TinyIdError::InvalidRequest(_) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                5002,
-                err.to_string(),
-            ),
-            TinyIdError::InvalidDatacenterId(_) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                5002,
-                err.to_string(),
-            ),
-            TinyIdError::ConfigError(_) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                5002,
-                err.to_string(),
-            ),
-            TinyIdError::ServerError(_) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                5002,
-                err.to_string(),
-            ),
-
-            TinyIdError::InvalidWorkerId(_) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                5002,
-                err.to_string(),
-            ),
-            TinyIdError::ClockMovedBackwards(_) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                5002,
-                err.to_string(),
-            ),
-            TinyIdError::IdGenerationFailed(_) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                5001,
-                err.to_string(),
-            ),
-            TinyIdError::UserServiceError(_) => {
-                (axum::http::StatusCode::BAD_GATEWAY, 5002, err.to_string())
-            }
-            TinyIdError::InternalError(_) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                5000,
-                err.to_string(),
-            ),
-        };
-
-        let response: ApiResponse<()> = ApiResponse::error(code, message);
-        (status, Json(response)).into_response()
+        err.into_response()
     }
 }
 
 impl From<std::io::Error> for TinyIdError {
     fn from(err: std::io::Error) -> Self {
-        TinyIdError::InternalError(err.to_string())
+        TinyIdError::Io(Arc::new(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::*;
+
+    #[test]
+    fn test_server_error_preserves_source() {
+        let io_err = std::io::Error::new(std::io::ErrorKind::AddrInUse, "address already in use");
+        let err = TinyIdError::server("failed to bind 0.0.0.0:8080", io_err);
+
+        assert_eq!(err.to_string(), "Server error: failed to bind 0.0.0.0:8080");
+        let source = err.source().expect("source should be preserved");
+        assert_eq!(source.to_string(), "address already in use");
+
+        // anyhow 能够展示完整的错误链
+        let chain = format!("{:#}", anyhow::Error::new(err));
+        assert!(chain.contains("failed to bind 0.0.0.0:8080"));
+        assert!(chain.contains("address already in use"));
+    }
+
+    #[cfg(all(feature = "http", feature = "grpc"))]
+    #[tokio::test]
+    async fn test_into_response_uses_err_code_envelope() {
+        let response = TinyIdError::InvalidRequest("bad sequence".to_string()).into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], 400);
+        assert_eq!(body["msg"], "Invalid request: bad sequence");
+
+        let response: axum::response::Response = TinyIdError::DeadlineExceeded.into();
+        assert_eq!(response.status(), axum::http::StatusCode::GATEWAY_TIMEOUT);
+        let response = TinyIdError::BatchTooLarge { count: 2, max: 1 }.into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+        let response = TinyIdError::ConfigError("x".to_string()).into_response();
+        assert_eq!(
+            response.status(),
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn test_io_error_conversion_keeps_source() {
+        let err: TinyIdError =
+            std::io::Error::new(std::io::ErrorKind::PermissionDenied, "permission denied").into();
+
+        assert!(matches!(err, TinyIdError::Io(_)));
+        assert_eq!(err.source().unwrap().to_string(), "permission denied");
     }
 }
@@ -0,0 +1,126 @@
+//! base62 编解码（字母表 `0-9A-Za-z`，按 ASCII 排序）
+//!
+//! 等长的编码按字符串排序与数值排序一致。热路径不做堆分配：编码写入栈上的定长缓冲区，
+//! 解码用查表代替逐字符匹配。
+
+use crate::error::TinyIdError;
+
+/// u64 编码后的最大长度（62^11 > 2^64）
+pub const MAX_LEN: usize = 11;
+
+const ALPHABET: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// 非法字符在反查表中的取值
+const INVALID: u8 = u8::MAX;
+
+/// 字符到数值的反查表
+const DECODE_TABLE: [u8; 256] = {
+    let mut table = [INVALID; 256];
+    let mut i = 0;
+    while i < ALPHABET.len() {
+        table[ALPHABET[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+};
+
+/// 编码到调用方提供的缓冲区，返回其中有效的部分（右对齐），不做堆分配
+pub fn encode_into(id: u64, buf: &mut [u8; MAX_LEN]) -> &str {
+    let mut n = id;
+    let mut start = MAX_LEN;
+    loop {
+        start -= 1;
+        buf[start] = ALPHABET[(n % 62) as usize];
+        n /= 62;
+        if n == 0 {
+            break;
+        }
+    }
+    std::str::from_utf8(&buf[start..]).expect("base62 alphabet is ascii")
+}
+
+/// 编码为字符串，只分配一次
+pub fn encode(id: u64) -> String {
+    let mut buf = [0u8; MAX_LEN];
+    encode_into(id, &mut buf).to_owned()
+}
+
+/// 解码，空串、非法字符或超出 u64 范围时返回 `InvalidRequest`
+pub fn decode(s: &str) -> Result<u64, TinyIdError> {
+    if s.is_empty() || s.len() > MAX_LEN {
+        return Err(TinyIdError::InvalidRequest(format!(
+            "invalid base62 id length {}, expected 1..={}",
+            s.len(),
+            MAX_LEN
+        )));
+    }
+    let mut id: u64 = 0;
+    for (i, &b) in s.as_bytes().iter().enumerate() {
+        let digit = DECODE_TABLE[b as usize];
+        if digit == INVALID {
+            return Err(TinyIdError::InvalidRequest(format!(
+                "invalid base62 character at position {}",
+                i
+            )));
+        }
+        id = id
+            .checked_mul(62)
+            .and_then(|id| id.checked_add(digit as u64))
+            .ok_or_else(|| {
+                TinyIdError::InvalidRequest(format!("base62 id {} is out of range", s))
+            })?;
+    }
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_values() {
+        assert_eq!(encode(0), "0");
+        assert_eq!(encode(61), "z");
+        assert_eq!(encode(62), "10");
+        assert_eq!(encode(u64::MAX), "LygHa16AHYF");
+        assert_eq!(decode("LygHa16AHYF").unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn test_encode_into_reuses_buffer() {
+        let mut buf = [0u8; MAX_LEN];
+        assert_eq!(encode_into(u64::MAX, &mut buf), "LygHa16AHYF");
+        // 短的结果覆盖之前的尾部，不受残留内容影响
+        assert_eq!(encode_into(62, &mut buf), "10");
+    }
+
+    #[test]
+    fn test_round_trip_and_order() {
+        let ids = [1, 1234567890123456789, 1 << 40, (1 << 40) + 1, u64::MAX - 1];
+        for id in ids {
+            assert_eq!(decode(&encode(id)).unwrap(), id);
+        }
+        let a = encode(1 << 40);
+        let b = encode((1 << 40) + 1);
+        assert_eq!(a.len(), b.len());
+        assert!(a < b);
+    }
+
+    #[test]
+    fn test_decode_rejects_invalid_input() {
+        for bad in [
+            "",
+            "abc-def",
+            "id_1",
+            "中",
+            "LygHa16AHYG",
+            "zzzzzzzzzzz",
+            "000000000000",
+        ] {
+            assert!(
+                matches!(decode(bad), Err(TinyIdError::InvalidRequest(_))),
+                "{bad}"
+            );
+        }
+    }
+}
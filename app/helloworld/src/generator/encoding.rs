@@ -0,0 +1,191 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use super::base62;
+use crate::error::TinyIdError;
+
+/// u64 十进制最大宽度
+const PADDED_WIDTH: usize = 20;
+
+/// ID 对外的编码格式，通过 `?encoding=` 指定
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdEncoding {
+    /// JSON 数字（默认）
+    #[default]
+    Decimal,
+    /// 20 位补零的十进制字符串，按字符串排序与数值排序一致
+    Padded,
+    /// 8 字节大端序，JSON 中为 base64 字符串，按字节排序与数值排序一致
+    Bytes,
+    /// base62 字符串（`0-9A-Za-z`），最长 11 位，适合短链接；不补齐，只有等长时才按数值排序
+    Base62,
+}
+
+/// 编码后的ID，序列化时不带标签：数字或字符串
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EncodedId {
+    Number(u64),
+    Text(String),
+}
+
+impl EncodedId {
+    /// 加上配置的前缀，数字形式的ID随之变为字符串
+    pub fn with_prefix(self, prefix: &str) -> EncodedId {
+        match self {
+            EncodedId::Number(id) => EncodedId::Text(format!("{}{}", prefix, id)),
+            EncodedId::Text(text) => EncodedId::Text(format!("{}{}", prefix, text)),
+        }
+    }
+}
+
+/// 去掉并校验ID前缀；配置了前缀时ID必须带该前缀，未配置时原样返回
+pub fn strip_prefix<'a>(prefix: Option<&str>, encoded: &'a str) -> Result<&'a str, TinyIdError> {
+    match prefix {
+        Some(prefix) => encoded.strip_prefix(prefix).ok_or_else(|| {
+            TinyIdError::InvalidRequest(format!(
+                "id must start with prefix {:?}: {}",
+                prefix, encoded
+            ))
+        }),
+        None => Ok(encoded),
+    }
+}
+
+impl IdEncoding {
+    pub fn encode(self, id: u64) -> EncodedId {
+        match self {
+            IdEncoding::Decimal => EncodedId::Number(id),
+            IdEncoding::Padded => EncodedId::Text(format!("{:0width$}", id, width = PADDED_WIDTH)),
+            IdEncoding::Bytes => EncodedId::Text(BASE64.encode(id.to_be_bytes())),
+            IdEncoding::Base62 => EncodedId::Text(base62::encode(id)),
+        }
+    }
+
+    /// 解析按当前格式编码的ID
+    pub fn decode(self, encoded: &str) -> Result<u64, TinyIdError> {
+        if self == IdEncoding::Base62 {
+            return base62::decode(encoded);
+        }
+        if self == IdEncoding::Bytes {
+            let bytes = BASE64
+                .decode(encoded)
+                .ok()
+                .and_then(|bytes| <[u8; 8]>::try_from(bytes).ok())
+                .ok_or_else(|| {
+                    TinyIdError::InvalidRequest(format!(
+                        "bytes id must be 8 base64-encoded bytes: {}",
+                        encoded
+                    ))
+                })?;
+            return Ok(u64::from_be_bytes(bytes));
+        }
+        if self == IdEncoding::Padded && encoded.len() != PADDED_WIDTH {
+            return Err(TinyIdError::InvalidRequest(format!(
+                "padded id must be {} digits: {}",
+                PADDED_WIDTH, encoded
+            )));
+        }
+        if !encoded.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(TinyIdError::InvalidRequest(format!(
+                "invalid decimal id: {}",
+                encoded
+            )));
+        }
+        encoded
+            .parse()
+            .map_err(|_| TinyIdError::InvalidRequest(format!("id out of range: {}", encoded)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_padded_round_trip() {
+        for id in [0, 9, 10, u64::MAX] {
+            let EncodedId::Text(text) = IdEncoding::Padded.encode(id) else {
+                panic!("padded encoding should produce a string");
+            };
+            assert_eq!(text.len(), 20);
+            assert_eq!(IdEncoding::Padded.decode(&text).unwrap(), id);
+        }
+    }
+
+    #[test]
+    fn test_padded_sorts_like_numbers() {
+        let encode = |id| match IdEncoding::Padded.encode(id) {
+            EncodedId::Text(text) => text,
+            EncodedId::Number(_) => unreachable!(),
+        };
+        assert!(encode(9) < encode(10));
+        assert!(encode(123456789) < encode(1234567890));
+    }
+
+    #[test]
+    fn test_decimal_serializes_as_number() {
+        let json = serde_json::to_value(IdEncoding::Decimal.encode(42)).unwrap();
+        assert_eq!(json, serde_json::json!(42));
+        assert_eq!(IdEncoding::Decimal.decode("42").unwrap(), 42);
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        assert_eq!(
+            IdEncoding::Bytes.encode(1),
+            EncodedId::Text("AAAAAAAAAAE=".to_string())
+        );
+        for id in [0, 1, 1 << 40, u64::MAX] {
+            let EncodedId::Text(text) = IdEncoding::Bytes.encode(id) else {
+                panic!("bytes encoding should produce a string");
+            };
+            assert_eq!(IdEncoding::Bytes.decode(&text).unwrap(), id);
+        }
+    }
+
+    #[test]
+    fn test_base62_round_trip() {
+        assert_eq!(
+            IdEncoding::Base62.encode(0),
+            EncodedId::Text("0".to_string())
+        );
+        let random: Vec<u64> = (0..32).map(|_| rand::random()).collect();
+        for id in [0, 1, 61, 62, u64::MAX].into_iter().chain(random) {
+            let EncodedId::Text(text) = IdEncoding::Base62.encode(id) else {
+                panic!("base62 encoding should produce a string");
+            };
+            assert_eq!(IdEncoding::Base62.decode(&text).unwrap(), id);
+        }
+        assert!(matches!(
+            IdEncoding::Base62.decode("abc+def"),
+            Err(TinyIdError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_prefix_round_trip() {
+        let encoded = IdEncoding::Decimal.encode(42).with_prefix("ord_");
+        assert_eq!(encoded, EncodedId::Text("ord_42".to_string()));
+        let EncodedId::Text(text) = encoded else {
+            unreachable!()
+        };
+        let digits = strip_prefix(Some("ord_"), &text).unwrap();
+        assert_eq!(IdEncoding::Decimal.decode(digits).unwrap(), 42);
+
+        assert!(strip_prefix(Some("ord_"), "usr_42").is_err());
+        assert!(strip_prefix(Some("ord_"), "42").is_err());
+        assert_eq!(strip_prefix(None, "42").unwrap(), "42");
+    }
+
+    #[test]
+    fn test_decode_rejects_invalid_input() {
+        assert!(IdEncoding::Bytes.decode("AAAA").is_err());
+        assert!(IdEncoding::Bytes.decode("not base64!").is_err());
+        assert!(IdEncoding::Padded.decode("42").is_err());
+        assert!(IdEncoding::Decimal.decode("-1").is_err());
+        assert!(IdEncoding::Decimal.decode("99999999999999999999").is_err());
+    }
+}
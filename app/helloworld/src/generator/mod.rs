@@ -0,0 +1,25 @@
+//! 可独立依赖的ID生成库接口
+//!
+//! 只依赖生成器核心和配置，不会引入 axum/tonic；关闭默认的 `http`/`grpc` feature 即可
+//! 把 tinyid 当作普通库使用：
+//!
+//! ```toml
+//! tinyid = { path = "...", default-features = false }
+//! ```
+pub mod base62;
+pub mod encoding;
+pub mod snowflake;
+pub mod ulid;
+pub mod uuid_v7;
+
+pub use base62::{decode as decode_base62, encode as encode_base62};
+pub use encoding::{EncodedId, IdEncoding};
+pub use shared::config::{
+    BitLayout, ClockRollbackPolicy, DatacenterId, Environment, IdGeneratorConfig, WorkerId,
+};
+pub use snowflake::Snowflake;
+pub use ulid::{generate_ulid, UlidGenerator};
+pub use uuid_v7::{generate_uuidv7, UuidV7Generator};
+
+pub use crate::core::{Clock, DecodedId, GeneratedId, GeneratorStats, LayoutInfo, SystemClock};
+pub use crate::error::TinyIdError;
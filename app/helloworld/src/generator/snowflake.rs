@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use shared::config::IdGeneratorConfig;
+
+use super::encoding::{EncodedId, IdEncoding};
+use crate::core::{Clock, DecodedId, GeneratorStats, IDGenerator, LayoutInfo};
+use crate::error::TinyIdError;
+
+/// 雪花ID生成器，[`IDGenerator`] 面向库使用者的精简封装
+///
+/// 线程安全，多个线程可共享同一个实例（如放进 `Arc`）；同一 worker/datacenter 组合
+/// 在同一时刻只能有一个实例，否则会生成重复ID。
+#[derive(Debug)]
+pub struct Snowflake {
+    inner: IDGenerator,
+}
+
+impl Snowflake {
+    /// 按配置创建生成器，配置不合法（位宽超限、节点ID越界等）时返回错误
+    pub fn new(config: IdGeneratorConfig) -> Result<Self, TinyIdError> {
+        let inner = IDGenerator::new(config).map_err(|e| match e.downcast::<TinyIdError>() {
+            Ok(e) => e,
+            Err(e) => TinyIdError::ConfigError(format!("{:#}", e)),
+        })?;
+        Ok(Self { inner })
+    }
+
+    /// 替换时间源，默认为 [`SystemClock`](crate::core::SystemClock)；测试中可注入脚本化时钟
+    /// 复现时钟回拨、同毫秒序列用尽等场景
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            inner: self.inner.with_clock(clock),
+        }
+    }
+
+    /// 生成一个ID，同一毫秒序列号用尽或时钟回拨时阻塞等待
+    pub fn next_id(&self) -> Result<u64, TinyIdError> {
+        self.inner.next_id()
+    }
+
+    /// 批量生成 `n` 个严格递增的ID
+    pub fn next_ids(&self, n: usize) -> Result<Vec<u64>, TinyIdError> {
+        self.inner.generate_ids_batch(n)
+    }
+
+    /// 按本实例的位布局拆解ID
+    pub fn decode(&self, id: u64) -> DecodedId {
+        self.inner.decode_id(id)
+    }
+
+    /// 把ID编码为对外格式（十进制、补零字符串或 base64 字节）
+    pub fn encode(&self, id: u64, encoding: IdEncoding) -> EncodedId {
+        encoding.encode(id)
+    }
+
+    /// 解析 [`Snowflake::encode`] 的字符串结果
+    pub fn parse(&self, encoded: &str, encoding: IdEncoding) -> Result<u64, TinyIdError> {
+        encoding.decode(encoded)
+    }
+
+    /// 生效的位布局及推导值
+    pub fn layout(&self) -> LayoutInfo {
+        self.inner.layout_info()
+    }
+
+    /// 运行统计
+    pub fn stats(&self) -> GeneratorStats {
+        self.inner.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::config::WorkerId;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// 依次返回预设的时间戳，用完后停在最后一个
+    #[derive(Debug)]
+    struct ScriptedClock(Mutex<VecDeque<u64>>);
+
+    impl Clock for ScriptedClock {
+        fn now_millis(&self) -> Result<u64, TinyIdError> {
+            let mut script = self.0.lock().unwrap();
+            if script.len() > 1 {
+                Ok(script.pop_front().unwrap())
+            } else {
+                Ok(script[0])
+            }
+        }
+    }
+
+    #[test]
+    fn test_snowflake_public_api() {
+        let cfg = IdGeneratorConfig {
+            worker_id: WorkerId::new(7, 127).unwrap(),
+            ..IdGeneratorConfig::default()
+        };
+        let snowflake = Snowflake::new(cfg).unwrap();
+
+        let first = snowflake.next_id().unwrap();
+        let ids = snowflake.next_ids(100).unwrap();
+        assert_eq!(ids.len(), 100);
+        assert!(first < ids[0]);
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+
+        let decoded = snowflake.decode(first);
+        assert_eq!(decoded.id, first);
+        assert_eq!(decoded.worker_id, 7);
+
+        let EncodedId::Text(padded) = snowflake.encode(first, IdEncoding::Padded) else {
+            panic!("padded encoding should produce a string");
+        };
+        assert_eq!(snowflake.parse(&padded, IdEncoding::Padded).unwrap(), first);
+        assert_eq!(snowflake.layout().worker_id, 7);
+    }
+
+    #[test]
+    fn test_snowflake_with_scripted_clock() {
+        let t = IdGeneratorConfig::default().epoch + 1_000;
+        // 第二次调用遇到 3ms 回拨，等时钟追回 t 后继续在同一毫秒分配
+        let script = [t, t - 3, t - 2, t, t + 1];
+        let clock = Arc::new(ScriptedClock(Mutex::new(script.into())));
+        let snowflake = Snowflake::new(IdGeneratorConfig::default())
+            .unwrap()
+            .with_clock(clock);
+
+        let ids: Vec<_> = (0..3).map(|_| snowflake.next_id().unwrap()).collect();
+        let decoded: Vec<_> = ids
+            .iter()
+            .map(|&id| {
+                let d = snowflake.decode(id);
+                (d.timestamp, d.sequence)
+            })
+            .collect();
+        assert_eq!(decoded, vec![(t, 0), (t, 1), (t + 1, 0)]);
+        assert_eq!(snowflake.stats().clock_backwards, 1);
+    }
+
+    #[test]
+    fn test_snowflake_rejects_invalid_config() {
+        // 默认 7 位工作节点ID最大为 127
+        let cfg = IdGeneratorConfig {
+            worker_id: WorkerId::new(200, 255).unwrap(),
+            ..IdGeneratorConfig::default()
+        };
+        assert!(Snowflake::new(cfg).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! ULID：48 位 Unix 毫秒时间戳 + 80 位随机数，Crockford base32 编码为 26 个字符
+//!
+//! 编码定长且字母表按 ASCII 排序，字符串排序与数值排序一致。同一实例内同一毫秒的 ULID
+//! 在上一个的基础上加一，保证与雪花ID一样严格递增。
+
+use std::sync::{Arc, Mutex, OnceLock};
+
+use rand::Rng;
+
+use crate::core::{Clock, SystemClock};
+use crate::error::TinyIdError;
+
+/// 编码后的长度
+pub const LEN: usize = 26;
+
+/// Crockford base32，去掉了易混淆的 I、L、O、U
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+const RANDOM_BITS: u32 = 80;
+const RANDOM_MASK: u128 = (1 << RANDOM_BITS) - 1;
+const MAX_TIMESTAMP: u64 = (1 << 48) - 1;
+
+/// 编码 128 位值，首字符只用到最高 3 位
+pub fn encode(value: u128) -> String {
+    let mut buf = [0u8; LEN];
+    let mut n = value;
+    for b in buf.iter_mut().rev() {
+        *b = ALPHABET[(n & 0x1f) as usize];
+        n >>= 5;
+    }
+    String::from_utf8(buf.to_vec()).expect("crockford alphabet is ascii")
+}
+
+/// 单调递增的 ULID 生成器
+///
+/// 时钟进入新的毫秒时重新取随机数；同一毫秒或时钟回拨时沿用上一个值加一，
+/// 随机部分加满后进位到下一毫秒，不等待时钟。
+#[derive(Debug)]
+pub struct UlidGenerator {
+    clock: Arc<dyn Clock>,
+    // 上一个发出的 ULID
+    last: Mutex<u128>,
+}
+
+impl Default for UlidGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UlidGenerator {
+    pub fn new() -> Self {
+        Self {
+            clock: Arc::new(SystemClock),
+            last: Mutex::new(0),
+        }
+    }
+
+    /// 替换时间源
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 生成一个 ULID 的 128 位数值
+    pub fn next_u128(&self) -> Result<u128, TinyIdError> {
+        let now = self.clock.now_millis()?;
+        if now > MAX_TIMESTAMP {
+            return Err(TinyIdError::IdGenerationFailed(format!(
+                "timestamp {}ms does not fit in 48 bits",
+                now
+            )));
+        }
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let next = if u128::from(now) > *last >> RANDOM_BITS {
+            (u128::from(now) << RANDOM_BITS) | (rand::rng().random::<u128>() & RANDOM_MASK)
+        } else {
+            last.checked_add(1).ok_or_else(|| {
+                TinyIdError::IdGenerationFailed("ulid space exhausted".to_string())
+            })?
+        };
+        *last = next;
+        Ok(next)
+    }
+
+    /// 生成一个 ULID 字符串
+    pub fn next_ulid(&self) -> Result<String, TinyIdError> {
+        self.next_u128().map(encode)
+    }
+}
+
+/// 用进程内共享的生成器生成一个 ULID
+///
+/// 系统时钟早于 1970 年时 panic；需要处理错误时使用 [`UlidGenerator::next_ulid`]。
+pub fn generate_ulid() -> String {
+    static GENERATOR: OnceLock<UlidGenerator> = OnceLock::new();
+    GENERATOR
+        .get_or_init(UlidGenerator::new)
+        .next_ulid()
+        .expect("system clock is before unix epoch")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[derive(Debug)]
+    struct FixedClock(AtomicU64);
+
+    impl Clock for FixedClock {
+        fn now_millis(&self) -> Result<u64, TinyIdError> {
+            Ok(self.0.load(Ordering::SeqCst))
+        }
+    }
+
+    #[test]
+    fn test_encode_known_values() {
+        assert_eq!(encode(0), "0".repeat(LEN));
+        assert_eq!(encode(u128::MAX), format!("7{}", "Z".repeat(LEN - 1)));
+        assert_eq!(encode(32), format!("{}10", "0".repeat(LEN - 2)));
+    }
+
+    #[test]
+    fn test_generate_ulid_format() {
+        let ulid = generate_ulid();
+        assert_eq!(ulid.len(), LEN);
+        assert!(ulid.bytes().all(|b| ALPHABET.contains(&b)), "{ulid}");
+        assert!(ulid < generate_ulid());
+    }
+
+    #[test]
+    fn test_monotonic_within_millisecond() {
+        let clock = Arc::new(FixedClock(AtomicU64::new(1_735_689_600_000)));
+        let generator = UlidGenerator::new().with_clock(clock.clone());
+
+        let ulids: Vec<_> = (0..1000).map(|_| generator.next_ulid().unwrap()).collect();
+        assert!(ulids.windows(2).all(|w| w[0] < w[1]));
+        // 前 10 个字符是时间戳
+        assert!(ulids.iter().all(|u| u[..10] == ulids[0][..10]));
+
+        // 时钟回拨后仍然递增
+        clock.0.fetch_sub(5, Ordering::SeqCst);
+        let after = generator.next_ulid().unwrap();
+        assert!(after > ulids[999]);
+        assert_eq!(after[..10], ulids[0][..10]);
+    }
+
+    #[test]
+    fn test_random_overflow_carries_into_next_millisecond() {
+        let now = 1_735_689_600_000u64;
+        let generator = UlidGenerator::new().with_clock(Arc::new(FixedClock(AtomicU64::new(now))));
+        *generator.last.lock().unwrap() = (u128::from(now) << RANDOM_BITS) | RANDOM_MASK;
+
+        let next = generator.next_u128().unwrap();
+        assert_eq!(next >> RANDOM_BITS, u128::from(now) + 1);
+        assert_eq!(next & RANDOM_MASK, 0);
+    }
+}
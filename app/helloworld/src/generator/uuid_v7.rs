@@ -0,0 +1,145 @@
+//! UUIDv7（RFC 9562）：48 位 Unix 毫秒时间戳 + 版本号 + 12 位 rand_a + 变体 + 62 位 rand_b
+//!
+//! rand_a 用作同一毫秒内的计数器，保证本实例内严格递增；rand_b 取随机数。
+
+use std::sync::{Arc, Mutex, OnceLock};
+
+use rand::Rng;
+use uuid::Uuid;
+
+use crate::core::{Clock, SystemClock};
+use crate::error::TinyIdError;
+
+const COUNTER_MAX: u16 = 0xfff;
+const RAND_B_MASK: u64 = (1 << 62) - 1;
+const MAX_TIMESTAMP: u64 = (1 << 48) - 1;
+
+/// 按时间戳、计数器和随机部分组装 UUIDv7
+fn assemble(timestamp: u64, counter: u16, rand_b: u64) -> Uuid {
+    let value = (u128::from(timestamp) << 80)
+        | (0x7 << 76)
+        | (u128::from(counter & COUNTER_MAX) << 64)
+        | (0b10 << 62)
+        | u128::from(rand_b & RAND_B_MASK);
+    Uuid::from_u128(value)
+}
+
+/// 单调递增的 UUIDv7 生成器
+///
+/// 同一毫秒或时钟回拨时沿用上一个时间戳并递增计数器，计数器用尽（每毫秒 4096 个）后进位到下一毫秒，
+/// 不等待时钟。
+#[derive(Debug)]
+pub struct UuidV7Generator {
+    clock: Arc<dyn Clock>,
+    // 上一个发出的（时间戳, 计数器）
+    last: Mutex<(u64, u16)>,
+}
+
+impl Default for UuidV7Generator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UuidV7Generator {
+    pub fn new() -> Self {
+        Self {
+            clock: Arc::new(SystemClock),
+            last: Mutex::new((0, 0)),
+        }
+    }
+
+    /// 替换时间源
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 生成一个 UUIDv7
+    pub fn next_uuid(&self) -> Result<Uuid, TinyIdError> {
+        let now = self.clock.now_millis()?;
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let (timestamp, counter) = match *last {
+            (last_ts, _) if now > last_ts => (now, 0),
+            (last_ts, counter) if counter < COUNTER_MAX => (last_ts, counter + 1),
+            (last_ts, _) => (last_ts + 1, 0),
+        };
+        if timestamp > MAX_TIMESTAMP {
+            return Err(TinyIdError::IdGenerationFailed(format!(
+                "timestamp {}ms does not fit in 48 bits",
+                timestamp
+            )));
+        }
+        *last = (timestamp, counter);
+        Ok(assemble(timestamp, counter, rand::rng().random()))
+    }
+}
+
+/// 用进程内共享的生成器生成一个 UUIDv7
+///
+/// 系统时钟早于 1970 年时 panic；需要处理错误时使用 [`UuidV7Generator::next_uuid`]。
+pub fn generate_uuidv7() -> Uuid {
+    static GENERATOR: OnceLock<UuidV7Generator> = OnceLock::new();
+    GENERATOR
+        .get_or_init(UuidV7Generator::new)
+        .next_uuid()
+        .expect("system clock is before unix epoch")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[derive(Debug)]
+    struct FixedClock(AtomicU64);
+
+    impl Clock for FixedClock {
+        fn now_millis(&self) -> Result<u64, TinyIdError> {
+            Ok(self.0.load(Ordering::SeqCst))
+        }
+    }
+
+    #[test]
+    fn test_version_and_variant_bits() {
+        let uuid = generate_uuidv7();
+        assert_eq!(uuid.get_version_num(), 7);
+        assert_eq!(uuid.get_variant(), uuid::Variant::RFC4122);
+
+        let now = SystemClock.now_millis().unwrap();
+        let timestamp = (uuid.as_u128() >> 80) as u64;
+        assert!(now.abs_diff(timestamp) < 1000);
+        assert_eq!(uuid.hyphenated().to_string().len(), 36);
+    }
+
+    #[test]
+    fn test_back_to_back_strictly_increasing() {
+        let uuids: Vec<_> = (0..10_000).map(|_| generate_uuidv7()).collect();
+        assert!(uuids.windows(2).all(|w| w[0] < w[1]));
+        // 字符串形式同样有序
+        assert!(uuids
+            .windows(2)
+            .all(|w| w[0].hyphenated().to_string() < w[1].hyphenated().to_string()));
+    }
+
+    #[test]
+    fn test_counter_carries_into_next_millisecond() {
+        let now = 1_735_689_600_000u64;
+        let clock = Arc::new(FixedClock(AtomicU64::new(now)));
+        let generator = UuidV7Generator::new().with_clock(clock.clone());
+
+        let uuids: Vec<_> = (0..=u32::from(COUNTER_MAX) + 1)
+            .map(|_| generator.next_uuid().unwrap())
+            .collect();
+        assert!(uuids.windows(2).all(|w| w[0] < w[1]));
+        let timestamp = |uuid: &Uuid| (uuid.as_u128() >> 80) as u64;
+        assert_eq!(timestamp(&uuids[COUNTER_MAX as usize]), now);
+        assert_eq!(timestamp(uuids.last().unwrap()), now + 1);
+
+        // 时钟回拨后仍然递增
+        clock.0.fetch_sub(5, Ordering::SeqCst);
+        let after = generator.next_uuid().unwrap();
+        assert!(&after > uuids.last().unwrap());
+        assert_eq!(after.get_version_num(), 7);
+    }
+}
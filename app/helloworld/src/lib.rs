@@ -1,11 +1,20 @@
+// 服务层同时实现 HTTP 与 gRPC，两个 feature 都启用时才编译；
+// 只用生成器时关闭默认 feature，依赖 `generator` 模块即可
+#[cfg(all(feature = "http", feature = "grpc"))]
 pub mod biz;
+pub mod build_info;
 pub mod core;
+#[cfg(all(feature = "http", feature = "grpc"))]
 pub mod data;
 pub mod error;
+pub mod generator;
+#[cfg(all(feature = "http", feature = "grpc"))]
 pub mod server;
+#[cfg(all(feature = "http", feature = "grpc"))]
 pub mod service;
 
 pub use error::TinyIdError;
+pub use generator::Snowflake;
 
 use anyhow::Result as AnyResult;
 
@@ -13,6 +22,6 @@ pub type Result<T> = AnyResult<T, TinyIdError>;
 
 impl From<anyhow::Error> for TinyIdError {
     fn from(err: anyhow::Error) -> Self {
-        TinyIdError::InternalError(err.to_string())
+        TinyIdError::InternalError(format!("{:#}", err))
     }
 }
@@ -0,0 +1,208 @@
+//! gRPC 调用指标
+//!
+//! tower layer，按 service/method/grpc-status 统计调用数和耗时，写入 `AppMetrics::grpc`，
+//! 通过 `Server::builder().layer(GrpcMetricsLayer::new(metrics))` 挂载。
+//! 正常响应的状态码在 trailers 中，因此在响应 body 结束时才记录。
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::Instant;
+
+use axum::http::{Request, Response};
+use http_body::{Body, Frame, SizeHint};
+use shared::metric::AppMetrics;
+use tower::{Layer, Service};
+
+use super::grpc_trace::{grpc_status, split_grpc_path};
+
+/// 客户端在响应结束前断开时记录的状态码（CANCELLED）
+const GRPC_CANCELLED: i32 = 1;
+/// 响应既没有 grpc-status 头也没有 trailers 时记录的状态码（UNKNOWN）
+const GRPC_UNKNOWN: i32 = 2;
+
+/// 挂载到 tonic `Server` 上的指标层
+#[derive(Debug, Clone)]
+pub struct GrpcMetricsLayer {
+    metrics: Arc<AppMetrics>,
+}
+
+impl GrpcMetricsLayer {
+    pub fn new(metrics: Arc<AppMetrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> Layer<S> for GrpcMetricsLayer {
+    type Service = GrpcMetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcMetricsService {
+            inner,
+            metrics: Arc::clone(&self.metrics),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GrpcMetricsService<S> {
+    inner: S,
+    metrics: Arc<AppMetrics>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for GrpcMetricsService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    ResBody: Body + Unpin,
+{
+    type Response = Response<GrpcMetricsBody<ResBody>>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let (service, method) = split_grpc_path(request.uri().path());
+        let mut call = CallRecord {
+            metrics: Arc::clone(&self.metrics),
+            service: service.to_string(),
+            method: method.to_string(),
+            start: Instant::now(),
+            done: false,
+        };
+        let future = self.inner.call(request);
+        Box::pin(async move {
+            let response = future.await?;
+            // trailers-only 响应（通常是错误）的状态码直接在响应头中
+            if let Some(code) = grpc_status(response.headers()) {
+                call.finish(code);
+            }
+            Ok(response.map(|body| GrpcMetricsBody { inner: body, call }))
+        })
+    }
+}
+
+/// 一次调用的记录状态，结束时写入指标；未正常结束就被丢弃时记为 CANCELLED
+struct CallRecord {
+    metrics: Arc<AppMetrics>,
+    service: String,
+    method: String,
+    start: Instant,
+    done: bool,
+}
+
+impl CallRecord {
+    fn finish(&mut self, code: i32) {
+        if !self.done {
+            self.done = true;
+            self.metrics
+                .grpc
+                .record_call(&self.service, &self.method, code, self.start.elapsed());
+        }
+    }
+}
+
+impl Drop for CallRecord {
+    fn drop(&mut self) {
+        self.finish(GRPC_CANCELLED);
+    }
+}
+
+/// 包装响应 body，在读到 trailers（或 body 结束）时记录状态码和耗时
+pub struct GrpcMetricsBody<B> {
+    inner: B,
+    call: CallRecord,
+}
+
+impl<B: Body + Unpin> Body for GrpcMetricsBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = &mut *self;
+        let frame = ready!(Pin::new(&mut this.inner).poll_frame(cx));
+        match &frame {
+            Some(Ok(frame)) => {
+                if let Some(code) = frame.trailers_ref().and_then(grpc_status) {
+                    this.call.finish(code);
+                }
+            }
+            Some(Err(_)) => this.call.finish(GRPC_UNKNOWN),
+            None => this.call.finish(GRPC_UNKNOWN),
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::health::HealthService;
+    use shared::proto::health::health_client::HealthClient;
+    use shared::proto::health::health_server::HealthServer;
+    use shared::proto::health::HealthCheckRequest;
+    use shared::proto::id_generator::id_generator_service_client::IdGeneratorServiceClient;
+    use shared::proto::id_generator::GenerateIdsRequest;
+    use tonic::transport::{Endpoint, Server};
+
+    use crate::server::grpc_trace_layer;
+
+    #[tokio::test]
+    async fn test_records_status_from_trailers() {
+        let metrics = Arc::new(AppMetrics::default());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .layer(grpc_trace_layer())
+                .layer(GrpcMetricsLayer::new(Arc::clone(&metrics)))
+                .add_service(HealthServer::new(HealthService::default()))
+                .serve_with_incoming(tonic::transport::server::TcpIncoming::from(listener)),
+        );
+
+        let channel = Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect_lazy();
+        for _ in 0..2 {
+            HealthClient::new(channel.clone())
+                .check(HealthCheckRequest::default())
+                .await
+                .unwrap();
+        }
+        // 未注册的服务返回 trailers-only 的 UNIMPLEMENTED
+        let status = IdGeneratorServiceClient::new(channel)
+            .generate_ids(GenerateIdsRequest::default())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unimplemented);
+
+        let grpc = &metrics.grpc;
+        assert_eq!(grpc.requests("grpc.health.v1.Health", "Check", 0), 2);
+        assert_eq!(
+            grpc.requests(
+                "id_generator.v1.IdGeneratorService",
+                "GenerateIds",
+                tonic::Code::Unimplemented as i32
+            ),
+            1
+        );
+        assert!(grpc
+            .render()
+            .contains("tinyid_grpc_duration_seconds_count{method=\"Check\",service=\"grpc.health.v1.Health\"} 2"));
+    }
+}
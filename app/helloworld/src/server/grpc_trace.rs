@@ -0,0 +1,151 @@
+//! gRPC 调用追踪
+//!
+//! 基于 `tower_http::trace::TraceLayer`，每次 gRPC 调用创建一个 span，记录服务名、方法名、
+//! 对端地址、grpc-status 和耗时，通过 `Server::builder().layer(grpc_trace_layer())` 挂载。
+
+use std::time::Duration;
+
+use axum::http::{HeaderMap, Request, Response};
+use tonic::transport::server::TcpConnectInfo;
+use tower_http::classify::{GrpcErrorsAsFailures, GrpcFailureClass, SharedClassifier};
+use tower_http::trace::{
+    DefaultOnBodyChunk, DefaultOnRequest, MakeSpan, OnEos, OnFailure, OnResponse, TraceLayer,
+};
+use tracing::{error, field::Empty, info, info_span, Span};
+
+/// 挂载到 tonic `Server` 上的追踪层
+pub type GrpcTraceLayer = TraceLayer<
+    SharedClassifier<GrpcErrorsAsFailures>,
+    GrpcTrace,
+    DefaultOnRequest,
+    GrpcTrace,
+    DefaultOnBodyChunk,
+    GrpcTrace,
+    GrpcTrace,
+>;
+
+/// 构造 gRPC 追踪层
+pub fn grpc_trace_layer() -> GrpcTraceLayer {
+    TraceLayer::new_for_grpc()
+        .make_span_with(GrpcTrace)
+        .on_response(GrpcTrace)
+        .on_eos(GrpcTrace)
+        .on_failure(GrpcTrace)
+}
+
+/// gRPC 调用的 span 构造与各阶段回调
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GrpcTrace;
+
+impl<B> MakeSpan<B> for GrpcTrace {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        let (service, method) = split_grpc_path(request.uri().path());
+        let peer = request
+            .extensions()
+            .get::<TcpConnectInfo>()
+            .and_then(|info| info.remote_addr())
+            .map_or_else(|| "unknown".to_string(), |addr| addr.to_string());
+
+        info_span!(
+            "grpc_request",
+            // 使用OpenTelemetry语义约定
+            "rpc.system" = "grpc",
+            "rpc.service" = %service,
+            "rpc.method" = %method,
+            "net.peer.addr" = %peer,
+            "rpc.grpc.status_code" = Empty,
+            duration_ms = Empty,
+        )
+    }
+}
+
+impl<B> OnResponse<B> for GrpcTrace {
+    fn on_response(self, response: &Response<B>, latency: Duration, span: &Span) {
+        // 只有 trailers-only 响应（通常是错误）的状态码在响应头中，正常响应在 trailers 中
+        if let Some(code) = grpc_status(response.headers()) {
+            span.record("rpc.grpc.status_code", code);
+        }
+        span.record("duration_ms", latency.as_millis() as u64);
+    }
+}
+
+impl OnEos for GrpcTrace {
+    fn on_eos(self, trailers: Option<&HeaderMap>, stream_duration: Duration, span: &Span) {
+        if let Some(code) = trailers.and_then(grpc_status) {
+            span.record("rpc.grpc.status_code", code);
+        }
+        info!(
+            stream_duration_ms = %stream_duration.as_millis(),
+            "gRPC call completed"
+        );
+    }
+}
+
+impl OnFailure<GrpcFailureClass> for GrpcTrace {
+    fn on_failure(&mut self, failure: GrpcFailureClass, latency: Duration, _span: &Span) {
+        error!(
+            error = %failure,
+            duration_ms = %latency.as_millis(),
+            "gRPC call failed"
+        );
+    }
+}
+
+/// 拆分 `/package.Service/Method` 形式的路径
+pub(super) fn split_grpc_path(path: &str) -> (&str, &str) {
+    let path = path.trim_start_matches('/');
+    path.split_once('/').unwrap_or((path, ""))
+}
+
+pub(super) fn grpc_status(headers: &HeaderMap) -> Option<i32> {
+    headers.get("grpc-status")?.to_str().ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::health::HealthService;
+    use shared::proto::health::health_check_response::ServingStatus;
+    use shared::proto::health::health_client::HealthClient;
+    use shared::proto::health::health_server::HealthServer;
+    use shared::proto::health::HealthCheckRequest;
+    use tonic::transport::{Endpoint, Server};
+
+    #[test]
+    fn test_split_grpc_path() {
+        assert_eq!(
+            split_grpc_path("/id_generator.IdGeneratorService/GenerateId"),
+            ("id_generator.IdGeneratorService", "GenerateId")
+        );
+        assert_eq!(split_grpc_path("/unknown"), ("unknown", ""));
+    }
+
+    #[test]
+    fn test_grpc_status() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(grpc_status(&headers), None);
+        headers.insert("grpc-status", "4".parse().unwrap());
+        assert_eq!(grpc_status(&headers), Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_layer_serves_grpc_calls() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .layer(grpc_trace_layer())
+                .add_service(HealthServer::new(HealthService::default()))
+                .serve_with_incoming(tonic::transport::server::TcpIncoming::from(listener)),
+        );
+
+        let channel = Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect_lazy();
+        let resp = HealthClient::new(channel)
+            .check(HealthCheckRequest::default())
+            .await
+            .unwrap();
+        assert_eq!(resp.into_inner().status(), ServingStatus::Serving);
+    }
+}
@@ -1,27 +1,51 @@
 use axum::{
+    body::{Body, Bytes},
     extract::Request,
-    http::{HeaderMap, HeaderName},
+    http::{
+        header::{CONTENT_LENGTH, CONTENT_TYPE},
+        HeaderMap, HeaderName,
+    },
     middleware::Next,
     response::Response,
 };
+#[cfg(feature = "otel")]
 use opentelemetry::{
     propagation::{Extractor, Injector},
     trace::{SpanKind, Status, TraceContextExt, Tracer},
     KeyValue,
 };
 // 手动定义语义常量，因为版本兼容性问题
+#[cfg(feature = "otel")]
 const HTTP_METHOD: &str = "http.method";
+#[cfg(feature = "otel")]
 const HTTP_ROUTE: &str = "http.route";
+#[cfg(feature = "otel")]
 const HTTP_STATUS_CODE: &str = "http.status_code";
+#[cfg(feature = "otel")]
 const HTTP_URL: &str = "http.url";
+#[cfg(feature = "otel")]
 const HTTP_USER_AGENT: &str = "http.user_agent";
-use std::time::Instant;
-use tracing::{error, info, warn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use http_body::Body as _;
+use shared::config::{HeaderLimitConfig, RequiredHeaderConfig};
+use shared::metric::AppMetrics;
+use tracing::{error, info, warn, Span};
+#[cfg(feature = "otel")]
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+use crate::service::response::{ErrCode, Response as ApiResponse};
+
 /// HTTP Headers 作为 Extractor，用于从请求头中提取 trace context
+#[cfg(feature = "otel")]
 struct HeaderExtractor<'a>(&'a HeaderMap);
 
+#[cfg(feature = "otel")]
 impl<'a> Extractor for HeaderExtractor<'a> {
     fn get(&self, key: &str) -> Option<&str> {
         self.0.get(key).and_then(|value| value.to_str().ok())
@@ -33,8 +57,10 @@ impl<'a> Extractor for HeaderExtractor<'a> {
 }
 
 /// HTTP Headers 作为 Injector，用于向响应头中注入 trace context
+#[cfg(feature = "otel")]
 struct HeaderInjector<'a>(&'a mut HeaderMap);
 
+#[cfg(feature = "otel")]
 impl<'a> Injector for HeaderInjector<'a> {
     fn set(&mut self, key: &str, value: String) {
         if let Ok(header_name) = HeaderName::try_from(key) {
@@ -45,19 +71,69 @@ impl<'a> Injector for HeaderInjector<'a> {
     }
 }
 
+/// 中间件记录的请求开始时间，通过 request extensions 传给 handler
+#[derive(Debug, Clone, Copy)]
+pub struct RequestStart(pub Instant);
+
+impl RequestStart {
+    pub fn elapsed(&self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// 在 request extensions 中记录请求开始时间，已由外层中间件记录时保留原值
+pub async fn request_start_middleware(mut request: Request, next: Next) -> Response {
+    if request.extensions().get::<RequestStart>().is_none() {
+        request
+            .extensions_mut()
+            .insert(RequestStart(Instant::now()));
+    }
+    next.run(request).await
+}
+
 /// Tracing 中间件配置
 #[derive(Debug, Clone)]
 pub struct TracingConfig {
-    /// 是否记录请求体
+    /// 是否记录请求体（仅缓冲不超过 64KB 且长度已知的 body）
     pub log_request_body: bool,
-    /// 是否记录响应体
+    /// 是否记录响应体（同上）
     pub log_response_body: bool,
+    /// 日志中 body 的最大字节数，超出部分截断
+    pub max_logged_body_bytes: usize,
+    /// 记录 JSON body 时需要脱敏的字段名（不区分大小写，任意层级）
+    pub redact_fields: Vec<String>,
     /// 慢请求阈值（毫秒）
     pub slow_request_threshold_ms: u64,
+    /// 慢请求日志采样：每 N 个慢请求只打印 1 条警告（0 或 1 表示全部打印）
+    pub slow_request_log_sample_rate: u64,
+    /// 慢请求计数，同时作为日志采样的依据；clone 后的配置共享同一计数
+    pub slow_requests: Arc<AtomicU64>,
+    /// 记录慢请求总数和最大响应时间，不受日志采样影响
+    pub metrics: Option<Arc<AppMetrics>>,
     /// 是否在响应头中包含 trace_id
     pub include_trace_id_header: bool,
     /// trace_id 响应头名称
     pub trace_id_header_name: String,
+    /// 是否同时返回 span_id（响应头及 `ref`），生产环境可关闭
+    pub include_span_id: bool,
+    /// span_id 响应头名称
+    pub span_id_header_name: String,
+    /// 是否把 trace_id（及 span_id）写入统一响应体的 `ref`，handler 已设置时不覆盖
+    ///
+    /// 需要缓冲并重新序列化 64KB 以内的 JSON 响应（字段顺序会变），默认关闭，
+    /// 只靠响应头即可定位 trace。
+    pub include_trace_ref: bool,
+}
+
+impl TracingConfig {
+    /// 记录一次慢请求，返回这次是否需要打印警告日志
+    fn record_slow_request(&self) -> bool {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_slow_request();
+        }
+        let seen = self.slow_requests.fetch_add(1, Ordering::Relaxed);
+        seen.is_multiple_of(self.slow_request_log_sample_rate.max(1))
+    }
 }
 
 impl Default for TracingConfig {
@@ -65,9 +141,20 @@ impl Default for TracingConfig {
         Self {
             log_request_body: false,
             log_response_body: false,
+            max_logged_body_bytes: 4096,
+            redact_fields: DEFAULT_REDACT_FIELDS
+                .iter()
+                .map(|f| f.to_string())
+                .collect(),
             slow_request_threshold_ms: 1000, // 1秒
+            slow_request_log_sample_rate: 1,
+            slow_requests: Arc::new(AtomicU64::new(0)),
+            metrics: None,
             include_trace_id_header: true,
             trace_id_header_name: "x-trace-id".to_string(),
+            include_span_id: true,
+            span_id_header_name: "x-span-id".to_string(),
+            include_trace_ref: false,
         }
     }
 }
@@ -86,11 +173,13 @@ pub async fn tracing_middleware(request: Request, next: Next) -> Response {
 
 /// 带配置的 tracing 中间件
 pub async fn tracing_middleware_with_config(
-    request: Request,
+    mut request: Request,
     next: Next,
     config: TracingConfig,
 ) -> Response {
     let start_time = Instant::now();
+    // 供 handler 计算服务端耗时
+    request.extensions_mut().insert(RequestStart(start_time));
 
     // 1. 提取请求信息
     let method = request.method().to_string().clone();
@@ -104,30 +193,7 @@ pub async fn tracing_middleware_with_config(
         .unwrap_or("")
         .to_string();
 
-    // 2. 从请求头中提取 trace context
-    let parent_cx = opentelemetry::global::get_text_map_propagator(|propagator| {
-        propagator.extract(&HeaderExtractor(&headers))
-    });
-
-    // 3. 创建新的 span
-    let tracer = opentelemetry::global::tracer("tinyid");
-    let span = tracer
-        .span_builder(format!("{} {}", method, path))
-        .with_kind(SpanKind::Server)
-        .with_attributes([
-            KeyValue::new(HTTP_METHOD, method.clone()),
-            KeyValue::new(HTTP_URL, uri.clone()),
-            KeyValue::new(HTTP_ROUTE, path.clone()),
-            KeyValue::new(HTTP_USER_AGENT, user_agent.clone()),
-        ])
-        .start_with_context(&tracer, &parent_cx);
-
-    let cx = parent_cx.with_span(span);
-
-    // 调试：打印当前 span 的 trace_id
-    // let current_trace_id = cx.span().span_context().trace_id().to_string();
-
-    // 4. 创建 tracing span 并关联 OpenTelemetry context
+    // 2. 创建 tracing span
     let request_id = uuid::Uuid::new_v4().to_string();
     let tracing_span = tracing::info_span!(
         "http_request",
@@ -137,14 +203,44 @@ pub async fn tracing_middleware_with_config(
         query = %query,
         user_agent = %user_agent,
     );
-    tracing_span.set_parent(cx.clone());
 
+    // 3. 从请求头中提取 trace context，创建 OpenTelemetry span 并关联到 tracing span
+    let cx = start_otel_span(&tracing_span, &headers, &method, &path, &uri, &user_agent);
+
+    // 4. 进入 span
     let _guard = tracing_span.enter();
 
     // 记录请求开始
     info!(service.ready = true, message = "processing request");
 
-    // 5. 处理请求
+    // 5. 记录请求体（如果配置启用）并处理请求
+    let request = if config.log_request_body {
+        let (parts, body) = request.into_parts();
+        let body = match buffer_body(body).await {
+            Ok(BufferedBody::Full(bytes)) => {
+                log_body("request", &bytes, &config);
+                Body::from(bytes)
+            }
+            Err(e) => {
+                warn!(error = %e, "failed to read request body");
+                return ApiResponse::<()>::failed(
+                    ErrCode::BadRequest,
+                    Some("failed to read request body"),
+                )
+                .into_response();
+            }
+            Ok(BufferedBody::Passthrough(body)) => {
+                info!(
+                    body.direction = "request",
+                    "body too large or streaming, not logged"
+                );
+                body
+            }
+        };
+        Request::from_parts(parts, body)
+    } else {
+        request
+    };
     let mut response = next.run(request).await;
 
     // 6. 计算请求持续时间
@@ -154,33 +250,26 @@ pub async fn tracing_middleware_with_config(
     // 7. 获取响应状态码
     let status_code = response.status();
     let status_code_value = status_code.as_u16();
+    if let Some(metrics) = &config.metrics {
+        metrics.observe_response_time(duration_ms);
+    }
 
     // 8. 更新 OpenTelemetry span 属性
-    let otel_ctx = tracing_span.context();
-    otel_ctx
-        .span()
-        .set_attribute(KeyValue::new(HTTP_STATUS_CODE, status_code_value as i64));
-
-    // 设置 span 状态
-    if status_code.is_server_error() {
-        otel_ctx
-            .span()
-            .set_status(Status::error("Internal server error"));
-    } else if status_code.is_client_error() {
-        otel_ctx.span().set_status(Status::error("Client error"));
-    } else {
-        otel_ctx.span().set_status(Status::Ok);
-    }
+    set_otel_status(&tracing_span, status_code);
 
     // 9. 记录日志
     match status_code_value {
         200..=299 => {
             if duration_ms >= config.slow_request_threshold_ms {
-                warn!(
-                    status_code = %status_code_value,
-                    duration_ms = %duration_ms,
-                    "Slow request completed"
-                );
+                // 所有慢请求都计数，但日志按采样率打印，避免故障期间刷屏
+                if config.record_slow_request() {
+                    warn!(
+                        status_code = %status_code_value,
+                        duration_ms = %duration_ms,
+                        slow_requests = config.slow_requests.load(Ordering::Relaxed),
+                        "Slow request completed"
+                    );
+                }
             } else {
                 info!(
                     status_code = %status_code_value,
@@ -212,26 +301,276 @@ pub async fn tracing_middleware_with_config(
         }
     }
 
-    // 10. 在响应头中注入 trace context
-    let response_headers = response.headers_mut();
+    // 10. 在响应头中注入 trace context，未启用 otel feature 时没有 trace_id / span_id
+    let trace_ids = inject_otel_context(&cx, &tracing_span, response.headers_mut());
+
+    // 11. 添加 trace_id / span_id 到响应头（如果配置启用）
+    if let Some((trace_id, span_id)) = &trace_ids {
+        let response_headers = response.headers_mut();
+        if config.include_trace_id_header {
+            insert_header(response_headers, &config.trace_id_header_name, trace_id);
+        }
+        if config.include_span_id {
+            insert_header(response_headers, &config.span_id_header_name, span_id);
+        }
+    }
+
+    // 12. 记录响应体，并把 trace_id（及 span_id）写入响应体的 ref，方便直接定位到具体 span
+    let trace_ref = trace_ids
+        .filter(|_| config.include_trace_ref && is_json(response.headers()))
+        .map(|(trace_id, span_id)| {
+            if config.include_span_id {
+                format!("{}:{}", trace_id, span_id)
+            } else {
+                trace_id
+            }
+        });
+    let fill_ref = trace_ref.is_some();
+    if config.log_response_body || fill_ref {
+        let (mut parts, body) = response.into_parts();
+        let body = match buffer_body(body).await {
+            Ok(BufferedBody::Full(bytes)) => {
+                if config.log_response_body {
+                    log_body("response", &bytes, &config);
+                }
+                let filled = trace_ref.and_then(|r#ref| with_response_ref(&bytes, r#ref));
+                match filled {
+                    Some(filled) => {
+                        parts.headers.remove(CONTENT_LENGTH);
+                        Body::from(filled)
+                    }
+                    None => Body::from(bytes),
+                }
+            }
+            Err(e) => {
+                error!(error = %e, "failed to read response body");
+                return ApiResponse::<()>::failed(
+                    ErrCode::InternalServerError,
+                    Some("failed to read response body"),
+                )
+                .into_response();
+            }
+            Ok(BufferedBody::Passthrough(body)) => {
+                if config.log_response_body {
+                    info!(
+                        body.direction = "response",
+                        "body too large or streaming, not logged"
+                    );
+                }
+                body
+            }
+        };
+        response = Response::from_parts(parts, body);
+    }
+
+    response
+}
 
-    // 注入 trace context 到响应头
+/// OpenTelemetry 上下文，未启用 otel feature 时为空
+#[cfg(feature = "otel")]
+type OtelContext = opentelemetry::Context;
+#[cfg(not(feature = "otel"))]
+struct OtelContext;
+
+/// 从请求头中提取上游 trace context，创建 server span 并设为 tracing span 的 parent
+#[cfg(feature = "otel")]
+fn start_otel_span(
+    tracing_span: &Span,
+    headers: &HeaderMap,
+    method: &str,
+    path: &str,
+    uri: &str,
+    user_agent: &str,
+) -> OtelContext {
+    let parent_cx = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+
+    let tracer = opentelemetry::global::tracer("tinyid");
+    let span = tracer
+        .span_builder(format!("{} {}", method, path))
+        .with_kind(SpanKind::Server)
+        .with_attributes([
+            KeyValue::new(HTTP_METHOD, method.to_string()),
+            KeyValue::new(HTTP_URL, uri.to_string()),
+            KeyValue::new(HTTP_ROUTE, path.to_string()),
+            KeyValue::new(HTTP_USER_AGENT, user_agent.to_string()),
+        ])
+        .start_with_context(&tracer, &parent_cx);
+
+    let cx = parent_cx.with_span(span);
+    tracing_span.set_parent(cx.clone());
+    cx
+}
+
+#[cfg(not(feature = "otel"))]
+fn start_otel_span(
+    _tracing_span: &Span,
+    _headers: &HeaderMap,
+    _method: &str,
+    _path: &str,
+    _uri: &str,
+    _user_agent: &str,
+) -> OtelContext {
+    OtelContext
+}
+
+/// 按响应状态码设置 span 的状态码属性和 status
+#[cfg(feature = "otel")]
+fn set_otel_status(tracing_span: &Span, status_code: StatusCode) {
+    let otel_ctx = tracing_span.context();
+    let span = otel_ctx.span();
+    span.set_attribute(KeyValue::new(HTTP_STATUS_CODE, status_code.as_u16() as i64));
+    if status_code.is_server_error() {
+        span.set_status(Status::error("Internal server error"));
+    } else if status_code.is_client_error() {
+        span.set_status(Status::error("Client error"));
+    } else {
+        span.set_status(Status::Ok);
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+fn set_otel_status(_tracing_span: &Span, _status_code: StatusCode) {}
+
+/// 把 trace context 注入响应头，返回当前 span 的 (trace_id, span_id)
+#[cfg(feature = "otel")]
+fn inject_otel_context(
+    cx: &OtelContext,
+    tracing_span: &Span,
+    headers: &mut HeaderMap,
+) -> Option<(String, String)> {
     opentelemetry::global::get_text_map_propagator(|propagator| {
-        propagator.inject_context(&cx, &mut HeaderInjector(response_headers))
+        propagator.inject_context(cx, &mut HeaderInjector(headers))
     });
+    let otel_ctx = tracing_span.context();
+    let span_context = otel_ctx.span().span_context().clone();
+    Some((
+        span_context.trace_id().to_string(),
+        span_context.span_id().to_string(),
+    ))
+}
+
+#[cfg(not(feature = "otel"))]
+fn inject_otel_context(
+    _cx: &OtelContext,
+    _tracing_span: &Span,
+    _headers: &mut HeaderMap,
+) -> Option<(String, String)> {
+    None
+}
 
-    // 11. 添加 trace_id 到响应头（如果配置启用）
-    if config.include_trace_id_header {
-        let trace_id = otel_ctx.span().span_context().trace_id().to_string();
-        if let (Ok(header_name), Ok(header_value)) = (
-            HeaderName::try_from(config.trace_id_header_name.as_str()),
-            trace_id.parse(),
-        ) {
-            response_headers.insert(header_name, header_value);
+/// 只缓冲（用于记录或改写）不超过该大小的 body
+const MAX_BUFFERED_BODY_BYTES: u64 = 64 * 1024;
+
+/// 默认脱敏的字段名（不区分大小写）
+const DEFAULT_REDACT_FIELDS: [&str; 5] =
+    ["password", "token", "secret", "authorization", "api_key"];
+
+/// 脱敏后的占位值
+const REDACTED: &str = "***";
+
+fn insert_header(headers: &mut HeaderMap, name: &str, value: &str) {
+    if let (Ok(header_name), Ok(header_value)) = (HeaderName::try_from(name), value.parse()) {
+        headers.insert(header_name, header_value);
+    }
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+/// `buffer_body` 的结果
+enum BufferedBody {
+    /// 已读出的完整 body
+    Full(Bytes),
+    /// 长度未知或超过 `MAX_BUFFERED_BODY_BYTES`，原样交还
+    Passthrough(Body),
+}
+
+/// 读出完整 body；读取失败（客户端中断、上游 body 出错）时返回错误，不能当作空 body 继续
+async fn buffer_body(body: Body) -> Result<BufferedBody, axum::Error> {
+    let small = body
+        .size_hint()
+        .upper()
+        .is_some_and(|n| n <= MAX_BUFFERED_BODY_BYTES);
+    if !small {
+        return Ok(BufferedBody::Passthrough(body));
+    }
+    axum::body::to_bytes(body, MAX_BUFFERED_BODY_BYTES as usize)
+        .await
+        .map(BufferedBody::Full)
+}
+
+/// 给统一响应体（带 `code` 字段的 JSON 对象）补上 `ref`，不需要改写时返回 None
+fn with_response_ref(bytes: &[u8], r#ref: String) -> Option<String> {
+    match serde_json::from_slice::<serde_json::Value>(bytes) {
+        Ok(serde_json::Value::Object(mut obj))
+            if obj.contains_key("code") && !obj.contains_key("ref") =>
+        {
+            obj.insert("ref".to_string(), serde_json::Value::String(r#ref));
+            Some(serde_json::Value::Object(obj).to_string())
         }
+        _ => None,
     }
+}
 
-    response
+/// 在当前 span 下记录 body：JSON 按 `redact_fields` 脱敏，超过 `max_logged_body_bytes` 截断
+fn log_body(direction: &'static str, bytes: &[u8], config: &TracingConfig) {
+    if bytes.is_empty() {
+        return;
+    }
+    let text = match serde_json::from_slice::<serde_json::Value>(bytes) {
+        Ok(mut value) => {
+            redact_json(&mut value, &config.redact_fields);
+            value.to_string()
+        }
+        Err(_) => String::from_utf8_lossy(bytes).into_owned(),
+    };
+    let (text, truncated) = truncate_utf8(&text, config.max_logged_body_bytes);
+    info!(
+        body.direction = direction,
+        body.size = bytes.len(),
+        body.truncated = truncated,
+        body = %text,
+        "http body"
+    );
+}
+
+/// 递归替换 key 命中 `fields`（不区分大小写）的值
+fn redact_json(value: &mut serde_json::Value, fields: &[String]) {
+    match value {
+        serde_json::Value::Object(obj) => {
+            for (key, v) in obj.iter_mut() {
+                if fields.iter().any(|f| f.eq_ignore_ascii_case(key)) {
+                    *v = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(v, fields);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for v in items {
+                redact_json(v, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 按字符边界截断到不超过 `max` 字节，返回截断后的文本和是否发生了截断
+fn truncate_utf8(text: &str, max: usize) -> (&str, bool) {
+    if text.len() <= max {
+        return (text, false);
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    (&text[..end], true)
 }
 
 /// 错误处理中间件
@@ -251,6 +590,96 @@ pub async fn error_handling_middleware(request: Request, next: Next) -> Response
     response
 }
 
+/// 未匹配到路由的请求在 `tinyid_requests_by_route_total` 中使用的路由标签
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// 请求指标中间件
+///
+/// 进入时累加请求总数，完成后按状态码记录成功（1xx-3xx）或失败（4xx、5xx）及耗时，
+/// 并按 axum 匹配到的路由模板分别计数。
+pub async fn metrics_middleware(
+    State(metrics): State<Arc<AppMetrics>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let start = Instant::now();
+    metrics.increment_request();
+    // 使用路由模板而不是实际路径，避免 /decode/{id} 之类的路径产生大量序列
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE.to_string(), |path| {
+            path.as_str().to_string()
+        });
+    let response = next.run(request).await;
+
+    let elapsed = start.elapsed();
+    let duration_ms = elapsed.as_millis() as u64;
+    metrics.observe_response_time(duration_ms);
+    metrics.record_latency(elapsed.as_micros() as u64);
+    let status = response.status();
+    metrics.record_route(&route, status.as_u16());
+    if status.is_client_error() || status.is_server_error() {
+        metrics.record_failure(duration_ms);
+    } else {
+        metrics.record_success(duration_ms);
+    }
+    response
+}
+
+/// 请求头限制中间件
+///
+/// 请求头数量或总字节数超过配置时直接返回 400，不再进入后续处理。
+pub async fn header_limit_middleware(
+    State(cfg): State<HeaderLimitConfig>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let count = headers.len();
+    let bytes: usize = headers
+        .iter()
+        .map(|(name, value)| name.as_str().len() + value.len())
+        .sum();
+
+    if count > cfg.max_headers || bytes > cfg.max_header_bytes {
+        warn!(count, bytes, "request rejected by header limit");
+        return ApiResponse::<()>::failed(ErrCode::BadRequest, Some("request headers too large"))
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// 必需请求头中间件
+///
+/// 缺少配置的请求头（或值为空）时直接返回 400；存在时把值记录到请求 span 的
+/// `request.required_header` 字段，便于按客户端归因。`exempt_paths` 中的路径不做检查。
+pub async fn required_header_middleware(
+    State(cfg): State<RequiredHeaderConfig>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if cfg.exempt_paths.iter().any(|p| p == request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let value = request
+        .headers()
+        .get(cfg.name.as_str())
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty());
+    let Some(value) = value else {
+        warn!(header = %cfg.name, "request rejected: missing required header");
+        return ApiResponse::<()>::failed(
+            ErrCode::BadRequest,
+            Some(format!("missing required header {}", cfg.name)),
+        )
+        .into_response();
+    };
+    Span::current().record("request.required_header", value);
+    next.run(request).await
+}
+
 /// 超时处理中间件的配置
 #[derive(Debug, Clone)]
 pub struct TimeoutConfig {
@@ -273,11 +702,12 @@ mod tests {
     use axum::{body::Body, extract::Request, routing::get, Router};
     use tower::ServiceExt;
 
+    #[cfg(feature = "otel")]
     #[tokio::test]
     async fn test_tracing_middleware() {
         // 初始化测试用的 tracing
         shared::init_env();
-        shared::init_tracing();
+        shared::traces::init_logs();
 
         // 创建测试路由
         let app = Router::new()
@@ -307,4 +737,298 @@ mod tests {
         // 验证响应头中包含 trace_id
         assert!(response.headers().contains_key("x-trace-id"));
     }
+
+    #[cfg(feature = "otel")]
+    #[tokio::test]
+    async fn test_trace_context_propagation() {
+        shared::init_env();
+        shared::traces::init_logs();
+
+        let app = Router::new()
+            .route("/test", get(|| async { "test response" }))
+            .layer(axum::middleware::from_fn(
+                move |request: Request, next: Next| {
+                    let config = TracingConfig::default();
+                    Box::pin(
+                        async move { tracing_middleware_with_config(request, next, config).await },
+                    )
+                },
+            ));
+
+        // 上游传入的 W3C traceparent，响应中的 trace id 必须沿用它而不是新开一条 trace
+        let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+        let request = Request::builder()
+            .uri("/test")
+            .header(
+                "traceparent",
+                format!("00-{}-00f067aa0ba902b7-01", trace_id),
+            )
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-trace-id"], trace_id);
+        let traceparent = response.headers()["traceparent"].to_str().unwrap();
+        assert!(traceparent.starts_with(&format!("00-{}-", trace_id)));
+    }
+
+    #[cfg(feature = "otel")]
+    fn traced_app(config: TracingConfig) -> Router {
+        Router::new()
+            .route(
+                "/api",
+                get(|| async { ApiResponse::with_data(ErrCode::Success, "ok", 1) }),
+            )
+            .route(
+                "/api/ref",
+                get(|| async { ApiResponse::<()>::success(None).set_ref("handler-ref") }),
+            )
+            .layer(axum::middleware::from_fn(
+                move |request: Request, next: Next| {
+                    let config = config.clone();
+                    Box::pin(
+                        async move { tracing_middleware_with_config(request, next, config).await },
+                    )
+                },
+            ))
+    }
+
+    #[cfg(feature = "otel")]
+    async fn traced_get(app: Router, uri: &str) -> (HeaderMap, serde_json::Value) {
+        let trace_id = "4bf92f3577b34da6a3ce929d0e0e4736";
+        let request = Request::builder()
+            .uri(uri)
+            .header(
+                "traceparent",
+                format!("00-{}-00f067aa0ba902b7-01", trace_id),
+            )
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let headers = response.headers().clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (headers, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[cfg(feature = "otel")]
+    #[tokio::test]
+    async fn test_span_id_in_headers_and_ref() {
+        shared::init_env();
+        shared::traces::init_logs();
+
+        let config = TracingConfig {
+            include_trace_ref: true,
+            ..TracingConfig::default()
+        };
+        let (headers, body) = traced_get(traced_app(config.clone()), "/api").await;
+        let trace_id = headers["x-trace-id"].to_str().unwrap();
+        let span_id = headers["x-span-id"].to_str().unwrap();
+        assert_eq!(trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(body["ref"], format!("{}:{}", trace_id, span_id));
+        assert_eq!(body["data"], 1);
+
+        // handler 自己设置的 ref 不会被覆盖
+        let (_, body) = traced_get(traced_app(config), "/api/ref").await;
+        assert_eq!(body["ref"], "handler-ref");
+    }
+
+    #[cfg(feature = "otel")]
+    #[tokio::test]
+    async fn test_span_id_disabled() {
+        shared::init_env();
+        shared::traces::init_logs();
+
+        let config = TracingConfig {
+            include_span_id: false,
+            include_trace_ref: true,
+            ..TracingConfig::default()
+        };
+        let (headers, body) = traced_get(traced_app(config), "/api").await;
+        assert!(!headers.contains_key("x-span-id"));
+        assert_eq!(body["ref"], "4bf92f3577b34da6a3ce929d0e0e4736");
+
+        // 默认不改写响应体
+        let (headers, body) = traced_get(traced_app(TracingConfig::default()), "/api").await;
+        assert!(headers.contains_key("x-span-id"));
+        assert!(body.get("ref").is_none());
+    }
+
+    #[test]
+    fn test_redact_json_nested() {
+        let fields = TracingConfig::default().redact_fields;
+        let mut value = serde_json::json!({
+            "username": "alice",
+            "Password": "hunter2",
+            "profile": {"api_key": "k", "age": 3},
+            "sessions": [{"token": "t1"}, {"token": "t2"}],
+        });
+        redact_json(&mut value, &fields);
+        assert_eq!(value["username"], "alice");
+        assert_eq!(value["Password"], REDACTED);
+        assert_eq!(value["profile"]["api_key"], REDACTED);
+        assert_eq!(value["profile"]["age"], 3);
+        assert_eq!(value["sessions"][1]["token"], REDACTED);
+    }
+
+    #[test]
+    fn test_truncate_utf8_on_char_boundary() {
+        assert_eq!(truncate_utf8("abc", 8), ("abc", false));
+        // "中" 占 3 字节，不能从中间截断
+        assert_eq!(truncate_utf8("a中文", 2), ("a", true));
+        assert_eq!(truncate_utf8("a中文", 4), ("a中", true));
+    }
+
+    #[tokio::test]
+    async fn test_body_logging_keeps_bodies_intact() {
+        shared::init_env();
+
+        let config = TracingConfig {
+            log_request_body: true,
+            log_response_body: true,
+            max_logged_body_bytes: 8,
+            ..TracingConfig::default()
+        };
+        let app = Router::new()
+            .route(
+                "/echo",
+                axum::routing::post(|body: String| async move { body }),
+            )
+            .layer(axum::middleware::from_fn(
+                move |request: Request, next: Next| {
+                    let config = config.clone();
+                    Box::pin(
+                        async move { tracing_middleware_with_config(request, next, config).await },
+                    )
+                },
+            ));
+
+        let payload = r#"{"user":"alice","password":"hunter2"}"#;
+        let request = Request::builder()
+            .method("POST")
+            .uri("/echo")
+            .body(Body::from(payload))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        // 脱敏和截断只作用于日志，handler 和客户端看到的是原始 body
+        assert_eq!(body, payload);
+    }
+
+    /// 声明了长度、读取时出错的 body
+    struct FailingBody;
+
+    impl http_body::Body for FailingBody {
+        type Data = Bytes;
+        type Error = std::io::Error;
+
+        fn poll_frame(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Result<http_body::Frame<Bytes>, Self::Error>>> {
+            std::task::Poll::Ready(Some(Err(std::io::Error::other("connection reset"))))
+        }
+
+        fn size_hint(&self) -> http_body::SizeHint {
+            http_body::SizeHint::with_exact(16)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_body_read_errors_are_not_swallowed() {
+        shared::init_env();
+
+        let config = TracingConfig {
+            log_request_body: true,
+            log_response_body: true,
+            ..TracingConfig::default()
+        };
+        let app = Router::new()
+            .route(
+                "/echo",
+                axum::routing::post(|body: String| async move { body }),
+            )
+            .route(
+                "/broken",
+                get(|| async { Response::new(Body::new(FailingBody)) }),
+            )
+            .layer(axum::middleware::from_fn(
+                move |request: Request, next: Next| {
+                    let config = config.clone();
+                    Box::pin(
+                        async move { tracing_middleware_with_config(request, next, config).await },
+                    )
+                },
+            ));
+
+        // 请求体读取失败：400，不把空 body 交给 handler
+        let request = Request::builder()
+            .method("POST")
+            .uri("/echo")
+            .body(Body::new(FailingBody))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // 响应体读取失败：500，不返回截断后的空 body
+        let request = Request::builder()
+            .uri("/broken")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_sampling_counts_all() {
+        let metrics = Arc::new(AppMetrics::default());
+        let config = TracingConfig {
+            slow_request_threshold_ms: 0,
+            slow_request_log_sample_rate: 3,
+            metrics: Some(metrics.clone()),
+            ..TracingConfig::default()
+        };
+
+        let app = Router::new()
+            .route("/test", get(|| async { "test response" }))
+            .layer(axum::middleware::from_fn(
+                move |request: Request, next: Next| {
+                    let config = config.clone();
+                    Box::pin(
+                        async move { tracing_middleware_with_config(request, next, config).await },
+                    )
+                },
+            ));
+
+        for _ in 0..5 {
+            let request = Request::builder().uri("/test").body(Body::empty()).unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        assert_eq!(metrics.slow_requests.load(Ordering::Relaxed), 5);
+    }
+
+    #[test]
+    fn test_slow_request_log_sampling() {
+        let config = TracingConfig {
+            slow_request_log_sample_rate: 3,
+            ..TracingConfig::default()
+        };
+        let logged: Vec<bool> = (0..6).map(|_| config.record_slow_request()).collect();
+        assert_eq!(logged, vec![true, false, false, true, false, false]);
+
+        // 0 视为不采样
+        let config = TracingConfig {
+            slow_request_log_sample_rate: 0,
+            ..TracingConfig::default()
+        };
+        assert!((0..3).all(|_| config.record_slow_request()));
+    }
 }
@@ -1,6 +1,11 @@
+mod grpc_metrics;
+mod grpc_trace;
 mod middleware;
 mod router;
+#[allow(clippy::module_inception)]
 pub mod server;
 
-pub use middleware::tracing_middleware;
+pub use grpc_metrics::{GrpcMetricsBody, GrpcMetricsLayer, GrpcMetricsService};
+pub use grpc_trace::{grpc_trace_layer, GrpcTrace, GrpcTraceLayer};
+pub use middleware::{error_handling_middleware, tracing_middleware, TimeoutConfig};
 pub use server::HttpServer;
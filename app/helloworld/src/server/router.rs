@@ -1,15 +1,47 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use axum::{response::Json, routing::get, Router};
+use axum::{
+    error_handling::HandleErrorLayer,
+    extract::{Extension, Request},
+    http::header::{HeaderName, HeaderValue, AUTHORIZATION, CACHE_CONTROL},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Json},
+    routing::{get, post},
+    BoxError, Router,
+};
+use serde::Serialize;
+use shared::config::{HealthConfig, HealthFormat};
+use tower::ServiceBuilder;
 use tower_http::{
     request_id::{MakeRequestId, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
     timeout::TimeoutLayer,
     trace::TraceLayer,
 };
-use tracing::{info_span, Span};
+use tracing::{field::Empty, info_span, Span};
+
+use super::{
+    middleware::{
+        header_limit_middleware, metrics_middleware, request_start_middleware,
+        required_header_middleware, RequestStart, TracingConfig,
+    },
+    server::{HttpServer, MaintenanceMode},
+};
+use crate::build_info::BuildInfo;
+use crate::service::response::{ErrCode, Response};
 
-use super::{middleware::TracingConfig, server::HttpServer};
+/// 客户端传入或 `SetRequestIdLayer` 生成的请求ID
+const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// 生成ID的接口每次结果都不同，禁止任何缓存
+const NO_STORE: [(HeaderName, &str); 1] = [(CACHE_CONTROL, "no-store")];
+
+/// 构造 `Cache-Control` 响应头
+fn cache_control(value: String) -> [(HeaderName, HeaderValue); 1] {
+    let value = HeaderValue::try_from(value).expect("cache-control value is ascii");
+    [(CACHE_CONTROL, value)]
+}
 
 /// 自定义请求 ID 生成器
 #[derive(Clone, Default)]
@@ -29,16 +61,155 @@ impl HttpServer {
 
     pub fn create_router_with_config(&self, _tracing_config: TracingConfig) -> Router {
         let hello_service = Arc::clone(&self.hello_world_service);
+        let log_request_id = self.cfg.log_request_id;
+        let decode_cache = cache_control(format!(
+            "public, max-age={}, immutable",
+            self.cfg.cache.decode_max_age_secs
+        ));
+        let layout_cache = cache_control(format!(
+            "public, max-age={}",
+            self.cfg.cache.layout_max_age_secs
+        ));
 
-        Router::new()
+        let mut router = Router::new()
             // API 路由
             .route("/ping", get(|| async { "ok" }))
-            .route("/health", get(self::health_check))
+            .route(
+                "/health",
+                get({
+                    let health = Arc::new(self.cfg.health.clone());
+                    let maintenance = self.maintenance.clone();
+                    move || async move { health_check(&health, maintenance.is_enabled()) }
+                }),
+            )
+            .route(
+                "/health/ready",
+                get({
+                    let service = hello_service.clone();
+                    let timeout = Duration::from_millis(self.cfg.health.ready_timeout_ms);
+                    let maintenance = self.maintenance.clone();
+                    move || async move {
+                        if let Some(resp) = maintenance.reject() {
+                            return resp;
+                        }
+                        (NO_STORE, service.readiness(timeout).await).into_response()
+                    }
+                }),
+            )
+            .route("/version", get(self::version_info))
             .route(
                 "/id",
                 get({
                     let service = hello_service.clone();
-                    move || async move { service.generate_id().await }
+                    let timing = self.cfg.server_timing;
+                    let maintenance = self.maintenance.clone();
+                    move |start: Option<Extension<RequestStart>>, query| async move {
+                        if let Some(resp) = maintenance.reject() {
+                            return resp;
+                        }
+                        let Json(resp) = service.generate_id(query).await;
+                        (NO_STORE, Json(server_timed(timing, start, resp))).into_response()
+                    }
+                }),
+            )
+            .route(
+                "/ulid",
+                get({
+                    let service = hello_service.clone();
+                    let maintenance = self.maintenance.clone();
+                    move || async move {
+                        if let Some(resp) = maintenance.reject() {
+                            return resp;
+                        }
+                        (NO_STORE, service.generate_ulid().await).into_response()
+                    }
+                }),
+            )
+            .route(
+                "/uuidv7",
+                get({
+                    let service = hello_service.clone();
+                    let maintenance = self.maintenance.clone();
+                    move || async move {
+                        if let Some(resp) = maintenance.reject() {
+                            return resp;
+                        }
+                        (NO_STORE, service.generate_uuidv7().await).into_response()
+                    }
+                }),
+            )
+            .route(
+                "/raw/id",
+                get({
+                    let service = hello_service.clone();
+                    let maintenance = self.maintenance.clone();
+                    move || async move {
+                        if let Some(resp) = maintenance.reject() {
+                            return resp;
+                        }
+                        (NO_STORE, service.generate_raw_id().await).into_response()
+                    }
+                }),
+            )
+            .route(
+                "/raw/id/bytes",
+                get({
+                    let service = hello_service.clone();
+                    let maintenance = self.maintenance.clone();
+                    move || async move {
+                        if let Some(resp) = maintenance.reject() {
+                            return resp;
+                        }
+                        (NO_STORE, service.generate_raw_id_bytes().await).into_response()
+                    }
+                }),
+            )
+            .route(
+                "/layout",
+                get({
+                    let service = hello_service.clone();
+                    move || {
+                        let headers = layout_cache.clone();
+                        async move { (headers, service.layout().await) }
+                    }
+                }),
+            )
+            .route(
+                "/stats",
+                get({
+                    let service = hello_service.clone();
+                    move || async move { service.stats().await }
+                }),
+            )
+            .route(
+                "/generator/stats",
+                get({
+                    let service = hello_service.clone();
+                    move || async move { service.stats().await }
+                }),
+            )
+            .route(
+                "/verify",
+                get({
+                    let service = hello_service.clone();
+                    move |query| async move { service.verify_id(query).await }
+                }),
+            )
+            .route(
+                "/compare",
+                get({
+                    let service = hello_service.clone();
+                    move |query| async move { service.compare_ids(query).await }
+                }),
+            )
+            .route(
+                "/decode/{id}",
+                get({
+                    let service = hello_service.clone();
+                    move |path, query| {
+                        let headers = decode_cache.clone();
+                        async move { (headers, service.decode_id(path, query).await) }
+                    }
                 }),
             )
             .route(
@@ -48,29 +219,30 @@ impl HttpServer {
                     move |query| async move { service.get_user(query).await }
                 }),
             )
+            .merge(self.batch_router())
+            .merge(self.admin_router())
+            .fallback(route_not_found);
+        if let Some(required) = &self.cfg.required_header {
+            router = router.layer(middleware::from_fn_with_state(
+                required.clone(),
+                required_header_middleware,
+            ));
+        }
+
+        let router = router
+            .layer(middleware::from_fn_with_state(
+                self.cfg.header_limit.clone(),
+                header_limit_middleware,
+            ))
             // 应用中间件层
             .layer(TimeoutLayer::new(Duration::from_secs(30)))
+            .layer(middleware::from_fn(request_start_middleware))
             .layer(PropagateRequestIdLayer::x_request_id())
-            .layer(SetRequestIdLayer::x_request_id(MyMakeRequestId::default()))
             // 使用简化的 TraceLayer，让 OpenTelemetryLayer 自动处理
             .layer(
                 TraceLayer::new_for_http()
-                    .make_span_with(|request: &axum::http::Request<_>| {
-                        let method = request.method();
-                        let uri = request.uri();
-                        let path = uri.path();
-                        let query = uri.query().unwrap_or("");
-                        let request_id = uuid::Uuid::new_v4();
-
-                        info_span!(
-                            "http_request",
-                            // 使用OpenTelemetry语义约定
-                            "http.method" = %method,
-                            "http.route" = %path,
-                            "http.url" = %uri,
-                            "request.id" = %request_id,
-                            "request.query" = %query,
-                        )
+                    .make_span_with(move |request: &axum::http::Request<_>| {
+                        http_request_span(request, log_request_id)
                     })
                     .on_request(|_request: &axum::http::Request<_>, _span: &Span| {
                         tracing::info!("Processing HTTP request");
@@ -96,15 +268,1437 @@ impl HttpServer {
                         },
                     ),
             )
+            // 放在 TraceLayer 外层，创建 span 时请求ID已经确定
+            .layer(SetRequestIdLayer::x_request_id(MyMakeRequestId));
+        // 放在最外层，请求头校验失败、超时等被中间件拒绝的请求也计入
+        match &self.metrics {
+            Some(metrics) => router.layer(middleware::from_fn_with_state(
+                Arc::clone(metrics),
+                metrics_middleware,
+            )),
+            None => router,
+        }
+    }
+}
+
+/// 请求 span，开启 `log_request_id` 时记录 `x-request-id`
+fn http_request_span<B>(request: &axum::http::Request<B>, log_request_id: bool) -> Span {
+    let uri = request.uri();
+    let span = info_span!(
+        "http_request",
+        // 使用OpenTelemetry语义约定
+        "http.method" = %request.method(),
+        "http.route" = %uri.path(),
+        "http.url" = %uri,
+        "request.id" = Empty,
+        // 配置了 required_header 时由 required_header_middleware 记录
+        "request.required_header" = Empty,
+        "request.query" = %uri.query().unwrap_or(""),
+    );
+    if log_request_id {
+        if let Some(request_id) = request
+            .headers()
+            .get(X_REQUEST_ID)
+            .and_then(|v| v.to_str().ok())
+        {
+            span.record("request.id", request_id);
+        }
+    }
+    span
+}
+
+impl HttpServer {
+    /// 批量接口路由
+    ///
+    /// 批量请求开销远大于单个 ID，单独限制并发，超出上限时直接返回 503，
+    /// 避免拖慢 `/id` 等普通接口。后续的批量/流式接口也应挂在这里。
+    fn batch_router(&self) -> Router {
+        let hello_service = Arc::clone(&self.hello_world_service);
+        let timing = self.cfg.server_timing;
+
+        Router::new()
+            .route(
+                "/ids",
+                get({
+                    let service = hello_service.clone();
+                    let maintenance = self.maintenance.clone();
+                    move |start: Option<Extension<RequestStart>>, query| async move {
+                        if let Some(resp) = maintenance.reject() {
+                            return resp;
+                        }
+                        let resp = service.generate_ids(query).await;
+                        (NO_STORE, server_timed(timing, start, resp)).into_response()
+                    }
+                }),
+            )
+            .layer(
+                ServiceBuilder::new()
+                    .layer(HandleErrorLayer::new(handle_batch_overload))
+                    .load_shed()
+                    .concurrency_limit(self.cfg.batch.max_concurrent_ops),
+            )
+    }
+}
+
+impl HttpServer {
+    /// 管理接口路由，未配置 `admin.token` 时不挂载任何路由
+    fn admin_router(&self) -> Router {
+        let Some(token) = self.cfg.admin.token.clone() else {
+            return Router::new();
+        };
+        let telemetry = self.telemetry.clone();
+        let hello_service = Arc::clone(&self.hello_world_service);
+
+        Router::new()
+            .route(
+                "/admin/flush",
+                post(move || async move { flush_telemetry(&telemetry) }),
+            )
+            .route(
+                "/admin/mint",
+                get({
+                    let service = hello_service.clone();
+                    move |query| async move { service.mint_backfill_id(query).await }
+                }),
+            )
+            .route(
+                "/admin/maintenance",
+                post({
+                    let maintenance = self.maintenance.clone();
+                    move |Json(req): Json<MaintenanceReq>| async move {
+                        set_maintenance(&maintenance, req.enabled)
+                    }
+                }),
+            )
+            .route(
+                "/admin/recent-errors",
+                get({
+                    let service = hello_service.clone();
+                    move || async move { service.recent_errors().await }
+                }),
+            )
+            .route(
+                "/admin/worker-id",
+                post(move |body| async move { hello_service.rotate_worker_id(body).await }),
+            )
+            .route_layer(middleware::from_fn(move |req: Request, next: Next| {
+                let token = token.clone();
+                async move { require_admin_token(&token, req, next).await }
+            }))
+    }
+}
+
+/// 校验 `Authorization: Bearer <token>`
+async fn require_admin_token(token: &str, req: Request, next: Next) -> axum::response::Response {
+    let authorized = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|v| v == token);
+    if !authorized {
+        return Response::<()>::failed(ErrCode::Unauthorized, Some("invalid admin token"))
+            .into_response();
     }
+    next.run(req).await
 }
 
-/// 健康检查端点
-async fn health_check() -> Json<serde_json::Value> {
-    Json(serde_json::json!({
-        "status": "healthy",
-        "timestamp": chrono::Utc::now().to_rfc3339(),
-        "service": "tinyid",
-        "version": env!("CARGO_PKG_VERSION")
+/// 立即导出缓存的 span
+///
+/// 指标通过 Prometheus 拉取，`AppMetrics` 直接读取原子计数，没有需要 flush 的缓冲。
+fn flush_telemetry(telemetry: &shared::traces::TelemetryFlusher) -> Response<FlushResp> {
+    if let Err(e) = telemetry.force_flush() {
+        tracing::error!(error = %e, "force flush failed");
+        return Response::failed(ErrCode::InternalServerError, Some("flush failed"));
+    }
+    tracing::info!("telemetry flushed on admin request");
+    Response::success(Some(FlushResp {
+        traces: telemetry.is_enabled(),
     }))
 }
+
+#[derive(Debug, serde::Deserialize)]
+struct MaintenanceReq {
+    enabled: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct MaintenanceResp {
+    previous: bool,
+    enabled: bool,
+}
+
+/// 开关维护模式
+fn set_maintenance(maintenance: &MaintenanceMode, enabled: bool) -> Response<MaintenanceResp> {
+    let previous = maintenance.set(enabled);
+    tracing::warn!(
+        previous,
+        enabled,
+        "maintenance mode switched by admin request"
+    );
+    Response::success(Some(MaintenanceResp { previous, enabled }))
+}
+
+#[derive(Debug, serde::Serialize)]
+struct FlushResp {
+    /// 是否实际 flush 了 tracer provider
+    traces: bool,
+}
+
+/// 开启 `server_timing` 时，在成功响应中附带从中间件记录的开始时间起算的耗时
+fn server_timed<T: Serialize>(
+    enabled: bool,
+    start: Option<Extension<RequestStart>>,
+    resp: Response<T>,
+) -> Response<T> {
+    match start {
+        Some(Extension(start)) if enabled && resp.code.is_success() => {
+            resp.with_server_time(start.elapsed())
+        }
+        _ => resp,
+    }
+}
+
+/// 未匹配路由的默认响应，保持与其它接口一致的 `Response<T>` 格式
+async fn route_not_found(uri: axum::http::Uri) -> Response<()> {
+    Response::failed(
+        ErrCode::NotFound,
+        Some(format!("route {} not found", uri.path())),
+    )
+}
+
+/// 批量接口并发超限
+async fn handle_batch_overload(err: BoxError) -> axum::response::Response {
+    tracing::warn!(error = %err, "batch request rejected");
+    Response::<()>::failed(
+        ErrCode::ServiceUnavailable,
+        Some("too many concurrent batch requests"),
+    )
+    .into_response()
+}
+
+/// 健康检查端点，响应格式由 `ServerConfig::health` 决定
+///
+/// 维护模式下返回 503（完整格式中 status 为 "maintenance"），让负载均衡摘除该节点。
+fn health_check(cfg: &HealthConfig, maintenance: bool) -> axum::response::Response {
+    let status = if maintenance {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    if cfg.format == HealthFormat::Minimal {
+        return status.into_response();
+    }
+
+    let mut body = serde_json::Map::new();
+    for (key, value) in &cfg.extra_fields {
+        body.insert(key.clone(), serde_json::Value::String(value.clone()));
+    }
+    // 内置字段优先，避免自定义字段把状态覆盖掉
+    let state = if maintenance {
+        "maintenance"
+    } else {
+        "healthy"
+    };
+    body.insert("status".into(), state.into());
+    body.insert("timestamp".into(), chrono::Utc::now().to_rfc3339().into());
+    body.insert("service".into(), "tinyid".into());
+    body.insert("version".into(), env!("CARGO_PKG_VERSION").into());
+
+    (status, Json(serde_json::Value::Object(body))).into_response()
+}
+
+/// 构建信息端点
+async fn version_info() -> Json<Response<BuildInfo>> {
+    Json(Response::success(Some(BuildInfo::current())))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use shared::config::{Environment, IdKind, RequiredHeaderConfig, ServerConfig};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::biz::{HelloWorldUseCase, UserDemoUseCase};
+    use crate::core::{IDGenerator, IdGeneratorRegistry, StaticCoordinator, WorkerLease};
+    use crate::data::{new_user_client, HelloWorldRepoImpl};
+    use shared::proto::user::user_demo_server::{UserDemo, UserDemoServer};
+    use shared::proto::user::{
+        GetUserRequest, GetUserResponse, ListUsersRequest, ListUsersResponse, User,
+    };
+
+    pub(crate) fn test_server(cfg: ServerConfig) -> HttpServer {
+        let generator = Arc::new(IDGenerator::new(cfg.id_generator.clone()).unwrap());
+        let user_client = new_user_client(cfg.user_rpc.clone()).unwrap();
+        let namespaces = IdGeneratorRegistry::new(&cfg.namespaces, &generator).unwrap();
+        let repo = Arc::new(
+            HelloWorldRepoImpl::new(generator, user_client)
+                .unwrap()
+                .with_namespaces(namespaces),
+        );
+        HttpServer::new(
+            Arc::new(cfg),
+            Arc::new(HelloWorldUseCase::new(repo.clone())),
+            Arc::new(UserDemoUseCase::new(repo)),
+        )
+    }
+
+    pub(crate) async fn get(router: Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = router
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+        (status, body)
+    }
+
+    /// 收集 span 上记录的 `request.id`
+    #[derive(Clone, Default)]
+    struct RequestIdCapture(Arc<std::sync::Mutex<Vec<String>>>);
+
+    struct RequestIdVisitor<'a>(&'a mut Vec<String>);
+
+    impl tracing::field::Visit for RequestIdVisitor<'_> {
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            if field.name() == "request.id" {
+                self.0.push(value.to_string());
+            }
+        }
+
+        fn record_debug(&mut self, _field: &tracing::field::Field, _value: &dyn std::fmt::Debug) {}
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for RequestIdCapture {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            _id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            attrs.record(&mut RequestIdVisitor(&mut self.0.lock().unwrap()));
+        }
+
+        fn on_record(
+            &self,
+            _id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            values.record(&mut RequestIdVisitor(&mut self.0.lock().unwrap()));
+        }
+    }
+
+    fn ping_request(request_id: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().uri("/ping");
+        if let Some(request_id) = request_id {
+            builder = builder.header("x-request-id", request_id);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_id_recorded_on_span() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let capture = RequestIdCapture::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+
+        // 客户端传入的请求ID原样记录
+        router
+            .clone()
+            .oneshot(ping_request(Some("client-req-1")))
+            .await
+            .unwrap();
+        // 未传入时记录服务端生成、并在响应头中返回的ID
+        let response = router.oneshot(ping_request(None)).await.unwrap();
+        let generated = response.headers()["x-request-id"].to_str().unwrap();
+        assert_eq!(
+            *capture.0.lock().unwrap(),
+            vec!["client-req-1".to_string(), generated.to_string()]
+        );
+
+        capture.0.lock().unwrap().clear();
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.log_request_id = false;
+        test_server(cfg)
+            .create_router()
+            .oneshot(ping_request(Some("client-req-2")))
+            .await
+            .unwrap();
+        assert!(capture.0.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_version_route() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+
+        let (status, body) = get(router, "/version").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["code"], 0);
+        assert_eq!(body["data"]["version"], env!("CARGO_PKG_VERSION"));
+        assert!(body["data"]["git_sha"].is_string());
+        assert!(body["data"]["rustc_version"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_health_full_with_extra_fields() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.health
+            .extra_fields
+            .insert("region".into(), "eu-west-1".into());
+        cfg.health
+            .extra_fields
+            .insert("status".into(), "overridden".into());
+        let router = test_server(cfg).create_router();
+
+        let (status, body) = get(router, "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "healthy");
+        assert_eq!(body["region"], "eu-west-1");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    }
+
+    async fn post_flush(router: Router, auth: Option<&str>) -> StatusCode {
+        let mut req = Request::builder().method("POST").uri("/admin/flush");
+        if let Some(auth) = auth {
+            req = req.header(AUTHORIZATION, auth);
+        }
+        router
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_admin_flush() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.admin.token = Some("secret".into());
+        let router = test_server(cfg).create_router();
+
+        assert_eq!(
+            post_flush(router.clone(), None).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            post_flush(router.clone(), Some("Bearer wrong")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            post_flush(router, Some("Bearer secret")).await,
+            StatusCode::OK
+        );
+    }
+
+    async fn post_worker_id(router: Router, body: &str) -> (StatusCode, serde_json::Value) {
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/admin/worker-id")
+                    .header(AUTHORIZATION, "Bearer secret")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_admin_maintenance_mode() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.admin.token = Some("secret".into());
+        cfg.maintenance.retry_after_secs = 15;
+        let router = test_server(cfg).create_router();
+        let switch = |enabled: bool| {
+            let router = router.clone();
+            async move {
+                let request = Request::builder()
+                    .method("POST")
+                    .uri("/admin/maintenance")
+                    .header(AUTHORIZATION, "Bearer secret")
+                    .header("content-type", "application/json")
+                    .body(Body::from(format!(r#"{{"enabled":{}}}"#, enabled)))
+                    .unwrap();
+                let response = router.oneshot(request).await.unwrap();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+            }
+        };
+
+        let body = switch(true).await;
+        assert_eq!(body["data"]["previous"], false);
+        assert_eq!(body["data"]["enabled"], true);
+
+        let response = router
+            .clone()
+            .oneshot(Request::builder().uri("/id").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()["retry-after"], "15");
+        let (status, body) = get(router.clone(), "/ids?count=2").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["code"], 503);
+        let (status, body) = get(router.clone(), "/health").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "maintenance");
+        // 非生成接口不受影响
+        let (status, _) = get(router.clone(), "/layout").await;
+        assert_eq!(status, StatusCode::OK);
+
+        switch(false).await;
+        let (status, _) = get(router.clone(), "/id").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, body) = get(router, "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "healthy");
+    }
+
+    #[tokio::test]
+    async fn test_admin_recent_errors() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.admin.token = Some("secret".into());
+        let router = test_server(cfg).create_router();
+        let admin_get = |uri: &'static str| {
+            let router = router.clone();
+            async move {
+                let request = Request::builder()
+                    .uri(uri)
+                    .header(AUTHORIZATION, "Bearer secret")
+                    .body(Body::empty())
+                    .unwrap();
+                let response = router.oneshot(request).await.unwrap();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+            }
+        };
+
+        let body = admin_get("/admin/recent-errors").await;
+        assert_eq!(body["data"], serde_json::json!([]));
+
+        // 未开启补录时 mint 失败，错误被采样
+        admin_get("/admin/mint?from_timestamp=0&sequence=0").await;
+        let body = admin_get("/admin/recent-errors").await;
+        let samples = body["data"].as_array().unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0]["kind"], "InvalidRequest");
+        assert!(samples[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("backfill is disabled"));
+        assert!(samples[0]["timestamp_ms"].as_i64().unwrap() > 0);
+
+        let (status, _) = get(router, "/admin/recent-errors").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_admin_mint_backfill_id() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.admin.token = Some("secret".into());
+        cfg.id_generator.backfill_sequences = 8;
+        let timestamp = cfg.id_generator.epoch + 1_000;
+        let router = test_server(cfg).create_router();
+
+        let mint = |uri: String| {
+            let request = Request::builder()
+                .uri(uri)
+                .header(AUTHORIZATION, "Bearer secret")
+                .body(Body::empty())
+                .unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<serde_json::Value>(&bytes).unwrap(),
+                )
+            }
+        };
+
+        let (status, body) = mint(format!(
+            "/admin/mint?from_timestamp={}&sequence=2",
+            timestamp
+        ))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["timestamp"], timestamp);
+        assert_eq!(body["data"]["sequence"], 4095 - 8 + 1 + 2);
+        assert_eq!(body["data"]["backfill"], true);
+
+        let (status, _) = mint(format!(
+            "/admin/mint?from_timestamp={}&sequence=8",
+            timestamp
+        ))
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = mint("/admin/mint?from_timestamp=0".to_string()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = get(router.clone(), "/admin/mint?from_timestamp=0").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_admin_rotate_worker_id() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.admin.token = Some("secret".into());
+        let legacy = cfg.id_generator.clone();
+        cfg.namespaces.insert("legacy".to_string(), legacy);
+        let router = test_server(cfg).create_router();
+
+        let (_, body) = get(router.clone(), "/id").await;
+        let before = body["data"]["id"].as_u64().unwrap();
+
+        let (status, body) = post_worker_id(router.clone(), r#"{"worker_id": 5}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["previous_worker_id"], 0);
+        assert_eq!(body["data"]["worker_id"], 5);
+
+        let (_, body) = get(router.clone(), "/id").await;
+        let after = body["data"]["id"].as_u64().unwrap();
+        assert!(after > before);
+        let (_, body) = get(router.clone(), &format!("/verify?id={}", after)).await;
+        assert_eq!(body["data"]["worker_id"], 5);
+        // 命名空间沿用节点的 worker id，一起切换
+        let (_, body) = get(router.clone(), "/id?namespace=legacy").await;
+        let id = body["data"]["id"].as_u64().unwrap();
+        let (_, body) = get(router.clone(), &format!("/decode/{id}?namespace=legacy")).await;
+        assert_eq!(body["data"]["worker_id"], 5);
+
+        let (status, _) = post_worker_id(router, r#"{"worker_id": 100000}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_rotate_worker_id_under_load() {
+        use crate::biz::HelloWorldRepo;
+
+        let cfg = ServerConfig::default_for_test();
+        let generator = Arc::new(IDGenerator::new(cfg.id_generator.clone()).unwrap());
+        let user_client = new_user_client(cfg.user_rpc.clone()).unwrap();
+        let repo = Arc::new(HelloWorldRepoImpl::new(generator, user_client).unwrap());
+        let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        let workers: Vec<_> = (0..4)
+            .map(|i| {
+                let repo = repo.clone();
+                let stop = stop.clone();
+                tokio::spawn(async move {
+                    let mut ids = Vec::new();
+                    while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                        if i % 2 == 0 {
+                            ids.push(repo.generate_id(None).await.unwrap());
+                        } else {
+                            ids.extend(repo.generate_ids(8, None).await.unwrap());
+                        }
+                        tokio::task::yield_now().await;
+                    }
+                    ids
+                })
+            })
+            .collect();
+
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        assert_eq!(repo.rotate_worker_id(5, None).await.unwrap(), 0);
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        stop.store(true, std::sync::atomic::Ordering::Relaxed);
+
+        // 旧 worker id 发出的ID都早于新 worker id 的第一个毫秒
+        let (mut old_max, mut new_min) = (0, u64::MAX);
+        for worker in workers {
+            for id in worker.await.unwrap() {
+                let decoded = repo.decode_id(id);
+                match decoded.worker_id {
+                    0 => old_max = old_max.max(decoded.timestamp),
+                    5 => new_min = new_min.min(decoded.timestamp),
+                    other => panic!("unexpected worker id {}", other),
+                }
+            }
+        }
+        assert!(new_min != u64::MAX, "no id issued after rotation");
+        assert!(old_max < new_min, "old {} >= new {}", old_max, new_min);
+    }
+
+    #[tokio::test]
+    async fn test_admin_disabled_without_token() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+        assert_eq!(
+            post_flush(router, Some("Bearer secret")).await,
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn test_raw_id_without_envelope() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+
+        let (status, body) = get(router, "/raw/id").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["id"].as_u64().unwrap() > 0);
+        assert_eq!(body.as_object().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_id_as_big_endian_bytes() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/raw/id/bytes")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-type"],
+            "application/octet-stream"
+        );
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let raw = u64::from_be_bytes(bytes[..].try_into().unwrap());
+
+        let (_, body) = get(router, "/id?encoding=bytes").await;
+        let encoded = body["data"]["id"].as_str().unwrap();
+        let id = crate::generator::IdEncoding::Bytes.decode(encoded).unwrap();
+        assert!(id > raw);
+    }
+
+    #[tokio::test]
+    async fn test_id_format_base62() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+        let (_, raw) = get(router.clone(), "/raw/id").await;
+
+        let (status, body) = get(router.clone(), "/id?format=base62").await;
+        assert_eq!(status, StatusCode::OK);
+        let encoded = body["data"]["id"].as_str().unwrap();
+        assert!(encoded.bytes().all(|b| b.is_ascii_alphanumeric()));
+        let id = crate::generator::decode_base62(encoded).unwrap();
+        assert!(id > raw["id"].as_u64().unwrap());
+
+        let (status, _) = get(router, "/id?format=base36").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_ulid_route_and_id_kind() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+        let (status, first) = get(router.clone(), "/ulid").await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, second) = get(router.clone(), "/ulid").await;
+        let (first, second) = (
+            first["data"]["id"].as_str().unwrap().to_string(),
+            second["data"]["id"].as_str().unwrap().to_string(),
+        );
+        assert_eq!(first.len(), 26);
+        assert!(first < second);
+        // 默认 /id 仍返回雪花ID
+        let (_, body) = get(router, "/id").await;
+        assert!(body["data"]["id"].is_u64());
+
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.id_kind = IdKind::Ulid;
+        let router = test_server(cfg).create_router();
+        let (status, body) = get(router, "/id").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["id"].as_str().unwrap().len(), 26);
+    }
+
+    #[tokio::test]
+    async fn test_uuidv7_route() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+        let mut previous = None;
+        for _ in 0..3 {
+            let (status, body) = get(router.clone(), "/uuidv7").await;
+            assert_eq!(status, StatusCode::OK);
+            let uuid: uuid::Uuid = body["data"]["id"].as_str().unwrap().parse().unwrap();
+            assert_eq!(uuid.get_version_num(), 7);
+            assert!(previous < Some(uuid));
+            previous = Some(uuid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_serves_until_error() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.port = 0;
+        // 正常情况下一直运行，不会立即返回
+        let running =
+            tokio::time::timeout(Duration::from_millis(200), test_server(cfg).run()).await;
+        assert!(running.is_err());
+
+        // 端口被占用时返回绑定错误
+        let occupied = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.port = occupied.local_addr().unwrap().port();
+        let result = tokio::time::timeout(Duration::from_secs(5), test_server(cfg).run())
+            .await
+            .unwrap();
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bind_with_tcp_options() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.port = 0;
+        cfg.tcp.backlog = 8;
+        let listener = test_server(cfg).bind().await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(addr.ip().is_loopback());
+        tokio::net::TcpStream::connect(addr).await.unwrap();
+
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.addr = "not a host".to_string();
+        assert!(test_server(cfg).bind().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_header_limit() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.header_limit.max_headers = 4;
+        cfg.header_limit.max_header_bytes = 64;
+        let router = test_server(cfg).create_router();
+
+        let send = |headers: Vec<(String, String)>| {
+            let router = router.clone();
+            async move {
+                let mut req = Request::builder().uri("/ping");
+                for (name, value) in headers {
+                    req = req.header(name, value);
+                }
+                router
+                    .oneshot(req.body(Body::empty()).unwrap())
+                    .await
+                    .unwrap()
+                    .status()
+            }
+        };
+
+        assert_eq!(send(vec![("x-a".into(), "1".into())]).await, StatusCode::OK);
+        let many = (0..5)
+            .map(|i| (format!("x-{}", i), "1".to_string()))
+            .collect();
+        assert_eq!(send(many).await, StatusCode::BAD_REQUEST);
+        let large = vec![("x-big".to_string(), "v".repeat(100))];
+        assert_eq!(send(large).await, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_required_header() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.required_header = Some(RequiredHeaderConfig {
+            name: "X-Client-Id".to_string(),
+            ..RequiredHeaderConfig::default()
+        });
+        let router = test_server(cfg).create_router();
+
+        let (status, body) = get(router.clone(), "/id").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], 400);
+        assert_eq!(body["msg"], "missing required header X-Client-Id");
+
+        let request = Request::builder()
+            .uri("/id")
+            .header("x-client-id", "billing-svc")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // 探活接口默认豁免
+        let (status, _) = get(router, "/health").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_into_router_nested_under_prefix() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.required_header = Some(RequiredHeaderConfig {
+            name: "X-Client-Id".to_string(),
+            ..RequiredHeaderConfig::default()
+        });
+        let app = Router::new()
+            .route("/ping", axum::routing::get(|| async { "pong" }))
+            .nest("/internal/ids", test_server(cfg).into_router());
+
+        let (status, body) = get(app.clone(), "/internal/ids/id").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["msg"], "missing required header X-Client-Id");
+
+        // 豁免路径按去掉前缀后的路径匹配
+        let (status, body) = get(app.clone(), "/internal/ids/health").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["status"], "healthy");
+
+        // 宿主应用自己的路由不受影响
+        let response = app
+            .oneshot(Request::builder().uri("/ping").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_compare_ids() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+        let (_, first) = get(router.clone(), "/raw/id").await;
+        let (_, second) = get(router.clone(), "/raw/id").await;
+        let (a, b) = (
+            first["id"].as_u64().unwrap(),
+            second["id"].as_u64().unwrap(),
+        );
+
+        let (status, body) = get(router.clone(), &format!("/compare?a={a}&b={b}")).await;
+        assert_eq!(status, StatusCode::OK);
+        let data = &body["data"];
+        assert_eq!(data["older"], "a");
+        assert_eq!(
+            data["delta_ms"].as_i64().unwrap(),
+            data["b"]["timestamp"].as_i64().unwrap() - data["a"]["timestamp"].as_i64().unwrap()
+        );
+        assert_eq!(data["same_worker"], true);
+        assert_eq!(data["same_datacenter"], true);
+
+        let (_, body) = get(router.clone(), &format!("/compare?a={b}&b={a}")).await;
+        assert_eq!(body["data"]["older"], "b");
+        assert!(body["data"]["delta_ms"].as_i64().unwrap() <= 0);
+        let (_, body) = get(router.clone(), &format!("/compare?a={a}&b={a}")).await;
+        assert_eq!(body["data"]["older"], "neither");
+
+        // 时间戳位为 0 的ID不在 epoch 之后
+        let (status, body) = get(router.clone(), &format!("/compare?a={a}&b=5")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["msg"].as_str().unwrap().contains("not after epoch"));
+        let (status, _) = get(router, &format!("/compare?a=abc&b={b}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_layout_and_stats_capacity() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+
+        let (status, body) = get(router.clone(), "/layout").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["sequence_bits"], 12);
+        assert_eq!(body["data"]["capacity_per_second"], 4_095_000);
+
+        get(router.clone(), "/id").await;
+        let (status, body) = get(router.clone(), "/stats").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["total_generated"], 1);
+        assert_eq!(body["data"]["capacity_per_second"], 4_095_000);
+
+        let (status, body) = get(router, "/generator/stats").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["total_generated"], 1);
+        assert_eq!(body["data"]["worker_id"], 0);
+        assert_eq!(body["data"]["datacenter_id"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_cache_control_headers() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+        let cache_control = |uri: &str| {
+            let router = router.clone();
+            let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                response.headers()[CACHE_CONTROL]
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            }
+        };
+
+        assert_eq!(cache_control("/id").await, "no-store");
+        assert_eq!(cache_control("/raw/id").await, "no-store");
+        assert_eq!(cache_control("/ids?count=2").await, "no-store");
+        assert_eq!(cache_control("/layout").await, "public, max-age=60");
+        assert_eq!(
+            cache_control("/decode/123").await,
+            "public, max-age=86400, immutable"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_decode_route_matches_verify() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+
+        let (_, body) = get(router.clone(), "/id").await;
+        let id = body["data"]["id"].as_u64().unwrap();
+        let (status, decoded) = get(router.clone(), &format!("/decode/{id}")).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, verified) = get(router.clone(), &format!("/verify?id={id}")).await;
+        assert_eq!(decoded, verified);
+
+        let (status, _) = get(router, "/decode/not-a-number").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_decode_with_their_own_epoch() {
+        let legacy_epoch = 1577836800000; // 2020-01-01 00:00:00 UTC
+        let mut cfg = ServerConfig::default_for_test();
+        let mut legacy = cfg.id_generator.clone();
+        legacy.epoch = legacy_epoch;
+        cfg.namespaces.insert("legacy".to_string(), legacy);
+        let default_epoch = cfg.id_generator.epoch;
+        let router = test_server(cfg).create_router();
+
+        let before = chrono::Utc::now().timestamp_millis() as u64;
+        let (status, body) = get(router.clone(), "/id?namespace=legacy").await;
+        assert_eq!(status, StatusCode::OK);
+        let id = body["data"]["id"].as_u64().unwrap();
+
+        // 按生成它的命名空间拆解，时间戳就是生成时刻
+        for uri in [
+            format!("/decode/{id}?namespace=legacy"),
+            format!("/verify?id={id}&namespace=legacy"),
+        ] {
+            let (status, body) = get(router.clone(), &uri).await;
+            assert_eq!(status, StatusCode::OK);
+            let timestamp = body["data"]["timestamp"].as_u64().unwrap();
+            assert!(timestamp.abs_diff(before) < 5_000, "{uri}: {timestamp}");
+        }
+        // 套用默认 epoch 会整体偏移两个 epoch 的差值
+        let (_, body) = get(router.clone(), &format!("/decode/{id}")).await;
+        let shifted = body["data"]["timestamp"].as_u64().unwrap();
+        assert!(shifted - before >= default_epoch - legacy_epoch - 5_000);
+
+        for uri in [
+            "/id?namespace=missing".to_string(),
+            format!("/decode/{id}?namespace=missing"),
+            format!("/verify?id={id}&namespace=missing"),
+        ] {
+            // /id 沿用 JSON 包装，HTTP 状态恒为 200，以 body 中的 code 为准
+            let (_, body) = get(router.clone(), &uri).await;
+            assert_eq!(body["code"], 400, "{uri}");
+            assert!(body["msg"].as_str().unwrap().contains("missing"), "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unknown_route_returns_envelope() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+
+        let (status, body) = get(router, "/no/such/route").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], 404);
+        assert_eq!(body["msg"], "route /no/such/route not found");
+    }
+
+    #[tokio::test]
+    async fn test_verify_flags_environment_mismatch() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.id_generator.layout = Some("39-3-7-12".parse().unwrap());
+        cfg.id_generator.environment_bits = 2;
+        cfg.id_generator.environment = Some(Environment::Staging);
+        let staging = IDGenerator::new(cfg.id_generator.clone()).unwrap();
+        let staging_id = staging.next_id().unwrap();
+
+        cfg.id_generator.environment = Some(Environment::Prod);
+        let router = test_server(cfg).create_router();
+
+        let (status, body) = get(router.clone(), &format!("/verify?id={}", staging_id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["environment"], "staging");
+        assert_eq!(body["data"]["environment_mismatch"], true);
+
+        let (_, body) = get(router.clone(), "/id").await;
+        let prod_id = body["data"]["id"].as_u64().unwrap();
+        let (_, body) = get(router, &format!("/verify?id={}", prod_id)).await;
+        assert_eq!(body["data"]["environment"], "prod");
+        assert_eq!(body["data"]["environment_mismatch"], false);
+    }
+
+    #[tokio::test]
+    async fn test_verify_rejects_version_mismatch() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.id_generator.layout = Some("39-3-7-12".parse().unwrap());
+        cfg.id_generator.version_bits = 2;
+        cfg.id_generator.version = 1;
+        let old_id = IDGenerator::new(cfg.id_generator.clone())
+            .unwrap()
+            .next_id()
+            .unwrap();
+
+        cfg.id_generator.version = 2;
+        let router = test_server(cfg).create_router();
+
+        let (status, body) = get(router.clone(), &format!("/verify?id={}", old_id)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["msg"], "id format version 1 is not supported");
+        let (status, _) = get(router.clone(), &format!("/decode/{}", old_id)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (_, body) = get(router.clone(), "/id").await;
+        let new_id = body["data"]["id"].as_u64().unwrap();
+        let (status, body) = get(router, &format!("/decode/{}", new_id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["version"], 2);
+        assert_eq!(body["data"]["version_mismatch"], false);
+    }
+
+    #[tokio::test]
+    async fn test_health_ready() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+        let (status, body) = get(router, "/health/ready").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["code"], 0);
+    }
+
+    /// 固定返回某一时刻的时钟
+    #[derive(Debug)]
+    struct StuckClock(u64);
+
+    impl crate::core::Clock for StuckClock {
+        fn now_millis(&self) -> Result<u64, crate::error::TinyIdError> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expired_lease_rejects_generation() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.admin.token = Some("secret".into());
+        let lease_cfg = shared::config::LeaseConfig {
+            ttl_ms: 20,
+            renew_interval_ms: 10,
+            ..Default::default()
+        };
+        // 不启动续期任务，模拟协调器一直不可达
+        let lease = WorkerLease::acquire(&StaticCoordinator::new(0), &lease_cfg)
+            .await
+            .unwrap();
+        let lease = Arc::new(lease);
+        let generator = Arc::new(IDGenerator::new(cfg.id_generator.clone()).unwrap());
+        let user_client = new_user_client(cfg.user_rpc.clone()).unwrap();
+        let repo = Arc::new(
+            HelloWorldRepoImpl::new(generator, user_client)
+                .unwrap()
+                .with_lease(Arc::clone(&lease)),
+        );
+        let server = HttpServer::new(
+            Arc::new(cfg),
+            Arc::new(HelloWorldUseCase::new(repo.clone())),
+            Arc::new(UserDemoUseCase::new(repo)),
+        );
+        let router = server.create_router();
+
+        // 租约有效期内正常发号
+        let (_, body) = get(router.clone(), "/id").await;
+        assert!(body["data"]["id"].is_u64());
+        // 租到的 worker id 不能手动切换
+        let (status, _) = post_worker_id(router.clone(), r#"{"worker_id": 5}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let (_, body) = get(router.clone(), "/id").await;
+        assert_eq!(body["code"], 503);
+        let (status, _) = get(router.clone(), "/ids?count=2").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        let (status, _) = get(router, "/health/ready").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_health_ready_fails_while_clock_is_behind() {
+        let cfg = ServerConfig::default_for_test();
+        let generator = IDGenerator::new(cfg.id_generator.clone()).unwrap();
+        generator.next_id().unwrap();
+        // 时钟停在上一个ID之前，生成器一直等待回拨恢复
+        let generator = generator.with_clock(Arc::new(StuckClock(cfg.id_generator.epoch + 1_000)));
+        let user_client = new_user_client(cfg.user_rpc.clone()).unwrap();
+        let repo = Arc::new(HelloWorldRepoImpl::new(Arc::new(generator), user_client).unwrap());
+        let server = HttpServer::new(
+            Arc::new(cfg),
+            Arc::new(HelloWorldUseCase::new(repo.clone())),
+            Arc::new(UserDemoUseCase::new(repo)),
+        );
+
+        let (status, body) = get(server.create_router(), "/health/ready").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["code"], 503);
+
+        // 存活探针不受影响
+        let (status, _) = get(server.create_router(), "/health").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_request_metrics_follow_status() {
+        let metrics = Arc::new(shared::metric::AppMetrics::default());
+        let mut server = test_server(ServerConfig::default_for_test());
+        server.metrics = Some(Arc::clone(&metrics));
+        let router = server.create_router();
+
+        for uri in ["/ping", "/health", "/no-such-route"] {
+            get(router.clone(), uri).await;
+        }
+
+        let load = |counter: &std::sync::atomic::AtomicU64| {
+            counter.load(std::sync::atomic::Ordering::Relaxed)
+        };
+        assert_eq!(load(&metrics.total_requests), 3);
+        assert_eq!(load(&metrics.successful_requests), 2);
+        assert_eq!(load(&metrics.failed_requests), 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_metrics_by_route() {
+        let metrics = Arc::new(shared::metric::AppMetrics::default());
+        let mut server = test_server(ServerConfig::default_for_test());
+        server.metrics = Some(Arc::clone(&metrics));
+        let router = server.create_router();
+
+        for uri in [
+            "/id",
+            "/id",
+            "/health",
+            "/decode/abc",
+            "/decode/xyz",
+            "/no-such-route",
+        ] {
+            get(router.clone(), uri).await;
+        }
+
+        let text = metrics.render();
+        for line in [
+            "tinyid_requests_by_route_total{route=\"/id\",status=\"200\"} 2",
+            "tinyid_requests_by_route_total{route=\"/health\",status=\"200\"} 1",
+            // 带参数的路由按模板聚合
+            "tinyid_requests_by_route_total{route=\"/decode/{id}\",status=\"400\"} 2",
+            "tinyid_requests_by_route_total{route=\"unmatched\",status=\"404\"} 1",
+        ] {
+            assert!(text.contains(line), "missing {}\n{}", line, text);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_minimal() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.health.format = HealthFormat::Minimal;
+        let router = test_server(cfg).create_router();
+
+        let (status, body) = get(router, "/health").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.is_null());
+    }
+
+    /// 只认识 id=1 的用户服务桩
+    struct StubUserDemo;
+
+    #[tonic::async_trait]
+    impl UserDemo for StubUserDemo {
+        async fn get_user(
+            &self,
+            request: tonic::Request<GetUserRequest>,
+        ) -> Result<tonic::Response<GetUserResponse>, tonic::Status> {
+            let id = request.into_inner().id;
+            if id != 1 {
+                return Err(tonic::Status::not_found(format!("User not found: {}", id)));
+            }
+            Ok(tonic::Response::new(GetUserResponse {
+                user: Some(User {
+                    id,
+                    name: "Alice".to_string(),
+                    ..Default::default()
+                }),
+            }))
+        }
+
+        async fn list_users(
+            &self,
+            _request: tonic::Request<ListUsersRequest>,
+        ) -> Result<tonic::Response<ListUsersResponse>, tonic::Status> {
+            Err(tonic::Status::unimplemented("list_users"))
+        }
+    }
+
+    /// 启动用户服务桩，返回指向它的配置
+    async fn config_with_stub_user_service() -> ServerConfig {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(UserDemoServer::new(StubUserDemo))
+                .serve_with_incoming(tonic::transport::server::TcpIncoming::from(listener)),
+        );
+
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.user_rpc.rpc_cfg.addr = vec![format!("http://{}", addr)];
+        cfg
+    }
+
+    #[tokio::test]
+    async fn test_user_route() {
+        let router = test_server(config_with_stub_user_service().await).create_router();
+
+        let (status, body) = get(router.clone(), "/user?id=1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["name"], "Alice");
+
+        let (status, body) = get(router, "/user?id=999").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], 404);
+        assert_eq!(body["msg"], "user 999 not found");
+    }
+
+    #[tokio::test]
+    async fn test_batch_ids_route() {
+        let router = test_server(ServerConfig::default_for_test()).create_router();
+
+        let (status, body) = get(router, "/ids?count=5").await;
+        assert_eq!(status, StatusCode::OK);
+        let ids = body["data"]["ids"].as_array().unwrap();
+        assert_eq!(ids.len(), 5);
+        let unique: std::collections::HashSet<_> = ids.iter().map(|v| v.as_u64()).collect();
+        assert_eq!(unique.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_padded_encoding() {
+        let server = test_server(ServerConfig::default_for_test());
+
+        let (status, body) = get(server.create_router(), "/id?encoding=padded").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["id"].as_str().unwrap().len(), 20);
+
+        let (_, body) = get(server.create_router(), "/ids?count=3&encoding=padded").await;
+        let ids = body["data"]["ids"].as_array().unwrap();
+        assert!(ids.iter().all(|id| id.as_str().unwrap().len() == 20));
+
+        let (_, body) = get(server.create_router(), "/id").await;
+        assert!(body["data"]["id"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_id_prefix() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.id_prefix = Some("ord_".parse().unwrap());
+        let router = test_server(cfg).create_router();
+
+        let (_, body) = get(router.clone(), "/id").await;
+        let prefixed = body["data"]["id"].as_str().unwrap().to_string();
+        let id: u64 = prefixed.strip_prefix("ord_").unwrap().parse().unwrap();
+
+        let (_, body) = get(router.clone(), "/ids?count=2&encoding=padded").await;
+        let ids = body["data"]["ids"].as_array().unwrap();
+        assert!(
+            ids.iter()
+                .all(|id| id.as_str().unwrap().starts_with("ord_")
+                    && id.as_str().unwrap().len() == 24)
+        );
+
+        let (status, body) = get(router.clone(), &format!("/decode/{}", prefixed)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["id"], id);
+        let (status, body) = get(router.clone(), &format!("/verify?id={}", prefixed)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"]["id"], id);
+
+        // 缺少或前缀不符的ID被拒绝
+        let (status, body) = get(router.clone(), &format!("/decode/{}", id)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["msg"].as_str().unwrap().contains("prefix"));
+        let (status, _) = get(router, &format!("/decode/usr_{}", id)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_server_timing() {
+        let (_, body) = get(
+            test_server(ServerConfig::default_for_test()).create_router(),
+            "/id",
+        )
+        .await;
+        assert!(body.get("server_time_ms").is_none());
+
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.server_timing = true;
+        let router = test_server(cfg).create_router();
+
+        let (_, body) = get(router.clone(), "/id").await;
+        assert!(body["server_time_ms"].as_f64().unwrap() >= 0.0);
+        let (_, body) = get(router.clone(), "/ids?count=2").await;
+        assert!(body["server_time_ms"].is_f64());
+
+        // 失败响应不带耗时
+        let (status, body) = get(router, "/ids?count=0").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.get("server_time_ms").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_batch_ids_exceeds_max_batch_size() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.batch.max_batch_size = 3;
+        let router = test_server(cfg).create_router();
+
+        let (status, body) = get(router, "/ids?count=4").await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(body["code"], 413);
+        // 与 gRPC 使用同一条错误信息
+        assert_eq!(body["msg"], "Batch too large: requested 4, max 3");
+    }
+
+    #[tokio::test]
+    async fn test_batch_size_follows_runtime_config() {
+        let server = test_server(ServerConfig::default_for_test());
+
+        let (status, _) = get(server.create_router(), "/ids?count=10").await;
+        assert_eq!(status, StatusCode::OK);
+
+        server
+            .runtime
+            .store(shared::config::RuntimeConfig { max_batch_size: 5 });
+        let (status, _) = get(server.create_router(), "/ids?count=10").await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_batch_ids_concurrency_limit_sheds_load() {
+        let mut cfg = ServerConfig::default_for_test();
+        cfg.batch.max_concurrent_ops = 0;
+        let router = test_server(cfg).create_router();
+
+        let (status, body) = get(router.clone(), "/ids?count=1").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["code"], 503);
+
+        // 普通接口不受批量并发限制影响
+        let (status, _) = get(router, "/id").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
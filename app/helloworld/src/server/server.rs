@@ -6,19 +6,77 @@
  * @Descriptiono
  * this server is used to how http server run
 */
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use shared::{config::ServerConfig, metric};
-use tracing::info;
+use axum::http::{header::RETRY_AFTER, HeaderValue};
+use axum::response::IntoResponse;
+use axum::serve::ListenerExt;
+use shared::{
+    config::{MaintenanceConfig, RuntimeConfig, RuntimeConfigHandle, ServerConfig},
+    metric,
+    traces::TelemetryFlusher,
+};
+use tokio::net::{TcpListener, TcpSocket};
+use tracing::{info, warn};
 
 use crate::biz::{HelloWorldUseCase, UserDemoUseCase};
 use crate::data::HelloWorldRepoImpl;
+use crate::service::response::{ErrCode, Response};
 use crate::{error::TinyIdError, service::HelloWorldServiceImpl, Result};
 
 pub struct HttpServer {
     pub cfg: Arc<ServerConfig>,
     pub hello_world_service: Arc<HelloWorldServiceImpl>,
     pub metrics: Option<Arc<metric::AppMetrics>>,
+    /// 可热更新的运行时配置，SIGHUP 时由启动程序替换
+    pub runtime: RuntimeConfigHandle,
+    /// 供 /admin/flush 使用的 tracing flush 句柄
+    pub telemetry: TelemetryFlusher,
+    /// 维护模式开关，由 /admin/maintenance 切换
+    pub maintenance: MaintenanceMode,
+}
+
+/// 维护模式：开启后生成ID的接口返回 503 并带上 `Retry-After`，`/health` 同步返回 503，
+/// 便于在高风险操作前把节点从负载均衡中摘除而不必停掉进程
+#[derive(Debug, Clone)]
+pub struct MaintenanceMode {
+    enabled: Arc<AtomicBool>,
+    retry_after_secs: u64,
+}
+
+impl MaintenanceMode {
+    pub fn new(cfg: &MaintenanceConfig) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(cfg.enabled)),
+            retry_after_secs: cfg.retry_after_secs,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    /// 切换维护模式，返回切换前的状态
+    pub fn set(&self, enabled: bool) -> bool {
+        self.enabled.swap(enabled, Ordering::AcqRel)
+    }
+
+    /// 维护模式下返回 503 响应，否则返回 None
+    pub fn reject(&self) -> Option<axum::response::Response> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let mut response = Response::<()>::failed(
+            ErrCode::ServiceUnavailable,
+            Some("id generation is disabled for maintenance"),
+        )
+        .into_response();
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(self.retry_after_secs));
+        Some(response)
+    }
 }
 
 impl HttpServer {
@@ -27,11 +85,21 @@ impl HttpServer {
         huc: Arc<HelloWorldUseCase<HelloWorldRepoImpl>>,
         uuc: Arc<UserDemoUseCase<HelloWorldRepoImpl>>,
     ) -> Self {
-        let hello_world_service = Arc::new(HelloWorldServiceImpl::new(huc, uuc));
+        let runtime = RuntimeConfigHandle::new(RuntimeConfig::from(cfg.as_ref()));
+        let maintenance = MaintenanceMode::new(&cfg.maintenance);
+        let hello_world_service = Arc::new(
+            HelloWorldServiceImpl::new(huc, uuc)
+                .with_runtime_config(runtime.clone())
+                .with_id_prefix(cfg.id_prefix.clone())
+                .with_id_kind(cfg.id_kind),
+        );
         Self {
             cfg,
             hello_world_service,
             metrics: None,
+            runtime,
+            telemetry: TelemetryFlusher::default(),
+            maintenance,
         }
     }
 
@@ -41,33 +109,94 @@ impl HttpServer {
         uuc: Arc<UserDemoUseCase<HelloWorldRepoImpl>>,
         metrics: Arc<metric::AppMetrics>,
     ) -> Self {
-        let hello_world_service = Arc::new(HelloWorldServiceImpl::new(huc, uuc));
+        let runtime = RuntimeConfigHandle::new(RuntimeConfig::from(cfg.as_ref()));
+        let maintenance = MaintenanceMode::new(&cfg.maintenance);
+        let hello_world_service = Arc::new(
+            HelloWorldServiceImpl::new(huc, uuc)
+                .with_runtime_config(runtime.clone())
+                .with_id_prefix(cfg.id_prefix.clone())
+                .with_id_kind(cfg.id_kind),
+        );
         Self {
             cfg,
             hello_world_service,
             metrics: Some(metrics),
+            runtime,
+            telemetry: TelemetryFlusher::default(),
+            maintenance,
         }
     }
 
-    pub async fn run(&self) -> Result<()> {
-        Ok(())
+    /// 设置 tracing flush 句柄
+    pub fn with_telemetry_flusher(mut self, telemetry: TelemetryFlusher) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+
+    /// 返回完整的路由（含中间件），供嵌入到已有的 axum 应用中，不绑定端口
+    ///
+    /// ```ignore
+    /// let app = Router::new().nest("/internal/ids", server.into_router());
+    /// ```
+    ///
+    /// 嵌套后各中间件看到的是去掉前缀的路径，`required_header.exempt_paths` 等配置仍按 `/health` 这样的原始路径填写。
+    pub fn into_router(self) -> axum::Router {
+        self.create_router()
+    }
+
+    /// 绑定地址并一直提供服务，直到出错；需要优雅关闭时使用 `run_with_shutdown`
+    pub async fn run(self) -> Result<()> {
+        self.run_with_shutdown(std::future::pending()).await
     }
 
     pub async fn run_with_shutdown(
         self,
         shutdown_signal: impl std::future::Future<Output = ()> + Send + 'static,
     ) -> Result<()> {
-        let listener =
-            tokio::net::TcpListener::bind(format!("{}:{}", self.cfg.addr, self.cfg.port)).await?;
-        info!("Server is running on {}", listener.local_addr().unwrap());
+        let listener = self.bind().await?;
+        info!("Server is running on {}", listener.local_addr()?);
 
         let app = self.create_router();
+        let nodelay = self.cfg.tcp.nodelay;
+        let listener = listener.tap_io(move |tcp| {
+            if nodelay {
+                if let Err(e) = tcp.set_nodelay(true) {
+                    warn!(error = %e, "failed to set TCP_NODELAY");
+                }
+            }
+        });
 
         axum::serve(listener, app)
             .with_graceful_shutdown(shutdown_signal)
             .await
-            .map_err(|e| TinyIdError::ServerError(e.to_string()))?;
+            .map_err(|e| TinyIdError::server("http server terminated unexpectedly", e))?;
 
         Ok(())
     }
+
+    /// 按 `tcp.backlog` 创建监听 socket
+    pub(crate) async fn bind(&self) -> Result<TcpListener> {
+        let addr = format!("{}:{}", self.cfg.addr, self.cfg.port);
+        let bind_err = |e| TinyIdError::server(format!("failed to bind {}", addr), e);
+        let socket_addr = tokio::net::lookup_host(&addr)
+            .await
+            .map_err(bind_err)?
+            .next()
+            .ok_or_else(|| TinyIdError::ServerError {
+                message: format!("failed to resolve {}", addr),
+                source: None,
+            })?;
+
+        let socket = if socket_addr.is_ipv4() {
+            TcpSocket::new_v4()
+        } else {
+            TcpSocket::new_v6()
+        }
+        .map_err(bind_err)?;
+        // 与 TcpListener::bind 一致，重启时可以立即复用处于 TIME_WAIT 的端口
+        #[cfg(unix)]
+        socket.set_reuseaddr(true).map_err(bind_err)?;
+        socket.bind(socket_addr).map_err(bind_err)?;
+        socket.listen(self.cfg.tcp.backlog).map_err(bind_err)
+    }
 }
@@ -9,7 +9,8 @@ use axum::{
     http::StatusCode,
     response::{IntoResponse, Json, Response as AxumResponse},
 };
-use serde_json::json;
+use email_address::EmailAddress;
+use shared::config::PaginationConfig;
 use std::collections::HashMap;
 use thiserror::Error;
 use tracing::error;
@@ -102,14 +103,6 @@ impl ApiError {
 impl IntoResponse for ApiError {
     fn into_response(self) -> AxumResponse {
         let (status_code, err_code, message) = match self {
-            ApiError::Database(ref e) => {
-                error!("Database error: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    ErrCode::DatabaseError,
-                    "数据库操作失败".to_string(),
-                )
-            }
             ApiError::Validation { ref message } => (
                 StatusCode::BAD_REQUEST,
                 ErrCode::ValidationError,
@@ -248,11 +241,12 @@ pub type ApiResult<T> = Result<T, ApiError>;
 pub struct Validator;
 
 impl Validator {
+    /// 按 RFC 5322 校验邮箱格式，允许不带点的内网域名（如 `admin@intranet`）
     pub fn validate_email(email: &str) -> ApiResult<()> {
         if email.is_empty() {
             return Err(ApiError::validation("邮箱不能为空"));
         }
-        if !email.contains('@') || !email.contains('.') {
+        if !EmailAddress::is_valid(email) {
             return Err(ApiError::validation("邮箱格式不正确"));
         }
         Ok(())
@@ -294,9 +288,14 @@ impl Validator {
         Ok(())
     }
 
-    pub fn validate_page_params(page: Option<u32>, size: Option<u32>) -> ApiResult<(u32, u32)> {
+    /// 校验分页参数，默认值和上限取自 `limits`，返回 (page, size)
+    pub fn validate_page_params(
+        page: Option<u32>,
+        size: Option<u32>,
+        limits: &PaginationConfig,
+    ) -> ApiResult<(u32, u32)> {
         let page = page.unwrap_or(1);
-        let size = size.unwrap_or(10);
+        let size = size.unwrap_or(limits.default_page_size);
 
         if page == 0 {
             return Err(ApiError::validation("页码必须大于0"));
@@ -304,8 +303,11 @@ impl Validator {
         if size == 0 {
             return Err(ApiError::validation("每页大小必须大于0"));
         }
-        if size > 100 {
-            return Err(ApiError::validation("每页大小不能超过100"));
+        if size > limits.max_page_size {
+            return Err(ApiError::validation(format!(
+                "每页大小不能超过{}",
+                limits.max_page_size
+            )));
         }
 
         Ok((page, size))
@@ -316,7 +318,7 @@ impl Validator {
 // 6. 批量验证
 // ====================================
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ValidationErrors {
     pub errors: HashMap<String, Vec<String>>,
 }
@@ -331,10 +333,7 @@ impl ValidationErrors {
     pub fn add_error(&mut self, field: impl Into<String>, message: impl Into<String>) {
         let field = field.into();
         let message = message.into();
-        self.errors
-            .entry(field)
-            .or_insert_with(Vec::new)
-            .push(message);
+        self.errors.entry(field).or_default().push(message);
     }
 
     pub fn has_errors(&self) -> bool {
@@ -350,8 +349,8 @@ impl ValidationErrors {
 
 impl IntoResponse for ValidationErrors {
     fn into_response(self) -> AxumResponse {
-        let response =
-            Response::failed(ErrCode::ValidationError, Some("数据验证失败")).set_data(self.errors);
+        let response = Response::<()>::failed(ErrCode::ValidationError, Some("数据验证失败"))
+            .with_detail(self.errors);
         (StatusCode::BAD_REQUEST, Json(response)).into_response()
     }
 }
@@ -451,6 +450,40 @@ mod tests {
         assert!(Validator::validate_email("@example.com").is_err());
     }
 
+    #[test]
+    fn test_validator_email_real_world() {
+        let valid = [
+            "first.last@example.com",
+            "user+tag@sub.example.co.uk",
+            "o'brien@example.com",
+            "user_name@example-host.org",
+            "UPPER@EXAMPLE.COM",
+            "12345@example.com",
+            // 内网地址没有顶级域名
+            "admin@intranet",
+            "a@b",
+        ];
+        for email in valid {
+            assert!(Validator::validate_email(email).is_ok(), "{email}");
+        }
+
+        let invalid = [
+            "user@.",
+            "user@example..com",
+            "user@@example.com",
+            "us er@example.com",
+            ".user@example.com",
+            "user.@example.com",
+            "user..name@example.com",
+            "user@exa mple.com",
+            "user@",
+            "user.example.com",
+        ];
+        for email in invalid {
+            assert!(Validator::validate_email(email).is_err(), "{email}");
+        }
+    }
+
     #[test]
     fn test_validator_username() {
         assert!(Validator::validate_username("valid_user").is_ok());
@@ -471,17 +504,36 @@ mod tests {
 
     #[test]
     fn test_validator_page_params() {
+        let limits = PaginationConfig::default();
         assert_eq!(
-            Validator::validate_page_params(Some(1), Some(10)).unwrap(),
+            Validator::validate_page_params(Some(1), Some(10), &limits).unwrap(),
             (1, 10)
         );
         assert_eq!(
-            Validator::validate_page_params(None, None).unwrap(),
+            Validator::validate_page_params(None, None, &limits).unwrap(),
             (1, 10)
         );
-        assert!(Validator::validate_page_params(Some(0), Some(10)).is_err());
-        assert!(Validator::validate_page_params(Some(1), Some(0)).is_err());
-        assert!(Validator::validate_page_params(Some(1), Some(101)).is_err());
+        assert!(Validator::validate_page_params(Some(0), Some(10), &limits).is_err());
+        assert!(Validator::validate_page_params(Some(1), Some(0), &limits).is_err());
+        assert!(Validator::validate_page_params(Some(1), Some(101), &limits).is_err());
+    }
+
+    #[test]
+    fn test_validator_page_params_custom_limits() {
+        let limits = PaginationConfig {
+            default_page_size: 50,
+            max_page_size: 500,
+        };
+        assert_eq!(
+            Validator::validate_page_params(None, None, &limits).unwrap(),
+            (1, 50)
+        );
+        assert_eq!(
+            Validator::validate_page_params(Some(2), Some(500), &limits).unwrap(),
+            (2, 500)
+        );
+        let err = Validator::validate_page_params(Some(1), Some(501), &limits).unwrap_err();
+        assert!(err.to_string().contains("500"));
     }
 
     #[test]
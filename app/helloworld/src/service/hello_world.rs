@@ -1,26 +1,134 @@
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use axum::extract::Query;
+use axum::extract::{Path, Query};
 use axum::response::Json;
+use futures::Stream;
 use serde::{Deserialize, Serialize};
 use shared::proto::id_generator::id_generator_service_server::IdGeneratorService;
-use shared::proto::id_generator::{GenerateIdRequest, GenerateIdResponse};
+use shared::proto::id_generator::{
+    GenerateIdRequest, GenerateIdResponse, GenerateIdsRequest, GenerateIdsResponse,
+    StreamIdsRequest,
+};
+use tokio::sync::Semaphore;
 use tonic::{Request, Response as TResponse, Status};
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+use shared::config::{IdKind, IdPrefix, RuntimeConfigHandle};
 
 use super::response::{ErrCode, Response};
-use crate::biz::{HelloWorldRepo, HelloWorldUseCase, UserDemoRepo, UserDemoUseCase};
+use crate::biz::{ErrorSample, HelloWorldRepo, HelloWorldUseCase, UserDemoRepo, UserDemoUseCase};
+use crate::core::{DecodedId, GeneratorStats, LayoutInfo};
 use crate::data::HelloWorldRepoImpl;
+use crate::error::TinyIdError;
+use crate::generator::encoding::{strip_prefix, EncodedId, IdEncoding};
+use crate::generator::{UlidGenerator, UuidV7Generator};
 
 // 为实际使用创建类型别名
 pub type HelloWorldServiceImpl = HelloWorldService<HelloWorldRepoImpl, HelloWorldRepoImpl>;
 
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct GenIdReq {
+    /// ID 编码格式，也可以写作 `?format=`（如 `format=base62`）
+    #[serde(default, alias = "format")]
+    pub encoding: IdEncoding,
+    /// 使用 `namespaces` 中配置的生成器，不填时使用默认生成器
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GenIdResp {
     // id
+    pub id: EncodedId,
+}
+
+/// 不带 `{code,msg,data}` 外层的ID响应，供内部高频调用方使用
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RawIdResp {
     pub id: u64,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct GenIdsReq {
+    /// 需要生成的ID数量
+    pub count: usize,
+    /// ID 编码格式
+    #[serde(default)]
+    pub encoding: IdEncoding,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GenIdsResp {
+    pub ids: Vec<EncodedId>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RotateWorkerIdReq {
+    pub worker_id: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RotateWorkerIdResp {
+    pub previous_worker_id: u32,
+    pub worker_id: u32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct VerifyIdReq {
+    /// 十进制ID，配置了 `id_prefix` 时需带前缀
+    pub id: String,
+    /// 生成该ID的命名空间，按其 epoch 和位布局拆解
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct DecodeIdReq {
+    /// 生成该ID的命名空间，按其 epoch 和位布局拆解
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CompareIdsReq {
+    /// 十进制ID，配置了 `id_prefix` 时需带前缀
+    pub a: String,
+    pub b: String,
+}
+
+/// 两个ID中较早生成的一方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OlderId {
+    A,
+    B,
+    /// 同一毫秒且来自不同节点（或是同一个ID），无法区分先后
+    Neither,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompareIdsResp {
+    pub a: DecodedId,
+    pub b: DecodedId,
+    pub older: OlderId,
+    /// b 的时间戳减去 a 的时间戳（毫秒），b 更早时为负
+    pub delta_ms: i64,
+    /// 数据中心和工作节点都相同
+    pub same_worker: bool,
+    pub same_datacenter: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MintIdReq {
+    /// 补录的时刻（Unix 毫秒）
+    pub from_timestamp: u64,
+    /// 保留段内的序列号偏移
+    #[serde(default)]
+    pub sequence: u32,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[allow(dead_code)]
 pub struct GetUserReq {
@@ -45,18 +153,113 @@ where
 {
     huc: Arc<HelloWorldUseCase<R>>,
     uuc: Arc<UserDemoUseCase<U>>,
+    runtime: RuntimeConfigHandle,
+    grpc_default_deadline: Option<Duration>,
+    id_prefix: Option<IdPrefix>,
+    /// `/id` 返回雪花ID还是 ULID
+    id_kind: IdKind,
+    ulid: Arc<UlidGenerator>,
+    uuid_v7: Arc<UuidV7Generator>,
+    /// StreamIds 的并发流配额，每个打开的流持有一个 permit 直到结束
+    stream_permits: Arc<Semaphore>,
+    max_concurrent_streams: usize,
 }
 
 impl<R: HelloWorldRepo, U: UserDemoRepo> HelloWorldService<R, U> {
     pub fn new(huc: Arc<HelloWorldUseCase<R>>, uuc: Arc<UserDemoUseCase<U>>) -> Self {
-        Self { huc, uuc }
+        Self {
+            huc,
+            uuc,
+            runtime: RuntimeConfigHandle::default(),
+            grpc_default_deadline: None,
+            id_prefix: None,
+            id_kind: IdKind::default(),
+            ulid: Arc::new(UlidGenerator::new()),
+            uuid_v7: Arc::new(UuidV7Generator::new()),
+            stream_permits: Arc::new(Semaphore::new(Semaphore::MAX_PERMITS)),
+            max_concurrent_streams: Semaphore::MAX_PERMITS,
+        }
+    }
+
+    /// 使用共享的运行时配置（批量上限等可热更新参数）
+    pub fn with_runtime_config(mut self, runtime: RuntimeConfigHandle) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
+    /// gRPC 请求未携带 grpc-timeout 时使用的默认截止时间
+    pub fn with_grpc_default_deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.grpc_default_deadline = deadline;
+        self
+    }
+
+    /// 限制 StreamIds 同时打开的流数量，超出后返回 RESOURCE_EXHAUSTED
+    pub fn with_max_concurrent_streams(mut self, max: usize) -> Self {
+        let max = max.min(Semaphore::MAX_PERMITS);
+        self.stream_permits = Arc::new(Semaphore::new(max));
+        self.max_concurrent_streams = max;
+        self
+    }
+
+    /// HTTP 响应中的ID加上前缀，解析ID时校验并去掉
+    pub fn with_id_prefix(mut self, prefix: Option<IdPrefix>) -> Self {
+        self.id_prefix = prefix;
+        self
+    }
+
+    /// 选择 `/id` 返回的ID类型
+    pub fn with_id_kind(mut self, id_kind: IdKind) -> Self {
+        self.id_kind = id_kind;
+        self
+    }
+
+    /// 按请求的编码格式编码ID，并加上配置的前缀
+    fn encode_id(&self, encoding: IdEncoding, id: u64) -> EncodedId {
+        self.with_prefix(encoding.encode(id))
+    }
+
+    fn with_prefix(&self, encoded: EncodedId) -> EncodedId {
+        match &self.id_prefix {
+            Some(prefix) => encoded.with_prefix(prefix.as_str()),
+            None => encoded,
+        }
+    }
+
+    /// 解析客户端传回的十进制ID，配置了前缀时先去掉前缀
+    fn parse_id(&self, encoded: &str) -> Result<u64, TinyIdError> {
+        let digits = strip_prefix(self.id_prefix.as_ref().map(IdPrefix::as_str), encoded)?;
+        IdEncoding::Decimal.decode(digits)
+    }
+
+    /// 根据 grpc-timeout 头（或默认值）计算本次请求的截止时间
+    fn grpc_deadline<T>(&self, request: &Request<T>) -> Option<Instant> {
+        request
+            .metadata()
+            .get("grpc-timeout")
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_grpc_timeout)
+            .or(self.grpc_default_deadline)
+            .map(|timeout| Instant::now() + timeout)
     }
 
     /// 生成ID并返回Response格式  
     #[tracing::instrument(skip(self), fields(operation = "generate_id"))]
-    pub async fn generate_id(&self) -> Json<Response<GenIdResp>> {
-        let id = match self.huc.generate_id().await {
+    pub async fn generate_id(&self, Query(req): Query<GenIdReq>) -> Json<Response<GenIdResp>> {
+        if self.id_kind == IdKind::Ulid {
+            return Json(self.generate_ulid().await);
+        }
+        let result = match req.namespace.as_deref() {
+            Some(namespace) => self.huc.generate_id_in(namespace, None).await,
+            None => self.huc.generate_id(None).await,
+        };
+        let id = match result {
             Ok(id) => id,
+            Err(TinyIdError::InvalidRequest(msg)) => {
+                return Json(Response::failed(ErrCode::BadRequest, Some(msg)));
+            }
+            Err(e @ TinyIdError::LeaseExpired(_)) => {
+                return Json(Response::failed(e.err_code(), Some(e.to_string())));
+            }
             Err(e) => {
                 error!("generate id failed: {}", e);
                 return Json(Response::failed(
@@ -65,12 +268,318 @@ impl<R: HelloWorldRepo, U: UserDemoRepo> HelloWorldService<R, U> {
                 ));
             }
         };
-        let data = GenIdResp { id };
+        let data = GenIdResp {
+            id: self.encode_id(req.encoding, id),
+        };
         info!("Generated ID: {}", id);
 
         Json(Response::success(Some(data)))
     }
 
+    /// 生成 ULID，与 `id_kind` 无关；配置了前缀时同样加上
+    #[tracing::instrument(skip(self), fields(operation = "generate_ulid"))]
+    pub async fn generate_ulid(&self) -> Response<GenIdResp> {
+        match self.ulid.next_ulid() {
+            Ok(ulid) => Response::success(Some(GenIdResp {
+                id: self.with_prefix(EncodedId::Text(ulid)),
+            })),
+            Err(e) => {
+                error!("generate ulid failed: {}", e);
+                Response::failed(ErrCode::InternalServerError, Some("generate ulid failed"))
+            }
+        }
+    }
+
+    /// 生成 UUIDv7，返回带连字符的字符串；配置了前缀时同样加上
+    #[tracing::instrument(skip(self), fields(operation = "generate_uuidv7"))]
+    pub async fn generate_uuidv7(&self) -> Response<GenIdResp> {
+        match self.uuid_v7.next_uuid() {
+            Ok(uuid) => Response::success(Some(GenIdResp {
+                id: self.with_prefix(EncodedId::Text(uuid.hyphenated().to_string())),
+            })),
+            Err(e) => {
+                error!("generate uuidv7 failed: {}", e);
+                Response::failed(ErrCode::InternalServerError, Some("generate uuidv7 failed"))
+            }
+        }
+    }
+
+    /// 拆解ID并标记环境不一致（启用 `environment_bits` 时），格式版本不一致时返回 400
+    #[tracing::instrument(skip(self), fields(operation = "verify_id", id = %req.id))]
+    pub async fn verify_id(&self, Query(req): Query<VerifyIdReq>) -> Response<DecodedId> {
+        self.decode(&req.id, req.namespace.as_deref())
+    }
+
+    /// 拆解ID，与 `verify_id` 相同但ID放在路径中，便于缓存
+    #[tracing::instrument(skip(self), fields(operation = "decode_id"))]
+    pub async fn decode_id(
+        &self,
+        Path(id): Path<String>,
+        Query(req): Query<DecodeIdReq>,
+    ) -> Response<DecodedId> {
+        self.decode(&id, req.namespace.as_deref())
+    }
+
+    /// 比较两个ID的先后、时间差以及是否来自同一节点
+    #[tracing::instrument(skip(self), fields(operation = "compare_ids", a = %req.a, b = %req.b))]
+    pub async fn compare_ids(&self, Query(req): Query<CompareIdsReq>) -> Response<CompareIdsResp> {
+        let epoch = self.huc.layout().epoch;
+        let mut decoded = Vec::with_capacity(2);
+        for encoded in [&req.a, &req.b] {
+            let id = match self.try_decode(encoded, None) {
+                Ok(id) => id,
+                Err(resp) => return resp,
+            };
+            // 时间戳位为 0 的ID不可能由生成器发出，多半是填错了参数
+            if id.timestamp <= epoch {
+                return Response::failed(
+                    ErrCode::BadRequest,
+                    Some(format!(
+                        "id {} decodes to timestamp {}ms, which is not after epoch {}ms",
+                        encoded, id.timestamp, epoch
+                    )),
+                );
+            }
+            decoded.push(id);
+        }
+        let (a, b) = (decoded.swap_remove(0), decoded.swap_remove(0));
+
+        let same_worker = a.datacenter_id == b.datacenter_id && a.worker_id == b.worker_id;
+        let older = match a.timestamp.cmp(&b.timestamp) {
+            std::cmp::Ordering::Less => OlderId::A,
+            std::cmp::Ordering::Greater => OlderId::B,
+            // 同一节点同一毫秒内按序列号先后分配
+            std::cmp::Ordering::Equal if same_worker && a.sequence != b.sequence => {
+                if a.sequence < b.sequence {
+                    OlderId::A
+                } else {
+                    OlderId::B
+                }
+            }
+            std::cmp::Ordering::Equal => OlderId::Neither,
+        };
+        Response::success(Some(CompareIdsResp {
+            delta_ms: b.timestamp as i64 - a.timestamp as i64,
+            same_worker,
+            same_datacenter: a.datacenter_id == b.datacenter_id,
+            older,
+            a,
+            b,
+        }))
+    }
+
+    fn decode(&self, encoded: &str, namespace: Option<&str>) -> Response<DecodedId> {
+        match self.try_decode(encoded, namespace) {
+            Ok(decoded) => Response::success(Some(decoded)),
+            Err(resp) => resp,
+        }
+    }
+
+    /// 解析并拆解ID，格式不合法、命名空间未配置或版本不一致时返回可直接响应的错误
+    fn try_decode<T: Serialize>(
+        &self,
+        encoded: &str,
+        namespace: Option<&str>,
+    ) -> Result<DecodedId, Response<T>> {
+        let to_response = |e: TinyIdError| Response::failed(e.err_code(), Some(e.to_string()));
+        let id = self.parse_id(encoded).map_err(to_response)?;
+        let decoded = match namespace {
+            Some(namespace) => self.huc.decode_id_in(namespace, id).map_err(to_response)?,
+            None => self.huc.decode_id(id),
+        };
+        // 其它格式版本的ID按当前布局解读没有意义，直接拒绝
+        if decoded.version_mismatch {
+            warn!(version = ?decoded.version, "id has an unexpected format version");
+            return Err(Response::failed(
+                ErrCode::BadRequest,
+                Some(format!(
+                    "id format version {} is not supported",
+                    decoded.version.unwrap_or_default()
+                )),
+            ));
+        }
+        if decoded.environment_mismatch {
+            warn!(environment = ?decoded.environment, "id minted in another environment");
+        }
+        Ok(decoded)
+    }
+
+    /// 为历史时刻补录ID（管理接口），返回拆解结果，其中 `backfill` 恒为 true
+    #[tracing::instrument(skip(self), fields(operation = "mint_backfill_id"))]
+    pub async fn mint_backfill_id(&self, Query(req): Query<MintIdReq>) -> Response<DecodedId> {
+        match self.huc.mint_backfill_id(req.from_timestamp, req.sequence) {
+            Ok(id) => {
+                info!(
+                    id,
+                    from_timestamp = req.from_timestamp,
+                    "backfill id minted"
+                );
+                Response::success(Some(self.huc.decode_id(id)))
+            }
+            Err(TinyIdError::InvalidRequest(msg)) => {
+                Response::failed(ErrCode::BadRequest, Some(msg))
+            }
+            Err(e @ TinyIdError::LeaseExpired(_)) => {
+                Response::failed(e.err_code(), Some(e.to_string()))
+            }
+            Err(e) => {
+                error!("mint backfill id failed: {}", e);
+                Response::failed(
+                    ErrCode::InternalServerError,
+                    Some("mint backfill id failed"),
+                )
+            }
+        }
+    }
+
+    /// 生成ID，直接返回 8 字节大端序（`application/octet-stream`），供二进制协议使用
+    #[tracing::instrument(skip(self), fields(operation = "generate_raw_id_bytes"))]
+    pub async fn generate_raw_id_bytes(&self) -> Result<Vec<u8>, Response<()>> {
+        match self.huc.generate_id(None).await {
+            Ok(id) => Ok(id.to_be_bytes().to_vec()),
+            Err(e @ TinyIdError::LeaseExpired(_)) => {
+                Err(Response::failed(e.err_code(), Some(e.to_string())))
+            }
+            Err(e) => {
+                error!("generate id failed: {}", e);
+                Err(Response::failed(
+                    ErrCode::InternalServerError,
+                    Some("generate id failed"),
+                ))
+            }
+        }
+    }
+
+    /// 运行时切换 worker id（管理接口）
+    #[tracing::instrument(skip(self), fields(operation = "rotate_worker_id"))]
+    pub async fn rotate_worker_id(
+        &self,
+        Json(req): Json<RotateWorkerIdReq>,
+    ) -> Response<RotateWorkerIdResp> {
+        // 屏障最多等待到时钟越过已用的最后一个毫秒，正常情况下不超过 1ms
+        let deadline = Instant::now() + Duration::from_secs(5);
+        match self
+            .huc
+            .rotate_worker_id(req.worker_id, Some(deadline))
+            .await
+        {
+            Ok(previous_worker_id) => {
+                info!(
+                    previous_worker_id,
+                    worker_id = req.worker_id,
+                    "worker id rotated"
+                );
+                Response::success(Some(RotateWorkerIdResp {
+                    previous_worker_id,
+                    worker_id: req.worker_id,
+                }))
+            }
+            Err(TinyIdError::InvalidWorkerId(id)) => Response::failed(
+                ErrCode::BadRequest,
+                Some(format!("invalid worker id {}", id)),
+            ),
+            Err(TinyIdError::InvalidRequest(msg)) => {
+                Response::failed(ErrCode::BadRequest, Some(msg))
+            }
+            Err(e) => {
+                error!("rotate worker id failed: {}", e);
+                Response::failed(
+                    ErrCode::InternalServerError,
+                    Some("rotate worker id failed"),
+                )
+            }
+        }
+    }
+
+    /// 生成ID，成功时直接返回 `{"id": 123}`，失败时仍返回统一的错误格式
+    #[tracing::instrument(skip(self), fields(operation = "generate_raw_id"))]
+    pub async fn generate_raw_id(&self) -> Result<Json<RawIdResp>, Response<()>> {
+        match self.huc.generate_id(None).await {
+            Ok(id) => Ok(Json(RawIdResp { id })),
+            Err(e @ TinyIdError::LeaseExpired(_)) => {
+                Err(Response::failed(e.err_code(), Some(e.to_string())))
+            }
+            Err(e) => {
+                error!("generate id failed: {}", e);
+                Err(Response::failed(
+                    ErrCode::InternalServerError,
+                    Some("generate id failed"),
+                ))
+            }
+        }
+    }
+
+    /// 就绪探针：实际生成一个ID，失败或超过 timeout（如卡在时钟回拨等待中）时返回 503
+    #[tracing::instrument(skip(self), fields(operation = "readiness"))]
+    pub async fn readiness(&self, timeout: Duration) -> Response<()> {
+        let deadline = Instant::now() + timeout;
+        let result = tokio::time::timeout(timeout, self.huc.generate_id(Some(deadline))).await;
+        match result {
+            Ok(Ok(_)) => Response::success(None),
+            Ok(Err(e)) => {
+                warn!("readiness check failed: {}", e);
+                Response::failed(ErrCode::ServiceUnavailable, Some("id generator not ready"))
+            }
+            Err(_) => {
+                warn!(?timeout, "readiness check timed out");
+                Response::failed(ErrCode::ServiceUnavailable, Some("id generator not ready"))
+            }
+        }
+    }
+
+    /// 生效的位布局，包含每秒理论容量等推导值
+    pub async fn layout(&self) -> Response<LayoutInfo> {
+        Response::success(Some(self.huc.layout()))
+    }
+
+    /// 生成器运行统计
+    pub async fn stats(&self) -> Response<GeneratorStats> {
+        Response::success(Some(self.huc.stats()))
+    }
+
+    /// 最近的错误样本（管理接口），按时间倒序
+    pub async fn recent_errors(&self) -> Response<Vec<ErrorSample>> {
+        Response::success(Some(self.huc.recent_errors()))
+    }
+
+    /// 校验批量数量不超过当前配置的上限，HTTP 和 gRPC 共用
+    fn check_batch_count(&self, count: usize) -> Result<(), TinyIdError> {
+        let max = self.runtime.load().max_batch_size;
+        if count > max {
+            return Err(TinyIdError::BatchTooLarge { count, max });
+        }
+        Ok(())
+    }
+
+    /// 批量生成ID
+    #[tracing::instrument(skip(self), fields(operation = "generate_ids", count = req.count))]
+    pub async fn generate_ids(&self, Query(req): Query<GenIdsReq>) -> Response<GenIdsResp> {
+        if req.count == 0 {
+            return Response::failed(ErrCode::BadRequest, Some("count must be greater than 0"));
+        }
+        if let Err(e) = self.check_batch_count(req.count) {
+            return Response::failed(e.err_code(), Some(e.to_string()));
+        }
+
+        match self.huc.generate_ids(req.count, None).await {
+            Ok(ids) => {
+                info!("Generated {} IDs", ids.len());
+                let ids = ids
+                    .into_iter()
+                    .map(|id| self.encode_id(req.encoding, id))
+                    .collect();
+                Response::success(Some(GenIdsResp { ids }))
+            }
+            Err(e @ TinyIdError::LeaseExpired(_)) => {
+                Response::failed(e.err_code(), Some(e.to_string()))
+            }
+            Err(e) => {
+                error!("generate ids failed: {}", e);
+                Response::failed(ErrCode::InternalServerError, Some("generate ids failed"))
+            }
+        }
+    }
+
     /// 获取用户信息
     #[tracing::instrument(
         skip(self),
@@ -79,15 +588,20 @@ impl<R: HelloWorldRepo, U: UserDemoRepo> HelloWorldService<R, U> {
             user_id = %req.id,
         )
     )]
-    pub async fn get_user(&self, Query(req): Query<GetUserReq>) -> Json<Response<GetUserResp>> {
+    pub async fn get_user(&self, Query(req): Query<GetUserReq>) -> Response<GetUserResp> {
         let user = match self.uuc.get_user(req.id).await {
             Ok(user) => user,
+            Err(TinyIdError::UserNotFound(id)) => {
+                warn!("user {} not found", id);
+                return Response::failed(ErrCode::NotFound, Some(format!("user {} not found", id)));
+            }
+            Err(e @ TinyIdError::UserServiceError { .. }) => {
+                error!("get user failed: {}", e);
+                return Response::failed(ErrCode::BadGateway, Some("user service unavailable"));
+            }
             Err(e) => {
-                error!("generate id failed: {}", e);
-                return Json(Response::failed(
-                    ErrCode::InternalServerError,
-                    Some("generate id failed"),
-                ));
+                error!("get user failed: {}", e);
+                return Response::failed(ErrCode::InternalServerError, Some("get user failed"));
             }
         };
         let data = GetUserResp {
@@ -99,25 +613,420 @@ impl<R: HelloWorldRepo, U: UserDemoRepo> HelloWorldService<R, U> {
             updated_at: user.updated_at,
         };
         info!("Get user: {:?}", data);
-        Json(Response::success(Some(data)))
+        Response::success(Some(data))
     }
 }
 
+type IdStream = Pin<Box<dyn Stream<Item = Result<GenerateIdResponse, Status>> + Send>>;
+
+/// 带上生成时间的 gRPC 响应，各字段直接从 id 中拆解，与 id 保持一致
+///
+/// `detailed` 为 false 时只填充时间戳，序列号和节点ID留空。
+fn id_response<R: HelloWorldRepo>(
+    huc: &HelloWorldUseCase<R>,
+    id: u64,
+    detailed: bool,
+) -> GenerateIdResponse {
+    let decoded = huc.decode_id(id);
+    let mut resp = GenerateIdResponse {
+        id,
+        timestamp_ms: decoded.timestamp,
+        ..Default::default()
+    };
+    if detailed {
+        resp.sequence = decoded.sequence;
+        resp.worker_id = decoded.worker_id;
+        resp.datacenter_id = decoded.datacenter_id;
+    }
+    resp
+}
+
 #[tonic::async_trait]
 impl IdGeneratorService for HelloWorldService<HelloWorldRepoImpl, HelloWorldRepoImpl> {
+    type StreamIdsStream = IdStream;
+
     /// gRPC生成ID接口
     #[tracing::instrument(skip(self), fields(operation = "grpc_generate_id", protocol = "grpc"))]
     async fn generate_id(
         &self,
-        _request: Request<GenerateIdRequest>,
+        request: Request<GenerateIdRequest>,
     ) -> Result<TResponse<GenerateIdResponse>, Status> {
-        let id_resp = self.huc.generate_id().await;
+        let deadline = self.grpc_deadline(&request);
+        let detailed = request.get_ref().detailed;
+        let id_resp = self.huc.generate_id(deadline).await;
         match id_resp {
-            Ok(id) => return Ok(TResponse::new(GenerateIdResponse { id: id })),
+            Ok(id) => return Ok(TResponse::new(id_response(&self.huc, id, detailed))),
+            Err(TinyIdError::DeadlineExceeded) => {
+                warn!("generate id deadline exceeded");
+                return Err(Status::deadline_exceeded("generate id deadline exceeded"));
+            }
+            Err(e @ TinyIdError::LeaseExpired(_)) => {
+                return Err(Status::unavailable(e.to_string()))
+            }
             Err(e) => {
                 error!("generate id failed: {}", e);
                 return Err(Status::internal("generate id failed"));
             }
         }
     }
+
+    /// gRPC批量生成ID接口
+    #[tracing::instrument(
+        skip(self, request),
+        fields(operation = "grpc_generate_ids", protocol = "grpc", count)
+    )]
+    async fn generate_ids(
+        &self,
+        request: Request<GenerateIdsRequest>,
+    ) -> Result<TResponse<GenerateIdsResponse>, Status> {
+        let deadline = self.grpc_deadline(&request);
+        let count = request.into_inner().count as usize;
+        tracing::Span::current().record("count", count);
+
+        if count == 0 {
+            return Err(Status::invalid_argument("count must be greater than 0"));
+        }
+        // 在分配前拒绝超限请求；count 本身不合法，不是服务端资源不足
+        if let Err(e) = self.check_batch_count(count) {
+            return Err(Status::invalid_argument(e.to_string()));
+        }
+
+        match self.huc.generate_ids(count, deadline).await {
+            Ok(ids) => Ok(TResponse::new(GenerateIdsResponse { ids })),
+            Err(TinyIdError::DeadlineExceeded) => {
+                warn!("generate ids deadline exceeded");
+                Err(Status::deadline_exceeded("generate ids deadline exceeded"))
+            }
+            Err(e @ TinyIdError::LeaseExpired(_)) => Err(Status::unavailable(e.to_string())),
+            Err(e) => {
+                error!("generate ids failed: {}", e);
+                Err(Status::internal("generate ids failed"))
+            }
+        }
+    }
+
+    /// gRPC流式生成ID接口，同时打开的流数量受 `grpc_max_concurrent_streams` 限制
+    #[tracing::instrument(
+        skip(self, request),
+        fields(operation = "grpc_stream_ids", protocol = "grpc", count)
+    )]
+    async fn stream_ids(
+        &self,
+        request: Request<StreamIdsRequest>,
+    ) -> Result<TResponse<Self::StreamIdsStream>, Status> {
+        let deadline = self.grpc_deadline(&request);
+        let count = request.into_inner().count;
+        tracing::Span::current().record("count", count);
+
+        if count == 0 {
+            return Err(Status::invalid_argument("count must be greater than 0"));
+        }
+        // 与 GenerateIds 共用批量上限，避免一个流占住 permit 无限发号
+        if let Err(e) = self.check_batch_count(count as usize) {
+            return Err(Status::invalid_argument(e.to_string()));
+        }
+        // permit 随流一起释放（正常结束或客户端断开）
+        let permit = self
+            .stream_permits
+            .clone()
+            .try_acquire_owned()
+            .map_err(|_| {
+                warn!(
+                    max = self.max_concurrent_streams,
+                    "too many concurrent id streams"
+                );
+                Status::resource_exhausted(format!(
+                    "too many concurrent streams, max {}",
+                    self.max_concurrent_streams
+                ))
+            })?;
+
+        let huc = self.huc.clone();
+        let stream = futures::stream::unfold(
+            (huc, count, permit),
+            move |(huc, remaining, permit)| async move {
+                if remaining == 0 {
+                    return None;
+                }
+                let item = match huc.generate_id(deadline).await {
+                    Ok(id) => Ok(id_response(&huc, id, false)),
+                    Err(TinyIdError::DeadlineExceeded) => {
+                        warn!("stream ids deadline exceeded");
+                        Err(Status::deadline_exceeded("stream ids deadline exceeded"))
+                    }
+                    Err(e @ TinyIdError::LeaseExpired(_)) => {
+                        Err(Status::unavailable(e.to_string()))
+                    }
+                    Err(e) => {
+                        error!("stream ids failed: {}", e);
+                        Err(Status::internal("stream ids failed"))
+                    }
+                };
+                // 出错后不再继续生成
+                let remaining = if item.is_ok() { remaining - 1 } else { 0 };
+                Some((item, (huc, remaining, permit)))
+            },
+        );
+        Ok(TResponse::new(Box::pin(stream)))
+    }
+}
+
+/// 解析 gRPC 协议的 grpc-timeout 头，格式为最多 8 位数字加单位（H/M/S/m/u/n）
+fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+    let (digits, unit) = value.split_at(value.len() - 1);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n: u64 = digits.parse().ok()?;
+    let timeout = match unit {
+        "H" => Duration::from_secs(n * 3600),
+        "M" => Duration::from_secs(n * 60),
+        "S" => Duration::from_secs(n),
+        "m" => Duration::from_millis(n),
+        "u" => Duration::from_micros(n),
+        "n" => Duration::from_nanos(n),
+        _ => return None,
+    };
+    Some(timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use shared::config::{RuntimeConfig, ServerConfig};
+    use tonic::Code;
+
+    use super::*;
+    use crate::core::IDGenerator;
+    use crate::data::new_user_client;
+
+    fn test_service(max_batch_size: usize) -> HelloWorldServiceImpl {
+        let cfg = ServerConfig::default_for_test();
+        let generator = Arc::new(IDGenerator::new(cfg.id_generator.clone()).unwrap());
+        let user_client = new_user_client(cfg.user_rpc.clone()).unwrap();
+        let repo = Arc::new(HelloWorldRepoImpl::new(generator, user_client).unwrap());
+        HelloWorldService::new(
+            Arc::new(HelloWorldUseCase::new(repo.clone())),
+            Arc::new(UserDemoUseCase::new(repo)),
+        )
+        .with_runtime_config(RuntimeConfigHandle::new(RuntimeConfig { max_batch_size }))
+    }
+
+    #[tokio::test]
+    async fn test_grpc_generate_id_with_timestamp() {
+        let service = test_service(10);
+        let before = chrono::Utc::now().timestamp_millis() as u64;
+
+        let resp =
+            IdGeneratorService::generate_id(&service, Request::new(GenerateIdRequest::default()))
+                .await
+                .unwrap()
+                .into_inner();
+        let after = chrono::Utc::now().timestamp_millis() as u64;
+        assert!((before..=after).contains(&resp.timestamp_ms));
+        assert_eq!(resp.timestamp_ms, service.huc.decode_id(resp.id).timestamp);
+        assert_eq!(
+            (resp.sequence, resp.worker_id, resp.datacenter_id),
+            (0, 0, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_grpc_generate_id_detailed() {
+        let service = test_service(10);
+
+        let resp = IdGeneratorService::generate_id(
+            &service,
+            Request::new(GenerateIdRequest { detailed: true }),
+        )
+        .await
+        .unwrap()
+        .into_inner();
+        let decoded = service.huc.decode_id(resp.id);
+        assert_eq!(resp.timestamp_ms, decoded.timestamp);
+        assert_eq!(resp.sequence, decoded.sequence);
+        assert_eq!(resp.worker_id, decoded.worker_id);
+        assert_eq!(resp.datacenter_id, decoded.datacenter_id);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_generate_ids() {
+        let service = test_service(10);
+
+        let resp = IdGeneratorService::generate_ids(
+            &service,
+            Request::new(GenerateIdsRequest { count: 10 }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(resp.into_inner().ids.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_generated_ids_metric_counts_each_id() {
+        let cfg = ServerConfig::default_for_test();
+        let generator = Arc::new(IDGenerator::new(cfg.id_generator.clone()).unwrap());
+        let user_client = new_user_client(cfg.user_rpc.clone()).unwrap();
+        let repo = Arc::new(HelloWorldRepoImpl::new(generator, user_client).unwrap());
+        let metrics = Arc::new(shared::metric::AppMetrics::default());
+        let service = HelloWorldService::new(
+            Arc::new(HelloWorldUseCase::new(repo.clone()).with_metrics(metrics.clone())),
+            Arc::new(UserDemoUseCase::new(repo)),
+        );
+
+        for _ in 0..5 {
+            IdGeneratorService::generate_id(&service, Request::new(GenerateIdRequest::default()))
+                .await
+                .unwrap();
+        }
+        IdGeneratorService::generate_ids(&service, Request::new(GenerateIdsRequest { count: 10 }))
+            .await
+            .unwrap();
+
+        let load = |counter: &std::sync::atomic::AtomicU64| {
+            counter.load(std::sync::atomic::Ordering::Relaxed)
+        };
+        assert_eq!(load(&metrics.generated_ids), 15);
+        assert_eq!(load(&metrics.failed_requests), 0);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_generate_ids_over_tonic_client() {
+        use shared::proto::id_generator::id_generator_service_client::IdGeneratorServiceClient;
+        use shared::proto::id_generator::id_generator_service_server::IdGeneratorServiceServer;
+        use tonic::transport::{Endpoint, Server};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(IdGeneratorServiceServer::new(test_service(10_000)))
+                .serve_with_incoming(tonic::transport::server::TcpIncoming::from(listener)),
+        );
+        let channel = Endpoint::from_shared(format!("http://{}", addr))
+            .unwrap()
+            .connect_lazy();
+        let mut client = IdGeneratorServiceClient::new(channel);
+
+        let ids = client
+            .generate_ids(GenerateIdsRequest { count: 5000 })
+            .await
+            .unwrap()
+            .into_inner()
+            .ids;
+        assert_eq!(ids.len(), 5000);
+        assert_eq!(
+            ids.iter().collect::<std::collections::HashSet<_>>().len(),
+            5000
+        );
+
+        let status = client
+            .generate_ids(GenerateIdsRequest { count: 10_001 })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_generate_ids_oversized_count() {
+        let service = test_service(10);
+
+        let status = IdGeneratorService::generate_ids(
+            &service,
+            Request::new(GenerateIdsRequest { count: u32::MAX }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().contains("max 10"));
+
+        let status = IdGeneratorService::generate_ids(
+            &service,
+            Request::new(GenerateIdsRequest { count: 0 }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_stream_ids() {
+        let service = test_service(10);
+
+        let resp =
+            IdGeneratorService::stream_ids(&service, Request::new(StreamIdsRequest { count: 5 }))
+                .await
+                .unwrap();
+        let items: Vec<GenerateIdResponse> =
+            resp.into_inner().map(|item| item.unwrap()).collect().await;
+        assert!(items
+            .iter()
+            .all(|item| item.timestamp_ms == service.huc.decode_id(item.id).timestamp));
+        let ids: Vec<u64> = items.iter().map(|item| item.id).collect();
+        assert_eq!(ids.len(), 5);
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[tokio::test]
+    async fn test_grpc_stream_ids_oversized_count() {
+        let service = test_service(10);
+
+        let status = match IdGeneratorService::stream_ids(
+            &service,
+            Request::new(StreamIdsRequest { count: u32::MAX }),
+        )
+        .await
+        {
+            Ok(_) => panic!("oversized stream should be rejected"),
+            Err(status) => status,
+        };
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().contains("max 10"));
+
+        // 刚好等于上限的请求照常返回
+        let resp =
+            IdGeneratorService::stream_ids(&service, Request::new(StreamIdsRequest { count: 10 }))
+                .await
+                .unwrap();
+        let items: Vec<_> = resp.into_inner().collect().await;
+        assert_eq!(items.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_stream_ids_concurrency_limit() {
+        let service = test_service(10).with_max_concurrent_streams(1);
+        let open = || {
+            IdGeneratorService::stream_ids(&service, Request::new(StreamIdsRequest { count: 1 }))
+        };
+
+        let first = open().await.unwrap();
+        let status = open().await.err().unwrap();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert!(status.message().contains("max 1"));
+
+        // 流结束后释放配额
+        drop(first);
+        assert!(open().await.is_ok());
+    }
+
+    #[test]
+    fn test_parse_grpc_timeout() {
+        assert_eq!(parse_grpc_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_grpc_timeout("2M"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_grpc_timeout("3S"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_grpc_timeout("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_grpc_timeout("40u"), Some(Duration::from_micros(40)));
+        assert_eq!(
+            parse_grpc_timeout("99999999n"),
+            Some(Duration::from_nanos(99999999))
+        );
+        assert_eq!(parse_grpc_timeout(""), None);
+        assert_eq!(parse_grpc_timeout("m"), None);
+        assert_eq!(parse_grpc_timeout("10x"), None);
+        assert_eq!(parse_grpc_timeout("-1S"), None);
+        assert_eq!(parse_grpc_timeout("123456789S"), None);
+    }
 }
@@ -5,13 +5,16 @@
  */
 
 use axum::{
-    extract::{Path, Query},
-    http::{StatusCode, HeaderMap},
-    response::{Json, Response as AxumResponse, IntoResponse},
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response as AxumResponse},
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use shared::config::PaginationConfig;
+
+use super::error_handling::{ApiError, Validator};
 use super::response::{ErrCode, Response};
 
 // ====================================
@@ -87,28 +90,31 @@ where
         Self(StatusCode::CREATED, Json(Response::success(Some(data))))
     }
 
-    pub fn no_content() -> JsonResponse<()> {
-        JsonResponse(StatusCode::NO_CONTENT, Json(Response::success(None)))
+    pub fn no_content() -> Self {
+        Self(StatusCode::NO_CONTENT, Json(Response::success(None)))
     }
 
-    pub fn bad_request(message: &str) -> JsonResponse<()> {
-        JsonResponse(
+    pub fn bad_request(message: &str) -> Self {
+        Self(
             StatusCode::BAD_REQUEST,
             Json(Response::failed(ErrCode::BadRequest, Some(message))),
         )
     }
 
-    pub fn not_found(message: &str) -> JsonResponse<()> {
-        JsonResponse(
+    pub fn not_found(message: &str) -> Self {
+        Self(
             StatusCode::NOT_FOUND,
             Json(Response::failed(ErrCode::NotFound, Some(message))),
         )
     }
 
-    pub fn internal_error(message: &str) -> JsonResponse<()> {
-        JsonResponse(
+    pub fn internal_error(message: &str) -> Self {
+        Self(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(Response::failed(ErrCode::InternalServerError, Some(message))),
+            Json(Response::failed(
+                ErrCode::InternalServerError,
+                Some(message),
+            )),
         )
     }
 }
@@ -140,18 +146,17 @@ pub async fn get_user(Path(user_id): Path<u64>) -> impl IntoResponse {
 }
 
 /// 获取用户列表（分页）
-pub async fn list_users(Query(params): Query<PaginationQuery>) -> impl IntoResponse {
-    let page = params.page.unwrap_or(1);
-    let size = params.size.unwrap_or(10);
-
-    // 参数验证
-    if size > 100 {
-        return JsonResponse::bad_request("每页大小不能超过100");
-    }
-
-    if page == 0 {
-        return JsonResponse::bad_request("页码必须大于0");
-    }
+pub async fn list_users(
+    State(pagination): State<PaginationConfig>,
+    Query(params): Query<PaginationQuery>,
+) -> impl IntoResponse {
+    // 参数验证，分页限制与 Validator 共用同一份配置
+    let (page, size) = match Validator::validate_page_params(params.page, params.size, &pagination)
+    {
+        Ok(params) => params,
+        Err(ApiError::Validation { message }) => return JsonResponse::bad_request(&message),
+        Err(e) => return JsonResponse::bad_request(&e.to_string()),
+    };
 
     // 模拟数据
     let users: Vec<UserDto> = (1..=size as u64)
@@ -165,7 +170,7 @@ pub async fn list_users(Query(params): Query<PaginationQuery>) -> impl IntoRespo
         .collect();
 
     let total = 1000u64; // 模拟总数
-    let pages = (total + size as u64 - 1) / size as u64;
+    let pages = total.div_ceil(size as u64);
 
     let response = PaginatedResponse {
         items: users,
@@ -185,12 +190,8 @@ pub async fn create_user(Json(request): Json<CreateUserRequest>) -> impl IntoRes
         return JsonResponse::bad_request("用户名不能为空");
     }
 
-    if request.email.is_empty() {
-        return JsonResponse::bad_request("邮箱不能为空");
-    }
-
-    if !request.email.contains('@') {
-        return JsonResponse::bad_request("邮箱格式不正确");
+    if let Err(e) = Validator::validate_email(&request.email) {
+        return JsonResponse::bad_request(&e.to_string());
     }
 
     if request.password.len() < 6 {
@@ -224,16 +225,20 @@ pub async fn update_user(
 
     // 验证邮箱格式（如果提供）
     if let Some(ref email) = request.email {
-        if !email.contains('@') {
-            return JsonResponse::bad_request("邮箱格式不正确");
+        if let Err(e) = Validator::validate_email(email) {
+            return JsonResponse::bad_request(&e.to_string());
         }
     }
 
     // 模拟更新用户
     let user = UserDto {
         id: user_id,
-        username: request.username.unwrap_or_else(|| format!("user_{}", user_id)),
-        email: request.email.unwrap_or_else(|| format!("user_{}@example.com", user_id)),
+        username: request
+            .username
+            .unwrap_or_else(|| format!("user_{}", user_id)),
+        email: request
+            .email
+            .unwrap_or_else(|| format!("user_{}@example.com", user_id)),
         active: request.active.unwrap_or(true),
         created_at: chrono::Utc::now().to_rfc3339(),
     };
@@ -244,7 +249,7 @@ pub async fn update_user(
 /// 删除用户
 pub async fn delete_user(Path(user_id): Path<u64>) -> impl IntoResponse {
     if user_id == 0 {
-        return JsonResponse::bad_request("用户ID不能为0");
+        return JsonResponse::<()>::bad_request("用户ID不能为0");
     }
 
     if user_id == 999 {
@@ -261,7 +266,7 @@ pub async fn delete_user(Path(user_id): Path<u64>) -> impl IntoResponse {
 
 /// 模拟数据库错误
 pub async fn simulate_db_error() -> impl IntoResponse {
-    JsonResponse::internal_error("数据库连接失败")
+    JsonResponse::<()>::internal_error("数据库连接失败")
 }
 
 /// 模拟验证错误
@@ -270,12 +275,8 @@ pub async fn simulate_validation_error() -> impl IntoResponse {
     errors.insert("username", vec!["用户名已存在"]);
     errors.insert("email", vec!["邮箱格式不正确", "邮箱已被使用"]);
 
-    Json(Response::failed(
-        ErrCode::ValidationError,
-        Some("数据验证失败"),
-    ))
-    .set_data(errors)
-    .into_response()
+    Json(Response::failed(ErrCode::ValidationError, Some("数据验证失败")).set_data(errors))
+        .into_response()
 }
 
 // ====================================
@@ -424,3 +425,41 @@ pub async fn get_dashboard_stats() -> impl IntoResponse {
 
     JsonResponse::success(stats)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_list_users_reads_pagination_config() {
+        let pagination = PaginationConfig {
+            default_page_size: 20,
+            max_page_size: 200,
+        };
+        let query = |size| PaginationQuery {
+            page: Some(1),
+            size,
+            sort: None,
+        };
+
+        let response = list_users(State(pagination.clone()), Query(query(None)))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["data"]["size"], 20);
+
+        // 上限跟随配置，而不是写死的 100
+        let response = list_users(State(pagination.clone()), Query(query(Some(200))))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = list_users(State(pagination), Query(query(Some(201))))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,4 +1,6 @@
+pub mod error_handling;
 pub mod hello_world;
+pub mod json_response_examples;
 pub mod response;
 pub mod user;
 
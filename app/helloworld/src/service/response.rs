@@ -4,6 +4,9 @@
  * @Description: 通用Response结构定义，符合Rust最佳实践
  */
 
+use std::time::Duration;
+
+use axum::response::{IntoResponse, Json};
 use serde::{Deserialize, Serialize};
 
 use http::StatusCode;
@@ -74,6 +77,35 @@ pub enum ErrCode {
 }
 
 impl ErrCode {
+    /// 全部错误码，新增变体时需要同步加入（测试会逐个校验序列化往返）
+    pub const ALL: [ErrCode; 25] = [
+        ErrCode::Success,
+        ErrCode::BadRequest,
+        ErrCode::Unauthorized,
+        ErrCode::Forbidden,
+        ErrCode::NotFound,
+        ErrCode::MethodNotAllowed,
+        ErrCode::RequestTimeout,
+        ErrCode::Conflict,
+        ErrCode::PayloadTooLarge,
+        ErrCode::TooManyRequests,
+        ErrCode::InternalServerError,
+        ErrCode::NotImplemented,
+        ErrCode::BadGateway,
+        ErrCode::ServiceUnavailable,
+        ErrCode::GatewayTimeout,
+        ErrCode::ValidationError,
+        ErrCode::DatabaseError,
+        ErrCode::ExternalServiceError,
+        ErrCode::ConfigError,
+        ErrCode::AuthenticationError,
+        ErrCode::AuthorizationError,
+        ErrCode::BusinessLogicError,
+        ErrCode::DataInconsistencyError,
+        ErrCode::RateLimitError,
+        ErrCode::CacheError,
+    ];
+
     /// 获取对应的HTTP状态码
     pub fn http_status(&self) -> u16 {
         match *self {
@@ -145,13 +177,13 @@ impl ErrCode {
     /// 判断是否为客户端错误 (4xx)
     pub fn is_client_error(&self) -> bool {
         let status = self.http_status();
-        status >= 400 && status < 500
+        (400..500).contains(&status)
     }
 
     /// 判断是否为服务器错误 (5xx)
     pub fn is_server_error(&self) -> bool {
         let status = self.http_status();
-        status >= 500 && status < 600
+        (500..600).contains(&status)
     }
 
     /// 判断是否为业务错误 (1000+)
@@ -230,7 +262,11 @@ impl From<u16> for ErrCode {
             502 => ErrCode::BadGateway,
             503 => ErrCode::ServiceUnavailable,
             504 => ErrCode::GatewayTimeout,
-            _ => ErrCode::InternalServerError, // 默认映射到500
+            _ => {
+                // 默认映射到500，记录下来以便补充映射
+                tracing::warn!(status, "unmapped HTTP status, falling back to 500");
+                ErrCode::InternalServerError
+            }
         }
     }
 }
@@ -242,10 +278,15 @@ impl std::fmt::Display for ErrCode {
     }
 }
 
+/// 统一响应结构
+///
+/// `T` 为成功时的数据，`E` 为失败时的结构化错误详情（如字段校验结果、重试建议），
+/// 两者都为空时省略，默认的 `Response<T>` 与原来的扁平 JSON 完全一致。
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Response<T = ()>
+pub struct Response<T = (), E = ()>
 where
     T: Serialize,
+    E: Serialize,
 {
     /// 响应状态码
     pub code: ErrCode,
@@ -257,6 +298,12 @@ where
     /// 响应数据
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<T>,
+    /// 服务端处理耗时（毫秒），仅在开启 `server_timing` 时出现在成功响应中
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_time_ms: Option<f64>,
+    /// 结构化错误详情
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<E>,
 }
 
 impl<T> Response<T>
@@ -270,6 +317,8 @@ where
             msg: msg.into(),
             r#ref: None,
             data: None,
+            server_time_ms: None,
+            detail: None,
         }
     }
 
@@ -280,9 +329,35 @@ where
             msg: msg.into(),
             r#ref: None,
             data: Some(data),
+            server_time_ms: None,
+            detail: None,
         }
     }
 
+    // 成功响应
+    pub fn success(data: Option<T>) -> Self {
+        match data {
+            Some(data) => {
+                Self::with_data(ErrCode::Success, ErrCode::Success.default_message(), data)
+            }
+            None => Self::new(ErrCode::Success, ErrCode::Success.default_message()),
+        }
+    }
+
+    // 失败响应
+    pub fn failed(code: ErrCode, msg: Option<impl Into<String>>) -> Self {
+        match msg {
+            Some(msg) => Self::new(code, msg),
+            None => Self::new(code, code.default_message()),
+        }
+    }
+}
+
+impl<T, E> Response<T, E>
+where
+    T: Serialize,
+    E: Serialize,
+{
     pub fn set_ref(mut self, r#ref: impl Into<String>) -> Self {
         self.r#ref = Some(r#ref.into());
         self
@@ -293,39 +368,51 @@ where
         self
     }
 
-    // 成功响应
-    pub fn success(data: Option<T>) -> Self {
-        if data.is_none() {
-            Self::new(ErrCode::Success, ErrCode::Success.default_message())
-        } else {
-            Self::with_data(
-                ErrCode::Success,
-                ErrCode::Success.default_message(),
-                data.unwrap(),
-            )
-        }
+    /// 附带服务端处理耗时，精确到微秒
+    pub fn with_server_time(mut self, elapsed: Duration) -> Self {
+        self.server_time_ms = Some(elapsed.as_micros() as f64 / 1000.0);
+        self
     }
 
-    // 失败响应
-    pub fn failed(code: ErrCode, msg: Option<impl Into<String>>) -> Self {
-        if msg.is_none() {
-            Self::new(code, code.default_message())
-        } else {
-            Self::new(code, msg.unwrap())
+    /// 附带结构化错误详情，如 `Response::<()>::failed(..).with_detail(errors)`
+    pub fn with_detail<D: Serialize>(self, detail: D) -> Response<T, D> {
+        Response {
+            code: self.code,
+            msg: self.msg,
+            r#ref: self.r#ref,
+            data: self.data,
+            server_time_ms: self.server_time_ms,
+            detail: Some(detail),
         }
     }
 }
 
+/// 直接作为 axum 响应返回时，HTTP 状态码与业务错误码保持一致
+impl<T, E> IntoResponse for Response<T, E>
+where
+    T: Serialize,
+    E: Serialize,
+{
+    fn into_response(self) -> axum::response::Response {
+        let status = StatusCode::from_u16(self.code.http_status())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, Json(self)).into_response()
+    }
+}
+
 // 为了方便测试，实现PartialEq
-impl<T> PartialEq for Response<T>
+impl<T, E> PartialEq for Response<T, E>
 where
     T: Serialize + PartialEq,
+    E: Serialize + PartialEq,
 {
     fn eq(&self, other: &Self) -> bool {
         self.code == other.code
             && self.msg == other.msg
             && self.r#ref == other.r#ref
             && self.data == other.data
+            && self.server_time_ms == other.server_time_ms
+            && self.detail == other.detail
     }
 }
 
@@ -507,6 +594,58 @@ mod tests {
     // Response 基础测试
     // ================================
 
+    /// 没有通配分支：新增变体时这里编译失败，提醒同步更新 `ErrCode::ALL`
+    fn listed_in_all(code: ErrCode) -> bool {
+        match code {
+            ErrCode::Success
+            | ErrCode::BadRequest
+            | ErrCode::Unauthorized
+            | ErrCode::Forbidden
+            | ErrCode::NotFound
+            | ErrCode::MethodNotAllowed
+            | ErrCode::RequestTimeout
+            | ErrCode::Conflict
+            | ErrCode::PayloadTooLarge
+            | ErrCode::TooManyRequests
+            | ErrCode::InternalServerError
+            | ErrCode::NotImplemented
+            | ErrCode::BadGateway
+            | ErrCode::ServiceUnavailable
+            | ErrCode::GatewayTimeout
+            | ErrCode::ValidationError
+            | ErrCode::DatabaseError
+            | ErrCode::ExternalServiceError
+            | ErrCode::ConfigError
+            | ErrCode::AuthenticationError
+            | ErrCode::AuthorizationError
+            | ErrCode::BusinessLogicError
+            | ErrCode::DataInconsistencyError
+            | ErrCode::RateLimitError
+            | ErrCode::CacheError => ErrCode::ALL.contains(&code),
+        }
+    }
+
+    #[test]
+    fn test_errcode_serde_round_trip_all_variants() {
+        let mut seen = std::collections::HashSet::new();
+        for code in ErrCode::ALL {
+            assert!(listed_in_all(code));
+            assert!(seen.insert(code as i32), "duplicate code {}", code);
+
+            let json = serde_json::to_string(&code).unwrap();
+            assert_eq!(json, (code as i32).to_string());
+            let back: ErrCode = serde_json::from_str(&json)
+                .unwrap_or_else(|e| panic!("{:?} does not deserialize: {}", code, e));
+            assert_eq!(back, code);
+        }
+    }
+
+    #[test]
+    fn test_errcode_from_unmapped_status() {
+        assert_eq!(ErrCode::from(418), ErrCode::InternalServerError);
+        assert_eq!(ErrCode::from(201), ErrCode::InternalServerError);
+    }
+
     #[test]
     fn test_response_new() {
         let response = Response::<()>::new(ErrCode::Success, "操作成功");
@@ -673,6 +812,53 @@ mod tests {
         assert_eq!(deserialized.data.unwrap().user_id, 456);
     }
 
+    #[test]
+    fn test_response_serialization_with_server_time() {
+        let response = Response::success(Some(TestData {
+            id: 1,
+            name: "timing".to_string(),
+        }))
+        .with_server_time(Duration::from_micros(1500));
+
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value["server_time_ms"], 1.5);
+
+        let deserialized: Response<TestData> = serde_json::from_value(value).unwrap();
+        assert_eq!(response, deserialized);
+
+        // 未开启时不输出该字段
+        let value = serde_json::to_value(Response::<()>::success(None)).unwrap();
+        assert!(value.get("server_time_ms").is_none());
+    }
+
+    #[test]
+    fn test_response_with_typed_detail() {
+        #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+        struct RetryHint {
+            retry_after_ms: u64,
+        }
+
+        let response = Response::<()>::failed(ErrCode::TooManyRequests, None::<String>)
+            .set_ref("req-429")
+            .with_detail(RetryHint {
+                retry_after_ms: 250,
+            });
+
+        let value = serde_json::to_value(&response).unwrap();
+        assert_eq!(value["code"], 429);
+        assert_eq!(value["ref"], "req-429");
+        assert_eq!(value["detail"]["retry_after_ms"], 250);
+        assert!(value.get("data").is_none());
+
+        let deserialized: Response<(), RetryHint> = serde_json::from_value(value).unwrap();
+        assert_eq!(response, deserialized);
+
+        // 不带详情时保持原来的扁平结构
+        let value = serde_json::to_value(Response::<()>::failed(ErrCode::NotFound, None::<String>))
+            .unwrap();
+        assert!(value.get("detail").is_none());
+    }
+
     // ================================
     // 边界情况和错误处理测试
     // ================================
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use anyhow::Result;
+use shared::config::PaginationConfig;
 use tracing::instrument;
 
 use super::models::User;
@@ -11,17 +12,34 @@ pub trait UserRepo: Send + Sync + std::fmt::Debug {
         &self,
         id: u64,
     ) -> impl std::future::Future<Output = Result<User, UserError>> + Send;
+
+    /// 按 id 升序跳过 offset 条后取 limit 条，同时返回总数
+    fn list_users(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> impl std::future::Future<Output = Result<(Vec<User>, u64), UserError>> + Send;
 }
 
 /// 用户业务逻辑用例
 #[derive(Debug)]
 pub struct UserUseCase<R: UserRepo> {
     user_repo: Arc<R>,
+    pagination: PaginationConfig,
 }
 
 impl<R: UserRepo> UserUseCase<R> {
     pub fn new(user_repo: Arc<R>) -> Self {
-        Self { user_repo }
+        Self {
+            user_repo,
+            pagination: PaginationConfig::default(),
+        }
+    }
+
+    /// 替换分页限制，通常取自 `ServerConfig::pagination`
+    pub fn with_pagination(mut self, pagination: PaginationConfig) -> Self {
+        self.pagination = pagination;
+        self
     }
 
     #[instrument(skip(self))]
@@ -32,28 +50,115 @@ impl<R: UserRepo> UserUseCase<R> {
 
         self.user_repo.get_user(id).await
     }
+
+    /// 分页列出用户，page 从 1 开始，0 使用默认值
+    #[instrument(skip(self))]
+    pub async fn list_users(&self, page: u32, size: u32) -> Result<(Vec<User>, u64), UserError> {
+        let page = if page == 0 { 1 } else { page };
+        let size = if size == 0 {
+            self.pagination.default_page_size
+        } else {
+            size
+        };
+        if size > self.pagination.max_page_size {
+            return Err(UserError::InvalidData(format!(
+                "page size cannot exceed {}",
+                self.pagination.max_page_size
+            )));
+        }
+
+        let offset = (page as usize - 1) * size as usize;
+        self.user_repo.list_users(offset, size as usize).await
+    }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use crate::data::InMemoryUserRepository;
-
-//     #[tokio::test]
-//     async fn test_get_user_success() {
-//         let repo = Arc::new(InMemoryUserRepository::new());
-//         let usecase = UserUseCase::new(repo);
-
-//         let user = usecase.get_user(1).await.unwrap();
-//         assert_eq!(user.name, "Alice");
-//     }
-
-//     #[tokio::test]
-//     async fn test_get_user_invalid_id() {
-//         let repo = Arc::new(InMemoryUserRepository::new());
-//         let usecase = UserUseCase::new(repo);
-
-//         let result = usecase.get_user(0).await;
-//         assert!(matches!(result, Err(UserError::InvalidData(_))));
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::UserRepoImpl;
+
+    #[tokio::test]
+    async fn test_get_user_success() {
+        let repo = Arc::new(UserRepoImpl::new());
+        let usecase = UserUseCase::new(repo);
+
+        let user = usecase.get_user(1).await.unwrap();
+        assert_eq!(user.name, "Alice");
+    }
+
+    #[tokio::test]
+    async fn test_get_user_not_found() {
+        let usecase = UserUseCase::new(Arc::new(UserRepoImpl::new()));
+
+        let result = usecase.get_user(404).await;
+        assert!(matches!(result, Err(UserError::NotFound(404))));
+    }
+
+    #[tokio::test]
+    async fn test_get_user_invalid_id() {
+        let repo = Arc::new(UserRepoImpl::new());
+        let usecase = UserUseCase::new(repo);
+
+        let result = usecase.get_user(0).await;
+        assert!(matches!(result, Err(UserError::InvalidData(_))));
+    }
+
+    #[tokio::test]
+    async fn test_list_users_pagination() {
+        let users = (1..=25).map(|id| {
+            User::new(
+                id,
+                format!("user_{}", id),
+                format!("user_{}@example.com", id),
+                20,
+            )
+        });
+        let usecase = UserUseCase::new(Arc::new(UserRepoImpl::with_users(users)));
+
+        let (page, total) = usecase.list_users(0, 0).await.unwrap();
+        assert_eq!(total, 25);
+        assert_eq!(page.len(), 10);
+        assert_eq!(page[0].id, 1);
+
+        let (page, _) = usecase.list_users(3, 10).await.unwrap();
+        assert_eq!(
+            page.iter().map(|u| u.id).collect::<Vec<_>>(),
+            (21..=25).collect::<Vec<_>>()
+        );
+
+        let (page, total) = usecase.list_users(4, 10).await.unwrap();
+        assert!(page.is_empty());
+        assert_eq!(total, 25);
+
+        let max = PaginationConfig::default().max_page_size;
+        let result = usecase.list_users(1, max + 1).await;
+        assert!(matches!(result, Err(UserError::InvalidData(_))));
+    }
+
+    #[tokio::test]
+    async fn test_list_users_custom_pagination() {
+        let users = (1..=300).map(|id| {
+            User::new(
+                id,
+                format!("user_{}", id),
+                format!("user_{}@example.com", id),
+                20,
+            )
+        });
+        let usecase = UserUseCase::new(Arc::new(UserRepoImpl::with_users(users))).with_pagination(
+            PaginationConfig {
+                default_page_size: 50,
+                max_page_size: 200,
+            },
+        );
+
+        let (page, _) = usecase.list_users(1, 0).await.unwrap();
+        assert_eq!(page.len(), 50);
+        // 超过默认上限 100，但在配置的上限内
+        let (page, _) = usecase.list_users(1, 200).await.unwrap();
+        assert_eq!(page.len(), 200);
+
+        let err = usecase.list_users(1, 201).await.unwrap_err();
+        assert!(err.to_string().contains("200"), "{}", err);
+    }
+}
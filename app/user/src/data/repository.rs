@@ -1,21 +1,101 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
 use anyhow::Result;
 use tracing::instrument;
 
 use crate::biz::{User, UserRepo};
 use crate::error::UserError;
 
+/// 内存版用户仓储，仅用于 demo 和测试
 #[derive(Debug, Clone)]
-pub struct UserRepoImpl {}
+pub struct UserRepoImpl {
+    users: Arc<RwLock<HashMap<u64, User>>>,
+}
 
 impl UserRepoImpl {
+    /// 使用内置的种子数据创建
     pub fn new() -> Self {
-        Self {}
+        Self::with_users(seed_users())
+    }
+
+    /// 使用指定的用户数据创建
+    pub fn with_users(users: impl IntoIterator<Item = User>) -> Self {
+        let users = users.into_iter().map(|u| (u.id, u)).collect();
+        Self {
+            users: Arc::new(RwLock::new(users)),
+        }
     }
 }
 
+fn seed_users() -> Vec<User> {
+    vec![
+        User::new(1, "Alice".to_string(), "alice@example.com".to_string(), 28),
+        User::new(2, "Bob".to_string(), "bob@example.com".to_string(), 32),
+        User::new(
+            3,
+            "Charlie".to_string(),
+            "charlie@example.com".to_string(),
+            25,
+        ),
+    ]
+}
+
 impl UserRepo for UserRepoImpl {
     #[instrument(skip(self))]
     async fn get_user(&self, id: u64) -> Result<User, UserError> {
-        Ok(User::new(id, "test".to_string(), "test".to_string(), 18))
+        let users = self
+            .users
+            .read()
+            .map_err(|e| UserError::InternalError(e.to_string()))?;
+        users.get(&id).cloned().ok_or(UserError::NotFound(id))
+    }
+
+    #[instrument(skip(self))]
+    async fn list_users(&self, offset: usize, limit: usize) -> Result<(Vec<User>, u64), UserError> {
+        let users = self
+            .users
+            .read()
+            .map_err(|e| UserError::InternalError(e.to_string()))?;
+
+        let mut ids: Vec<u64> = users.keys().copied().collect();
+        ids.sort_unstable();
+        let page = ids
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .filter_map(|id| users.get(id).cloned())
+            .collect();
+        Ok((page, users.len() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_seed_users() {
+        let repo = UserRepoImpl::new();
+
+        let user = repo.get_user(1).await.unwrap();
+        assert_eq!(user.name, "Alice");
+        assert_eq!(user.email, "alice@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_custom_users_and_not_found() {
+        let repo = UserRepoImpl::with_users([User::new(
+            42,
+            "Dave".to_string(),
+            "dave@example.com".to_string(),
+            40,
+        )]);
+
+        assert_eq!(repo.get_user(42).await.unwrap().name, "Dave");
+        assert!(matches!(
+            repo.get_user(1).await,
+            Err(UserError::NotFound(1))
+        ));
     }
 }
@@ -57,7 +57,9 @@ fn init_app(cfg: ServerConfig) -> Result<(UserDemoSrvImpl, impl FnOnce())> {
     }
     // data
     let user_demo_repo = UserRepoImpl::new();
-    let user_demo_uc = Arc::new(UserUseCase::new(Arc::new(user_demo_repo)));
+    let user_demo_uc = Arc::new(
+        UserUseCase::new(Arc::new(user_demo_repo)).with_pagination(cfg.pagination.clone()),
+    );
     let service = UserDemoSrvImpl::new(user_demo_uc);
 
     let cleanup = || {
@@ -1,9 +1,10 @@
 use std::sync::Arc;
 use tonic::{Request, Response, Status};
-use tracing::{error, info, instrument};
+use tracing::{error, instrument};
 
 use shared::proto::user::{
     user_demo_server::UserDemo as UserServiceTrait, GetUserRequest, GetUserResponse,
+    ListUsersRequest, ListUsersResponse,
 };
 
 use crate::biz::UserUseCase;
@@ -34,28 +35,78 @@ impl UserServiceTrait for UserDemoSrvImpl {
             })),
             Err(e) => {
                 error!("get user failed: {}", e);
-                Err(Status::internal("get user failed"))
+                // 保留 NotFound 等错误语义，方便调用方区分
+                Err(e.into())
+            }
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn list_users(
+        &self,
+        request: Request<ListUsersRequest>,
+    ) -> Result<Response<ListUsersResponse>, Status> {
+        let req = request.into_inner();
+        match self.huc.list_users(req.page, req.size).await {
+            Ok((users, total)) => Ok(Response::new(ListUsersResponse {
+                users: users.into_iter().map(Into::into).collect(),
+                total,
+            })),
+            Err(e) => {
+                error!("list users failed: {}", e);
+                Err(e.into())
             }
         }
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use crate::data::InMemoryUserRepository;
-
-//     #[tokio::test]
-//     async fn test_get_user_success() {
-//         let repo = Arc::new(InMemoryUserRepository::new());
-//         let usecase = Arc::new(UserUseCase::new(repo));
-//         let service = UserService::new(usecase);
-
-//         let request = Request::new(GetUserRequest { id: 1 });
-//         let response = service.get_user(request).await.unwrap();
-
-//         let user = response.into_inner().user.unwrap();
-//         assert_eq!(user.name, "Alice");
-//         assert_eq!(user.email, "alice@example.com");
-//     }
-// }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::UserRepoImpl;
+
+    #[tokio::test]
+    async fn test_get_user_success() {
+        let repo = Arc::new(UserRepoImpl::new());
+        let usecase = Arc::new(UserUseCase::new(repo));
+        let service = UserDemoSrvImpl::new(usecase);
+
+        let request = Request::new(GetUserRequest { id: 1 });
+        let response = service.get_user(request).await.unwrap();
+
+        let user = response.into_inner().user.unwrap();
+        assert_eq!(user.name, "Alice");
+        assert_eq!(user.email, "alice@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_get_user_not_found() {
+        let repo = Arc::new(UserRepoImpl::new());
+        let usecase = Arc::new(UserUseCase::new(repo));
+        let service = UserDemoSrvImpl::new(usecase);
+
+        let request = Request::new(GetUserRequest { id: 999 });
+        let status = service.get_user(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_list_users() {
+        let repo = Arc::new(UserRepoImpl::new());
+        let usecase = Arc::new(UserUseCase::new(repo));
+        let service = UserDemoSrvImpl::new(usecase);
+
+        let request = Request::new(ListUsersRequest { page: 1, size: 2 });
+        let response = service.list_users(request).await.unwrap().into_inner();
+        assert_eq!(response.total, 3);
+        assert_eq!(response.users.len(), 2);
+        assert_eq!(response.users[0].name, "Alice");
+
+        let request = Request::new(ListUsersRequest {
+            page: 1,
+            size: 1000,
+        });
+        let status = service.list_users(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
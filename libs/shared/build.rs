@@ -1,4 +1,9 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // 未启用 grpc feature 时不需要生成 protobuf 代码
+    if std::env::var_os("CARGO_FEATURE_GRPC").is_none() {
+        return Ok(());
+    }
+
     tonic_prost_build::configure()
         // .out_dir(&out_dir)
         // .file_descriptor_set_path(&out_dir.join("descriptor.bin"))
@@ -14,5 +19,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             &["../../api/user/v1/user.proto"],
             &["../../api/user/v1", "../../api/third_party"],
         )?;
+
+    tonic_prost_build::configure().compile_protos(
+        &["../../api/health/v1/health.proto"],
+        &["../../api/health/v1"],
+    )?;
+
+    // proto 修改后重新生成代码
+    println!("cargo:rerun-if-changed=../../api");
     Ok(())
 }
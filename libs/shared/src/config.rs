@@ -1,15 +1,446 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
 use serde::{Deserialize, Serialize};
 
+use crate::error::SharedError;
+
+/// 服务配置
+///
+/// 新增字段必须带 `#[serde(default)]`（或 `default = "..."`），保证旧版本的配置文件仍能加载；
+/// 未知字段会被忽略，滚动升级期间新配置也能被旧版本读取。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
     pub addr: String,
     pub port: u16,
     pub id_generator: IdGeneratorConfig,
+    /// 按命名空间划分的ID空间，每个命名空间有自己完整的生成器配置（含 epoch 和位布局）
+    ///
+    /// 适合同时托管不同时期创建的ID空间，例如 2020 年起用的旧业务和 2025 年新建的业务。
+    /// 请求通过 `namespace` 参数选择，未指定时使用 `id_generator`。
+    /// worker id 和 datacenter id 沿用 `id_generator` 解析出的值，这里的配置不生效。
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub namespaces: BTreeMap<String, IdGeneratorConfig>,
 
     // grpc server 地址 [addr]:port, 可以有多个
+    #[serde(default)]
     pub grpc_addr: Vec<String>,
 
+    #[serde(default)]
     pub user_rpc: UserRpcConfig,
+
+    /// 批量生成相关配置
+    #[serde(default)]
+    pub batch: BatchConfig,
+
+    /// 健康检查响应配置
+    #[serde(default)]
+    pub health: HealthConfig,
+
+    /// gRPC 请求未携带 grpc-timeout 时使用的默认截止时间（毫秒），None 表示不限制
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grpc_default_deadline_ms: Option<u64>,
+
+    /// StreamIds 同时打开的流数量上限，超出后返回 RESOURCE_EXHAUSTED
+    #[serde(default = "default_grpc_max_concurrent_streams")]
+    pub grpc_max_concurrent_streams: usize,
+
+    /// 管理接口配置
+    #[serde(default)]
+    pub admin: AdminConfig,
+
+    /// 请求头数量/大小限制
+    #[serde(default)]
+    pub header_limit: HeaderLimitConfig,
+
+    /// 列表接口的分页限制
+    #[serde(default)]
+    pub pagination: PaginationConfig,
+
+    /// 维护模式配置，运行时可通过 /admin/maintenance 切换
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+
+    /// `/admin/recent-errors` 保留的最近错误条数，0 表示不记录
+    #[serde(default = "default_recent_errors_capacity")]
+    pub recent_errors_capacity: usize,
+
+    /// 要求请求必须携带的请求头（如 `X-Client-Id`），None 表示不检查
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_header: Option<RequiredHeaderConfig>,
+
+    /// 已生成ID区间的审计日志，None 表示不记录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit: Option<AuditConfig>,
+
+    /// worker id 租约，启动时向协调器租用并定期续期；None 表示 worker id 固定、不续期
+    ///
+    /// 需要嵌入方通过 `start_lease` 接入协调器，http-server 还没有协调器后端，配置后启动报错。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lease: Option<LeaseConfig>,
+
+    /// 只读接口的 Cache-Control 配置
+    #[serde(default)]
+    pub cache: CacheConfig,
+
+    /// 生成器健康采样间隔（秒），None 表示不启动采样任务
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generator_report_interval_secs: Option<u64>,
+
+    /// HTTP 监听 socket 选项
+    #[serde(default)]
+    pub tcp: TcpConfig,
+
+    /// HTTP 响应中ID的前缀（如 "ord_"），None 表示返回裸ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_prefix: Option<IdPrefix>,
+
+    /// `/id` 返回的ID类型，默认雪花ID
+    #[serde(default)]
+    pub id_kind: IdKind,
+
+    /// 在ID接口的成功响应中附带 `server_time_ms`（服务端处理耗时）
+    #[serde(default)]
+    pub server_timing: bool,
+
+    /// 在请求 span 上记录 `x-request-id`（客户端传入或服务端生成），便于从客户端的请求ID定位 trace
+    #[serde(default = "default_log_request_id")]
+    pub log_request_id: bool,
+
+    /// metrics 端口绑定失败时直接退出（fail closed）；默认只记录错误，主服务照常启动
+    #[serde(default)]
+    pub metrics_required: bool,
+}
+
+fn default_recent_errors_capacity() -> usize {
+    50
+}
+
+fn default_log_request_id() -> bool {
+    true
+}
+
+fn default_grpc_max_concurrent_streams() -> usize {
+    64
+}
+
+/// HTTP 监听 socket 选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TcpConfig {
+    /// 对接入连接设置 TCP_NODELAY，避免 Nagle 算法拖慢单个ID这类小响应
+    pub nodelay: bool,
+    /// listen 队列长度
+    pub backlog: u32,
+}
+
+impl Default for TcpConfig {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            backlog: 1024,
+        }
+    }
+}
+
+/// 只读接口的 `Cache-Control` 缓存时间
+///
+/// 同一ID的拆解结果永远不变，可以长期缓存；布局会随运行时切换 worker id 变化，
+/// 只做短期缓存。生成ID的接口始终为 `no-store`，不受此配置影响。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// `/decode/{id}` 的 max-age（秒）
+    pub decode_max_age_secs: u64,
+    /// `/layout` 的 max-age（秒）
+    pub layout_max_age_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            decode_max_age_secs: 24 * 60 * 60,
+            layout_max_age_secs: 60,
+        }
+    }
+}
+
+/// 请求头限制，超出时直接返回 400，防止利用大量或超长请求头耗尽资源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeaderLimitConfig {
+    /// 允许的最大请求头数量
+    pub max_headers: usize,
+    /// 所有请求头名称与值的总字节数上限
+    pub max_header_bytes: usize,
+}
+
+impl Default for HeaderLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_headers: 64,
+            max_header_bytes: 16 * 1024,
+        }
+    }
+}
+
+/// 维护模式：开启后生成ID的接口返回 503，`/health` 同步返回 503
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    /// 启动时即处于维护模式
+    pub enabled: bool,
+    /// 维护期间 503 响应的 `Retry-After`（秒）
+    pub retry_after_secs: u64,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retry_after_secs: 30,
+        }
+    }
+}
+
+/// 必需请求头，缺少时返回 400，用于强制客户端归因（计费/审计）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequiredHeaderConfig {
+    /// 请求头名称（不区分大小写）
+    pub name: String,
+    /// 不做检查的路径，默认豁免探活和版本接口
+    #[serde(default = "default_required_header_exempt_paths")]
+    pub exempt_paths: Vec<String>,
+}
+
+fn default_required_header_exempt_paths() -> Vec<String> {
+    ["/ping", "/health", "/version"].map(String::from).to_vec()
+}
+
+impl Default for RequiredHeaderConfig {
+    fn default() -> Self {
+        Self {
+            name: "x-client-id".to_string(),
+            exempt_paths: default_required_header_exempt_paths(),
+        }
+    }
+}
+
+/// 审计日志的输出位置
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSinkKind {
+    /// 每条区间一行 JSON 写到标准输出
+    #[default]
+    Stdout,
+    /// 追加写入 `path` 指定的文件
+    File,
+}
+
+/// 审计日志：按毫秒记录本节点发出的ID区间 `(worker, datacenter, timestamp, start_seq..=end_seq)`，
+/// 而不是逐个记录ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// 输出位置
+    pub sink: AuditSinkKind,
+    /// `sink = "file"` 时写入的文件
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// 定期写出尚未结束的区间的间隔（毫秒），保证空闲时最后一段区间也能及时落盘
+    pub flush_interval_ms: u64,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            sink: AuditSinkKind::Stdout,
+            path: None,
+            flush_interval_ms: 1000,
+        }
+    }
+}
+
+/// worker id 租约到期（协调器持续不可达）后的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaseExpiryAction {
+    /// 拒绝生成ID（503），进程继续运行，续期恢复后自动恢复服务
+    #[default]
+    Reject,
+    /// 退出进程，交给编排系统重启后重新租用
+    Exit,
+}
+
+/// worker id 租约
+///
+/// 协调器在启动后不可达时，继续使用已租到的 worker id 直到租约到期；
+/// 到期后按 `on_expiry` 处理，不会冒险用可能已被其它节点接手的 worker id 发号。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LeaseConfig {
+    /// 租约时长（毫秒），也是协调器不可达时最多继续服务的时间
+    pub ttl_ms: u64,
+    /// 续期间隔（毫秒），需小于 `ttl_ms`，到期前可以多次重试
+    pub renew_interval_ms: u64,
+    /// 租约到期后的处理方式
+    pub on_expiry: LeaseExpiryAction,
+}
+
+impl Default for LeaseConfig {
+    fn default() -> Self {
+        Self {
+            ttl_ms: 30_000,
+            renew_interval_ms: 10_000,
+            on_expiry: LeaseExpiryAction::Reject,
+        }
+    }
+}
+
+impl LeaseConfig {
+    pub fn validate(&self) -> Result<(), SharedError> {
+        if self.renew_interval_ms == 0 || self.renew_interval_ms >= self.ttl_ms {
+            return Err(SharedError::ValidationError(format!(
+                "lease.renew_interval_ms must be in 1..{} (ttl_ms), got {}",
+                self.ttl_ms, self.renew_interval_ms
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 分页限制，参数校验和列表接口共用同一份配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaginationConfig {
+    /// 未指定每页大小时使用的默认值
+    pub default_page_size: u32,
+    /// 每页大小上限
+    pub max_page_size: u32,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self {
+            default_page_size: 10,
+            max_page_size: 100,
+        }
+    }
+}
+
+/// 管理接口（/admin/*）配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AdminConfig {
+    /// 访问令牌，请求需携带 `Authorization: Bearer <token>`；未配置时不挂载管理接口
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// 批量生成配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BatchConfig {
+    /// 单次批量生成的最大数量
+    pub max_batch_size: usize,
+    /// 批量接口（/ids 及流式接口）允许的最大并发数，超出后直接返回 503
+    pub max_concurrent_ops: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 10000,
+            max_concurrent_ops: 16,
+        }
+    }
+}
+
+/// 健康检查响应格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthFormat {
+    /// 完整 JSON：status、timestamp、service、version 以及自定义字段
+    #[default]
+    Full,
+    /// 仅返回 200 和空 body
+    Minimal,
+}
+
+/// 健康检查配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthConfig {
+    #[serde(default)]
+    pub format: HealthFormat,
+    /// 附加到完整 JSON 中的静态字段（如 region、cluster），不会覆盖内置字段
+    #[serde(default)]
+    pub extra_fields: BTreeMap<String, String>,
+    /// 就绪探针 `/health/ready` 生成一个ID的超时（毫秒）
+    #[serde(default = "default_ready_timeout_ms")]
+    pub ready_timeout_ms: u64,
+}
+
+fn default_ready_timeout_ms() -> u64 {
+    50
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            format: HealthFormat::default(),
+            extra_fields: BTreeMap::new(),
+            ready_timeout_ms: default_ready_timeout_ms(),
+        }
+    }
+}
+
+/// 可在运行时重新加载的配置子集（SIGHUP 触发）
+///
+/// 只包含可以安全热更新的限制类参数，ID 位布局等结构性配置始终以启动时为准。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    /// 单次批量生成的最大数量
+    pub max_batch_size: usize,
+}
+
+impl From<&ServerConfig> for RuntimeConfig {
+    fn from(cfg: &ServerConfig) -> Self {
+        Self {
+            max_batch_size: cfg.batch.max_batch_size,
+        }
+    }
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_size: BatchConfig::default().max_batch_size,
+        }
+    }
+}
+
+/// 运行时配置的共享句柄，读多写少，clone 后指向同一份配置
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeConfigHandle(Arc<ArcSwap<RuntimeConfig>>);
+
+impl RuntimeConfigHandle {
+    pub fn new(cfg: RuntimeConfig) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(cfg)))
+    }
+
+    /// 当前生效的配置快照
+    pub fn load(&self) -> Arc<RuntimeConfig> {
+        self.0.load_full()
+    }
+
+    /// 替换为新的配置，正在处理的请求仍使用旧快照
+    pub fn store(&self, cfg: RuntimeConfig) {
+        self.0.store(Arc::new(cfg));
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,28 +484,250 @@ impl ServerConfig {
             addr,
             port,
             id_generator: IdGeneratorConfig::default(),
+            namespaces: BTreeMap::new(),
             grpc_addr,
             user_rpc: UserRpcConfig::default(),
+            batch: BatchConfig::default(),
+            health: HealthConfig::default(),
+            grpc_default_deadline_ms: None,
+            grpc_max_concurrent_streams: default_grpc_max_concurrent_streams(),
+            admin: AdminConfig::default(),
+            header_limit: HeaderLimitConfig::default(),
+            pagination: PaginationConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            recent_errors_capacity: default_recent_errors_capacity(),
+            required_header: None,
+            audit: None,
+            lease: None,
+            cache: CacheConfig::default(),
+            generator_report_interval_secs: None,
+            tcp: TcpConfig::default(),
+            id_prefix: None,
+            id_kind: IdKind::default(),
+            server_timing: false,
+            log_request_id: default_log_request_id(),
+            metrics_required: false,
         }
     }
 
+    /// 只提供 HTTP 服务的配置，`grpc_addr` 为空
+    pub fn http_only(addr: String, port: u16) -> Self {
+        Self::new(addr, port, vec![])
+    }
+
     pub fn default_for_test() -> Self {
         Self {
             addr: "127.0.0.1".to_string(),
             port: 8080,
             id_generator: IdGeneratorConfig::default(),
+            namespaces: BTreeMap::new(),
             grpc_addr: vec!["[127.0.0.1]:50051".to_string()],
             user_rpc: UserRpcConfig::default(),
+            batch: BatchConfig::default(),
+            health: HealthConfig::default(),
+            grpc_default_deadline_ms: None,
+            grpc_max_concurrent_streams: default_grpc_max_concurrent_streams(),
+            admin: AdminConfig::default(),
+            header_limit: HeaderLimitConfig::default(),
+            pagination: PaginationConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            recent_errors_capacity: default_recent_errors_capacity(),
+            required_header: None,
+            audit: None,
+            lease: None,
+            cache: CacheConfig::default(),
+            generator_report_interval_secs: None,
+            tcp: TcpConfig::default(),
+            id_prefix: None,
+            id_kind: IdKind::default(),
+            server_timing: false,
+            log_request_id: default_log_request_id(),
+            metrics_required: false,
         }
     }
+
+    /// 从配置文件加载（格式按扩展名识别，推荐 TOML）
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SharedError> {
+        let path = path.as_ref();
+        config::Config::builder()
+            .add_source(config::File::from(path))
+            .build()
+            .and_then(|c| c.try_deserialize())
+            .map_err(|e| {
+                SharedError::ConfigurationError(format!(
+                    "failed to load config from {}: {}",
+                    path.display(),
+                    e
+                ))
+            })
+    }
+
+    /// 生成带注释的默认配置模板（TOML）
+    pub fn template() -> String {
+        let cfg = ServerConfig::http_only("0.0.0.0".to_string(), 8080);
+        let value = serde_json::to_value(&cfg).expect("ServerConfig is always serializable");
+
+        let mut out = String::from(
+            "# TinyId 配置模板，由 `ServerConfig::write_template` 生成，所有字段均为默认值\n",
+        );
+        write_toml_table(&mut out, "", &value);
+        out
+    }
+
+    /// 将默认配置模板写入指定路径
+    pub fn write_template(path: impl AsRef<Path>) -> Result<(), SharedError> {
+        let path = path.as_ref();
+        std::fs::write(path, Self::template()).map_err(|e| {
+            SharedError::ConfigurationError(format!(
+                "failed to write config template to {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+/// 模板中各字段的注释，key 为点分路径
+fn template_comment(path: &str) -> Option<&'static str> {
+    let comment = match path {
+        "addr" => "HTTP 监听地址",
+        "port" => "HTTP 监听端口",
+        "grpc_addr" => "gRPC 监听地址 [addr]:port，可以有多个",
+        "grpc_default_deadline_ms" => "gRPC 请求未携带 grpc-timeout 时的默认截止时间（毫秒）",
+        "grpc_max_concurrent_streams" => {
+            "StreamIds 同时打开的流数量上限，超出后返回 RESOURCE_EXHAUSTED"
+        }
+        "admin" => "管理接口配置，设置 token 后启用 /admin/*（Authorization: Bearer <token>）",
+        "batch" => "批量生成配置",
+        "batch.max_batch_size" => "单次批量生成的最大数量",
+        "batch.max_concurrent_ops" => "批量接口允许的最大并发数，超出后返回 503",
+        "header_limit" => "请求头限制，超出时返回 400",
+        "id_prefix" => "HTTP 响应中ID的前缀（如 \"ord_\"），解析ID时会校验并去掉",
+        "namespaces" => "按命名空间划分的生成器配置，字段与 id_generator 相同，请求用 namespace 参数选择",
+        "id_kind" => "/id 返回的ID类型：snowflake（64 位雪花ID）或 ulid（26 位可排序字符串）",
+        "server_timing" => "在ID接口的成功响应中附带 server_time_ms（服务端处理耗时）",
+        "log_request_id" => "在请求 span 上记录 x-request-id，便于从客户端的请求ID定位 trace",
+        "metrics_required" => {
+            "metrics 端口绑定失败时直接退出；false 时只记录错误，主服务照常启动（指标不可用）"
+        }
+        "tcp" => "HTTP 监听 socket 选项",
+        "tcp.nodelay" => "对接入连接设置 TCP_NODELAY，降低小响应的延迟",
+        "tcp.backlog" => "listen 队列长度",
+        "header_limit.max_headers" => "允许的最大请求头数量",
+        "header_limit.max_header_bytes" => "所有请求头名称与值的总字节数上限",
+        "maintenance" => "维护模式，开启后生成ID的接口和 /health 返回 503",
+        "maintenance.enabled" => "启动时即处于维护模式，运行时可通过 /admin/maintenance 切换",
+        "maintenance.retry_after_secs" => "维护期间 503 响应的 Retry-After（秒）",
+        "recent_errors_capacity" => "/admin/recent-errors 保留的最近错误条数，0 表示不记录",
+        "required_header" => "要求请求必须携带的请求头，缺少时返回 400",
+        "required_header.name" => "请求头名称（不区分大小写），值会记录到请求 span",
+        "required_header.exempt_paths" => "不做检查的路径",
+        "audit" => "已生成ID区间的审计日志，按毫秒记录 (worker, datacenter, timestamp, 序列号区间)",
+        "audit.sink" => "输出位置：stdout 或 file",
+        "audit.path" => "sink = \"file\" 时追加写入的文件",
+        "audit.flush_interval_ms" => "定期写出尚未结束的区间的间隔（毫秒）",
+        "lease" => "worker id 租约，协调器不可达时继续使用已租到的 worker id 直到到期",
+        "lease.ttl_ms" => "租约时长（毫秒），也是协调器不可达时最多继续服务的时间",
+        "lease.renew_interval_ms" => "续期间隔（毫秒），需小于 ttl_ms",
+        "lease.on_expiry" => "租约到期后的处理：reject（返回 503，续期恢复后自动恢复）或 exit（退出进程）",
+        "pagination" => "列表接口的分页限制",
+        "pagination.default_page_size" => "未指定每页大小时使用的默认值",
+        "pagination.max_page_size" => "每页大小上限，超出时返回 400",
+        "health" => "健康检查响应配置",
+        "health.format" => "响应格式：full（完整 JSON）或 minimal（仅 200 空 body）",
+        "health.extra_fields" => "附加到完整 JSON 中的静态字段，例如 region = \"eu-west-1\"",
+        "health.ready_timeout_ms" => {
+            "就绪探针 /health/ready 生成一个 ID 的超时（毫秒），超时或失败时返回 503"
+        }
+        "id_generator" => "雪花算法配置，位宽之和（加符号位）不能超过 64",
+        "id_generator.worker_id" => "工作节点ID，不能超过 max_worker_id",
+        "id_generator.datacenter_id" => "数据中心ID，不能超过 max_datacenter_id",
+        "id_generator.sequence_bits" => "序列号位数",
+        "id_generator.worker_id_bits" => "工作节点ID位数",
+        "id_generator.datacenter_id_bits" => "数据中心ID位数",
+        "id_generator.timestamp_bits" => "时间戳位数",
+        "id_generator.epoch" => "起始时间戳（毫秒）",
+        "id_generator.max_sequence" => "最大序列号，应为 (1 << sequence_bits) - 1",
+        "id_generator.max_worker_id" => "最大工作节点ID，应为 (1 << worker_id_bits) - 1",
+        "id_generator.max_datacenter_id" => "最大数据中心ID，应为 (1 << datacenter_id_bits) - 1",
+        "id_generator.environment_bits" => {
+            "环境标识位数（dev/staging/prod 至少需要 2 位），0 表示不启用"
+        }
+        "id_generator.random_fallback_on_clock_error" => {
+            "时钟不可用时退化为随机ID（丢失时间有序性）"
+        }
+        "id_generator.max_backwards_wait_ms" => {
+            "时钟回拨时最多等待的毫秒数，超过后返回错误，0 表示一直等待"
+        }
+        "id_generator.clock_rollback" => {
+            "时钟回拨时的处理：wait（等待，受 max_backwards_wait_ms 限制）、error（立即报错）、borrow（沿用逻辑时钟继续分配）"
+        }
+        "id_generator.backfill_sequences" => {
+            "每毫秒保留给手动补录的序列号数量（最高段），0 表示不开放补录"
+        }
+        "id_generator.total_generated_batch" => {
+            "生成计数的线程本地批量大小，1 表示实时累加；调大可降低争用但统计会略有滞后"
+        }
+        "id_generator.version_bits" => {
+            "ID格式版本号位数（位于最高位），0 表示不启用；启用后版本不一致的ID会被拒绝"
+        }
+        "id_generator.version" => "写入ID的格式版本号，升级位布局时递增",
+        "id_generator.state_file" => "持久化预留高水位的文件，重启（包括崩溃）后从预留上限继续生成",
+        "id_generator.worker_id_source" => {
+            "worker id 来源，设置后忽略 worker_id：from_env（读取 WORKER_ID）、from_hostname（主机名哈希，WORKER_ID 优先）"
+        }
+        "id_generator.state_persist_interval_ms" => {
+            "定期推进 state_file 预留的间隔（毫秒），也是每次预留的时长"
+        }
+        "id_generator.layout" => {
+            "位布局简写（时间戳-数据中心-工作节点-序列号），设置后优先于各位宽字段"
+        }
+        "user_rpc" => "用户服务 RPC 配置",
+        "user_rpc.rpc_cfg.addr" => "用户服务地址列表",
+        _ => return None,
+    };
+    Some(comment)
+}
+
+/// 把 JSON 对象按 TOML 格式输出：先写标量字段，再递归写子表
+fn write_toml_table(out: &mut String, path: &str, value: &serde_json::Value) {
+    let Some(map) = value.as_object() else {
+        return;
+    };
+    let child_path = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+
+    for (key, v) in map.iter().filter(|(_, v)| !v.is_object()) {
+        let full = child_path(key);
+        if let Some(comment) = template_comment(&full) {
+            let _ = writeln!(out, "# {}", comment);
+        }
+        // JSON 的字符串、数字、布尔以及标量数组与 TOML 写法一致
+        let _ = writeln!(out, "{} = {}", key, v);
+    }
+
+    for (key, v) in map.iter().filter(|(_, v)| v.is_object()) {
+        let full = child_path(key);
+        out.push('\n');
+        if let Some(comment) = template_comment(&full) {
+            let _ = writeln!(out, "# {}", comment);
+        }
+        let _ = writeln!(out, "[{}]", full);
+        write_toml_table(out, &full, v);
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IdGeneratorConfig {
-    /// 工作节点ID (0-1023)
-    pub worker_id: u32,
-    /// 数据中心ID (0-31)
-    pub datacenter_id: u32,
+    /// 工作节点ID (0-max_worker_id)
+    pub worker_id: WorkerId,
+    /// 数据中心ID (0-max_datacenter_id)
+    pub datacenter_id: DatacenterId,
     /// 序列号位数
     pub sequence_bits: u32,
     /// 工作节点ID位数
@@ -91,6 +744,521 @@ pub struct IdGeneratorConfig {
     pub max_worker_id: u32,
     /// 最大数据中心ID
     pub max_datacenter_id: u32,
+    /// 时钟不可用时退化为 63 位随机ID，而不是直接报错
+    ///
+    /// 随机ID不含时间戳，丢失时间有序性，只能尽力保证唯一，默认关闭。
+    #[serde(default)]
+    pub random_fallback_on_clock_error: bool,
+    /// 时钟回拨时最多等待的毫秒数，0 表示一直等到时钟追上
+    ///
+    /// 回拨幅度超过该值或累计等待超时后返回 `ClockMovedBackwards`；批量生成时已预留的ID一并丢弃，
+    /// 不会返回部分结果。
+    #[serde(default)]
+    pub max_backwards_wait_ms: u64,
+    /// 时钟回拨时的处理方式，默认等待时钟追上（受 `max_backwards_wait_ms` 约束）
+    #[serde(default)]
+    pub clock_rollback: ClockRollbackPolicy,
+    /// 随机组件（如随机兜底ID）的固定种子，None 时使用线程随机数；主要用于测试复现
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random_seed: Option<u64>,
+    /// 位布局简写，如 "41-3-7-12"，设置后优先于上面的各个位宽字段
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<BitLayout>,
+    /// 环境标识位数，位于时间戳之后，0 表示不启用
+    ///
+    /// 启用后需要相应缩减其它位宽，使总位数不超过 63。
+    #[serde(default)]
+    pub environment_bits: u32,
+    /// 写入ID的环境，未配置时从 `ENVIRONMENT` 环境变量推导
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
+    /// 每毫秒保留给手动补录的序列号数量，0 表示不开放补录
+    ///
+    /// 保留段位于序列号最高处，正常生成只使用其余序列号，补录ID不会与在线生成的ID冲突。
+    #[serde(default)]
+    pub backfill_sequences: u32,
+    /// `total_generated` 计数的批量大小，1 表示每个ID都实时累加
+    ///
+    /// 大于 1 时各线程先在本地累计，满该数量（或线程退出）时再写入共享计数，
+    /// 减少高并发下的争用；代价是统计值最多落后 (批量大小 - 1) × 线程数。
+    #[serde(default = "default_total_generated_batch")]
+    pub total_generated_batch: u32,
+    /// ID格式版本号位数，位于时间戳之上（紧挨符号位），0 表示不启用
+    ///
+    /// 启用后每个ID都带上 `version`，拆解/校验时版本不一致的ID会被拒绝。
+    /// 升级位布局时递增 `version`（位宽保持不变），新布局的实例即可识别并拒绝旧格式的ID，
+    /// 而不是按新布局错误解读；需要先启用版本位再调整布局，未启用版本位时生成的ID无法区分。
+    #[serde(default)]
+    pub version_bits: u32,
+    /// 写入ID的格式版本号，必须能放进 `version_bits`
+    #[serde(default)]
+    pub version: u32,
+    /// 持久化生成状态（预留到的毫秒）的文件，None 表示不持久化
+    ///
+    /// 发出某个毫秒的ID之前，先把预留上限写入该文件；启动时从预留上限继续，
+    /// 即使进程崩溃、重启期间时钟回拨也不会与上次生成的ID重复。代价是重启后
+    /// 最多等待一个预留窗口（约 2 倍 `state_persist_interval_ms`）。文件损坏或与位布局不符时拒绝启动。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_file: Option<String>,
+    /// 定期推进 `state_file` 预留的间隔（毫秒），也是每次推进的预留时长
+    #[serde(default = "default_state_persist_interval_ms")]
+    pub state_persist_interval_ms: u64,
+    /// worker id 的来源，设置后忽略 `worker_id`，启动时按来源解析
+    ///
+    /// 适合所有副本共用同一份配置的部署（如 k8s Deployment）。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worker_id_source: Option<WorkerIdSource>,
+}
+
+fn default_total_generated_batch() -> u32 {
+    1
+}
+
+fn default_state_persist_interval_ms() -> u64 {
+    1000
+}
+
+/// `IdGeneratorConfig` 环境变量前缀，字段名转大写后拼接，如 `TINYID_SEQUENCE_BITS`
+pub const ID_GENERATOR_ENV_PREFIX: &str = "TINYID_";
+
+impl IdGeneratorConfig {
+    /// 从 `TINYID_*` 环境变量构造，未设置的字段使用默认值
+    pub fn from_env() -> Result<Self, SharedError> {
+        Self::default().with_env()
+    }
+
+    /// 用 `TINYID_*` 环境变量覆盖当前配置（如从文件加载的配置）并校验结果
+    pub fn with_env(self) -> Result<Self, SharedError> {
+        self.with_env_from(|key| std::env::var(key).ok())
+    }
+
+    /// `with_env` 的实现，`lookup` 按完整变量名取值，便于测试时不修改进程环境
+    pub fn with_env_from(
+        mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, SharedError> {
+        let lookup = &lookup;
+
+        // 只改了位宽时同步推导对应的最大值，否则默认的 max_* 会与新位宽不符
+        for (name, bits, max) in [
+            ("sequence", &mut self.sequence_bits, &mut self.max_sequence),
+            (
+                "worker_id",
+                &mut self.worker_id_bits,
+                &mut self.max_worker_id,
+            ),
+            (
+                "datacenter_id",
+                &mut self.datacenter_id_bits,
+                &mut self.max_datacenter_id,
+            ),
+        ] {
+            let bits_override = env_value::<u32>(lookup, &format!("{}_bits", name))?;
+            let max_override = env_value::<u32>(lookup, &format!("max_{}", name))?;
+            if let Some(b) = bits_override {
+                *bits = b;
+                *max = 1u32.checked_shl(b).map_or(u32::MAX, |v| v - 1);
+            }
+            if let Some(m) = max_override {
+                *max = m;
+            }
+        }
+        set_from_env(lookup, "timestamp_bits", &mut self.timestamp_bits)?;
+        set_from_env(lookup, "epoch", &mut self.epoch)?;
+        set_from_env(
+            lookup,
+            "random_fallback_on_clock_error",
+            &mut self.random_fallback_on_clock_error,
+        )?;
+        set_from_env(
+            lookup,
+            "max_backwards_wait_ms",
+            &mut self.max_backwards_wait_ms,
+        )?;
+        set_from_env(lookup, "clock_rollback", &mut self.clock_rollback)?;
+        set_from_env(lookup, "environment_bits", &mut self.environment_bits)?;
+        set_from_env(lookup, "backfill_sequences", &mut self.backfill_sequences)?;
+        set_from_env(
+            lookup,
+            "total_generated_batch",
+            &mut self.total_generated_batch,
+        )?;
+        set_from_env(lookup, "version_bits", &mut self.version_bits)?;
+        set_from_env(lookup, "version", &mut self.version)?;
+        set_from_env(
+            lookup,
+            "state_persist_interval_ms",
+            &mut self.state_persist_interval_ms,
+        )?;
+        if let Some(seed) = env_value(lookup, "random_seed")? {
+            self.random_seed = Some(seed);
+        }
+        if let Some(layout) = env_value(lookup, "layout")? {
+            self.layout = Some(layout);
+        }
+        if let Some(environment) = env_value(lookup, "environment")? {
+            self.environment = Some(environment);
+        }
+        if let Some(state_file) = env_value(lookup, "state_file")? {
+            self.state_file = Some(state_file);
+        }
+        if let Some(source) = env_value(lookup, "worker_id_source")? {
+            self.worker_id_source = Some(source);
+        }
+
+        let mut cfg = self.resolve_layout();
+        // 节点ID最后处理，按最终的最大值校验
+        if let Some(id) = env_value(lookup, "worker_id")? {
+            cfg.worker_id = WorkerId::new(id, cfg.max_worker_id)?;
+        }
+        if let Some(id) = env_value(lookup, "datacenter_id")? {
+            cfg.datacenter_id = DatacenterId::new(id, cfg.max_datacenter_id)?;
+        }
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    /// 基本校验：总位数不超过 63，`max_*` 等于位宽上限，节点ID不超过最大值
+    pub fn validate(&self) -> Result<(), SharedError> {
+        let cfg = self.clone().resolve_layout();
+        // 按 u64 求和，避免任意大的位宽在 u32 中溢出
+        let total: u64 = [
+            cfg.version_bits,
+            cfg.timestamp_bits,
+            cfg.environment_bits,
+            cfg.datacenter_id_bits,
+            cfg.worker_id_bits,
+            cfg.sequence_bits,
+        ]
+        .into_iter()
+        .map(u64::from)
+        .sum();
+        if total > 63 {
+            return Err(SharedError::ValidationError(format!(
+                "total bits {} exceeds 63",
+                total
+            )));
+        }
+        for (name, max, bits) in [
+            ("max_sequence", cfg.max_sequence, cfg.sequence_bits),
+            ("max_worker_id", cfg.max_worker_id, cfg.worker_id_bits),
+            (
+                "max_datacenter_id",
+                cfg.max_datacenter_id,
+                cfg.datacenter_id_bits,
+            ),
+        ] {
+            // 超出会写进相邻字段；小于上限多半是改了位宽而忘了同步的旧值
+            let expected = (1u64 << bits) - 1;
+            if u64::from(max) != expected {
+                return Err(SharedError::ValidationError(format!(
+                    "{} {} does not match {} bits (expected {})",
+                    name, max, bits, expected
+                )));
+            }
+        }
+        WorkerId::new(cfg.worker_id.get(), cfg.max_worker_id)?;
+        DatacenterId::new(cfg.datacenter_id.get(), cfg.max_datacenter_id)?;
+        Ok(())
+    }
+
+    /// 从默认布局开始构建配置，时间戳位宽和 `max_*` 由 `build` 推导
+    pub fn builder() -> IdGeneratorConfigBuilder {
+        IdGeneratorConfigBuilder::default()
+    }
+
+    /// 应用 `layout` 简写：覆盖四个位宽并重新计算 `max_*` 字段
+    pub fn resolve_layout(mut self) -> Self {
+        if let Some(layout) = self.layout {
+            self.timestamp_bits = layout.timestamp_bits;
+            self.datacenter_id_bits = layout.datacenter_id_bits;
+            self.worker_id_bits = layout.worker_id_bits;
+            self.sequence_bits = layout.sequence_bits;
+            self.max_sequence = layout.max(layout.sequence_bits);
+            self.max_worker_id = layout.max(layout.worker_id_bits);
+            self.max_datacenter_id = layout.max(layout.datacenter_id_bits);
+        }
+        self
+    }
+}
+
+/// `IdGeneratorConfig` 的构建器，只暴露布局相关的字段，其余字段取默认值
+#[derive(Debug, Clone)]
+pub struct IdGeneratorConfigBuilder {
+    sequence_bits: u32,
+    worker_id_bits: u32,
+    datacenter_id_bits: u32,
+    epoch: u64,
+    worker_id: u32,
+    datacenter_id: u32,
+}
+
+impl Default for IdGeneratorConfigBuilder {
+    fn default() -> Self {
+        let cfg = IdGeneratorConfig::default();
+        Self {
+            sequence_bits: cfg.sequence_bits,
+            worker_id_bits: cfg.worker_id_bits,
+            datacenter_id_bits: cfg.datacenter_id_bits,
+            epoch: cfg.epoch,
+            worker_id: cfg.worker_id.get(),
+            datacenter_id: cfg.datacenter_id.get(),
+        }
+    }
+}
+
+impl IdGeneratorConfigBuilder {
+    pub fn sequence_bits(mut self, bits: u32) -> Self {
+        self.sequence_bits = bits;
+        self
+    }
+
+    pub fn worker_id_bits(mut self, bits: u32) -> Self {
+        self.worker_id_bits = bits;
+        self
+    }
+
+    pub fn datacenter_id_bits(mut self, bits: u32) -> Self {
+        self.datacenter_id_bits = bits;
+        self
+    }
+
+    /// 纪元时间（毫秒）
+    pub fn epoch(mut self, epoch: u64) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    pub fn worker_id(mut self, id: u32) -> Self {
+        self.worker_id = id;
+        self
+    }
+
+    pub fn datacenter_id(mut self, id: u32) -> Self {
+        self.datacenter_id = id;
+        self
+    }
+
+    /// 时间戳占用剩余的位（63 减去其余字段），`max_*` 取各位宽的上限
+    pub fn build(self) -> Result<IdGeneratorConfig, SharedError> {
+        let node_bits = self
+            .sequence_bits
+            .saturating_add(self.worker_id_bits)
+            .saturating_add(self.datacenter_id_bits);
+        let layout = format!(
+            "{}-{}-{}-{}",
+            63u32.saturating_sub(node_bits),
+            self.datacenter_id_bits,
+            self.worker_id_bits,
+            self.sequence_bits
+        );
+        // 复用 BitLayout 的校验：时间戳和序列号至少 1 位，节点字段不超过 31 位
+        let layout: BitLayout = layout.parse()?;
+        let mut cfg = IdGeneratorConfig {
+            epoch: self.epoch,
+            layout: Some(layout),
+            ..IdGeneratorConfig::default()
+        }
+        .resolve_layout();
+        cfg.layout = None;
+        cfg.worker_id = WorkerId::new(self.worker_id, cfg.max_worker_id)?;
+        cfg.datacenter_id = DatacenterId::new(self.datacenter_id, cfg.max_datacenter_id)?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+}
+
+/// 读取 `TINYID_<FIELD>` 并解析，未设置时返回 None
+fn env_value<T>(
+    lookup: &dyn Fn(&str) -> Option<String>,
+    field: &str,
+) -> Result<Option<T>, SharedError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let key = format!("{}{}", ID_GENERATOR_ENV_PREFIX, field.to_ascii_uppercase());
+    let Some(value) = lookup(&key) else {
+        return Ok(None);
+    };
+    value
+        .trim()
+        .parse()
+        .map(Some)
+        .map_err(|e| SharedError::ConfigurationError(format!("invalid {}={:?}: {}", key, value, e)))
+}
+
+/// 设置了 `TINYID_<FIELD>` 时覆盖 `target`
+fn set_from_env<T>(
+    lookup: &dyn Fn(&str) -> Option<String>,
+    field: &str,
+    target: &mut T,
+) -> Result<(), SharedError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    if let Some(value) = env_value(lookup, field)? {
+        *target = value;
+    }
+    Ok(())
+}
+
+/// `/id` 返回的ID类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdKind {
+    /// 64 位雪花ID
+    #[default]
+    Snowflake,
+    /// 128 位 ULID，Crockford base32 编码为 26 个字符
+    Ulid,
+}
+
+/// HTTP 响应中ID的前缀，形如 Stripe 的 `ord_1a2b3c`
+///
+/// 以字母开头、只含字母数字和下划线，最长 16 个字符；以字母开头保证裸的数字ID不会被误认为带前缀。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct IdPrefix(String);
+
+impl IdPrefix {
+    const MAX_LEN: usize = 16;
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::str::FromStr for IdPrefix {
+    type Err = SharedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let valid = s.len() <= Self::MAX_LEN
+            && s.starts_with(|c: char| c.is_ascii_alphabetic())
+            && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(SharedError::ValidationError(format!(
+                "invalid id prefix {:?}: expected up to {} ASCII letters, digits or '_', starting with a letter",
+                s,
+                Self::MAX_LEN
+            )));
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl fmt::Display for IdPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<String> for IdPrefix {
+    type Error = SharedError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<IdPrefix> for String {
+    fn from(prefix: IdPrefix) -> Self {
+        prefix.0
+    }
+}
+
+/// ID 位布局：时间戳-数据中心-工作节点-序列号
+///
+/// 在配置中写成 "41-3-7-12" 这样的字符串，四段之和不能超过 63（保留符号位）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BitLayout {
+    pub timestamp_bits: u32,
+    pub datacenter_id_bits: u32,
+    pub worker_id_bits: u32,
+    pub sequence_bits: u32,
+}
+
+impl BitLayout {
+    fn max(&self, bits: u32) -> u32 {
+        ((1u64 << bits) - 1) as u32
+    }
+}
+
+impl std::str::FromStr for BitLayout {
+    type Err = SharedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| {
+            SharedError::ValidationError(format!("invalid bit layout {:?}: {}", s, reason))
+        };
+
+        let parts = s
+            .split('-')
+            .map(|p| p.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid("expected timestamp-datacenter-worker-sequence"))?;
+        let [timestamp_bits, datacenter_id_bits, worker_id_bits, sequence_bits] = parts[..] else {
+            return Err(invalid("expected 4 segments"));
+        };
+
+        if timestamp_bits == 0 || sequence_bits == 0 {
+            return Err(invalid("timestamp and sequence bits must be positive"));
+        }
+        // 序列号和节点ID都以 u32 保存
+        if [datacenter_id_bits, worker_id_bits, sequence_bits]
+            .iter()
+            .any(|&bits| bits > 31)
+        {
+            return Err(invalid(
+                "datacenter, worker and sequence bits must be at most 31",
+            ));
+        }
+        let total = [
+            timestamp_bits,
+            datacenter_id_bits,
+            worker_id_bits,
+            sequence_bits,
+        ]
+        .into_iter()
+        .map(u64::from)
+        .sum::<u64>();
+        if total > 63 {
+            return Err(invalid(&format!("total bits {} exceeds 63", total)));
+        }
+
+        Ok(Self {
+            timestamp_bits,
+            datacenter_id_bits,
+            worker_id_bits,
+            sequence_bits,
+        })
+    }
+}
+
+impl fmt::Display for BitLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}-{}-{}",
+            self.timestamp_bits, self.datacenter_id_bits, self.worker_id_bits, self.sequence_bits
+        )
+    }
+}
+
+impl TryFrom<String> for BitLayout {
+    type Error = SharedError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<BitLayout> for String {
+    fn from(layout: BitLayout) -> Self {
+        layout.to_string()
+    }
 }
 
 impl Default for IdGeneratorConfig {
@@ -101,8 +1269,8 @@ impl Default for IdGeneratorConfig {
         let timestamp_bits = 41;
 
         Self {
-            worker_id: 0,
-            datacenter_id: 0,
+            worker_id: WorkerId::default(),
+            datacenter_id: DatacenterId::default(),
             sequence_bits,
             worker_id_bits,
             datacenter_id_bits,
@@ -111,6 +1279,738 @@ impl Default for IdGeneratorConfig {
             max_sequence: (1 << sequence_bits) - 1,
             max_worker_id: (1 << worker_id_bits) - 1,
             max_datacenter_id: (1 << datacenter_id_bits) - 1,
+            random_fallback_on_clock_error: false,
+            max_backwards_wait_ms: 0,
+            clock_rollback: ClockRollbackPolicy::default(),
+            random_seed: None,
+            layout: None,
+            environment_bits: 0,
+            environment: None,
+            backfill_sequences: 0,
+            total_generated_batch: default_total_generated_batch(),
+            version_bits: 0,
+            version: 0,
+            state_file: None,
+            state_persist_interval_ms: default_state_persist_interval_ms(),
+            worker_id_source: None,
         }
     }
 }
+
+/// 时钟回拨（当前时间早于已发出的最后一个毫秒）时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockRollbackPolicy {
+    /// 等待时钟追上，等待上限见 `max_backwards_wait_ms`
+    #[default]
+    Wait,
+    /// 立即返回 `ClockMovedBackwards`，由调用方重试或切换节点
+    Error,
+    /// 沿用最后一个毫秒继续分配，序列号用尽时直接占用下一毫秒，不等待时钟
+    ///
+    /// 回拨期间生成的ID时间戳会超前于真实时间；持续高负载时逻辑时钟可能越跑越快。
+    Borrow,
+}
+
+impl std::str::FromStr for ClockRollbackPolicy {
+    type Err = SharedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "wait" => Ok(ClockRollbackPolicy::Wait),
+            "error" => Ok(ClockRollbackPolicy::Error),
+            "borrow" => Ok(ClockRollbackPolicy::Borrow),
+            other => Err(SharedError::ValidationError(format!(
+                "unknown clock rollback policy: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// `WorkerIdSource` 读取的环境变量
+pub const WORKER_ID_ENV: &str = "WORKER_ID";
+
+/// 自动分配 worker id 的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkerIdSource {
+    /// 读取 `WORKER_ID` 环境变量（如 StatefulSet 序号），未设置时报错
+    FromEnv,
+    /// 把主机名（pod 名）哈希到 `[0, max_worker_id]`，设置了 `WORKER_ID` 时以它为准
+    ///
+    /// 副本数接近 worker id 空间时可能冲突，需要严格唯一时使用 `FromEnv`。
+    FromHostname,
+}
+
+impl WorkerIdSource {
+    /// 按进程环境解析 worker id
+    pub fn resolve(self, max_worker_id: u32) -> Result<WorkerId, SharedError> {
+        self.resolve_from(
+            max_worker_id,
+            |key| std::env::var(key).ok(),
+            system_hostname,
+        )
+    }
+
+    /// `resolve` 的实现，`lookup` 按变量名取值、`hostname` 返回主机名，便于测试时不修改进程环境
+    pub fn resolve_from(
+        self,
+        max_worker_id: u32,
+        lookup: impl Fn(&str) -> Option<String>,
+        hostname: impl FnOnce() -> Option<String>,
+    ) -> Result<WorkerId, SharedError> {
+        if let Some(raw) = lookup(WORKER_ID_ENV) {
+            let id = raw.trim().parse::<u32>().map_err(|e| {
+                SharedError::ValidationError(format!("invalid {}={:?}: {}", WORKER_ID_ENV, raw, e))
+            })?;
+            return WorkerId::new(id, max_worker_id);
+        }
+        match self {
+            WorkerIdSource::FromEnv => Err(SharedError::ValidationError(format!(
+                "worker_id_source is from_env but {} is not set",
+                WORKER_ID_ENV
+            ))),
+            WorkerIdSource::FromHostname => {
+                let host = hostname()
+                    .map(|h| h.trim().to_string())
+                    .filter(|h| !h.is_empty())
+                    .ok_or_else(|| {
+                        SharedError::ValidationError("hostname is not available".to_string())
+                    })?;
+                let id = fnv1a(host.as_bytes()) % (u64::from(max_worker_id) + 1);
+                WorkerId::new(id as u32, max_worker_id)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for WorkerIdSource {
+    type Err = SharedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "from_env" => Ok(WorkerIdSource::FromEnv),
+            "from_hostname" => Ok(WorkerIdSource::FromHostname),
+            other => Err(SharedError::ValidationError(format!(
+                "unknown worker id source: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// 主机名：优先 `HOSTNAME` 环境变量（k8s 中为 pod 名），其次 `/etc/hostname`
+fn system_hostname() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+}
+
+/// FNV-1a 64 位哈希，结果不随 Rust 版本或进程变化，同一主机名总是得到同一个 worker id
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// 部署环境，启用 `environment_bits` 后编码进ID，防止跨环境误用
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Environment {
+    Dev,
+    Staging,
+    Prod,
+}
+
+impl Environment {
+    /// 从 `ENVIRONMENT` 环境变量推导，未设置时视为开发环境
+    pub fn from_env() -> Result<Self, SharedError> {
+        std::env::var("ENVIRONMENT")
+            .map(|v| v.parse())
+            .unwrap_or(Ok(Environment::Dev))
+    }
+
+    /// 写入ID中的编码
+    pub fn code(self) -> u64 {
+        match self {
+            Environment::Dev => 0,
+            Environment::Staging => 1,
+            Environment::Prod => 2,
+        }
+    }
+
+    pub fn from_code(code: u64) -> Option<Self> {
+        match code {
+            0 => Some(Environment::Dev),
+            1 => Some(Environment::Staging),
+            2 => Some(Environment::Prod),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for Environment {
+    type Err = SharedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "dev" | "development" => Ok(Environment::Dev),
+            "staging" => Ok(Environment::Staging),
+            "prod" | "production" => Ok(Environment::Prod),
+            other => Err(SharedError::ValidationError(format!(
+                "unknown environment: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// 工作节点ID
+///
+/// 只能通过 [`WorkerId::new`] 做范围校验后构造，避免与数据中心ID混用。
+/// 反序列化时不知道位宽，`IDGenerator` 仍会按配置的最大值再校验一次。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WorkerId(u32);
+
+impl WorkerId {
+    pub fn new(id: u32, max_worker_id: u32) -> Result<Self, SharedError> {
+        if id > max_worker_id {
+            return Err(SharedError::ValidationError(format!(
+                "worker_id is too large: {} > {}",
+                id, max_worker_id
+            )));
+        }
+        Ok(Self(id))
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for WorkerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// 数据中心ID
+///
+/// 与 [`WorkerId`] 相同，构造时校验范围。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DatacenterId(u32);
+
+impl DatacenterId {
+    pub fn new(id: u32, max_datacenter_id: u32) -> Result<Self, SharedError> {
+        if id > max_datacenter_id {
+            return Err(SharedError::ValidationError(format!(
+                "datacenter_id is too large: {} > {}",
+                id, max_datacenter_id
+            )));
+        }
+        Ok(Self(id))
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for DatacenterId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_round_trip() {
+        let path = std::env::temp_dir().join(format!("tinyid-{}.toml", uuid::Uuid::new_v4()));
+        ServerConfig::write_template(&path).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("[id_generator]"));
+        assert!(text.contains("# 工作节点ID位数"));
+
+        let loaded = ServerConfig::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let expected = ServerConfig::new("0.0.0.0".to_string(), 8080, vec![]);
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_namespaces_from_file() {
+        let path = std::env::temp_dir().join(format!("tinyid-{}.toml", uuid::Uuid::new_v4()));
+        let mut text = ServerConfig::template();
+        text.push_str(
+            r#"
+[namespaces.legacy]
+worker_id = 3
+datacenter_id = 1
+sequence_bits = 12
+worker_id_bits = 7
+datacenter_id_bits = 3
+timestamp_bits = 41
+epoch = 1577836800000
+max_sequence = 4095
+max_worker_id = 127
+max_datacenter_id = 7
+"#,
+        );
+        std::fs::write(&path, text).unwrap();
+        let cfg = ServerConfig::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(cfg.namespaces.len(), 1);
+        assert_eq!(cfg.namespaces["legacy"].epoch, 1577836800000);
+        assert_eq!(cfg.namespaces["legacy"].worker_id.get(), 3);
+        // 默认生成器不受影响
+        assert_eq!(cfg.id_generator.epoch, IdGeneratorConfig::default().epoch);
+    }
+
+    #[test]
+    fn test_lease_config() {
+        let cfg: ServerConfig = serde_json::from_value(serde_json::json!({
+            "addr": "0.0.0.0",
+            "port": 8080,
+            "id_generator": IdGeneratorConfig::default(),
+            "lease": { "ttl_ms": 5000, "on_expiry": "exit" },
+        }))
+        .unwrap();
+        let lease = cfg.lease.unwrap();
+        assert_eq!(lease.ttl_ms, 5000);
+        assert_eq!(lease.renew_interval_ms, 10_000);
+        assert_eq!(lease.on_expiry, LeaseExpiryAction::Exit);
+        // 续期间隔不小于租约时长时，到期前没有重试机会
+        assert!(lease.validate().is_err());
+        assert!(LeaseConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_older_config_still_loads() {
+        // 早期版本的配置文件：只有最初的字段，子配置也只写了部分字段，另带一个未知字段
+        let path = std::env::temp_dir().join(format!("tinyid-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"
+addr = "0.0.0.0"
+port = 8080
+grpc_addr = ["[::1]:50051"]
+field_from_newer_release = true
+
+[id_generator]
+worker_id = 3
+datacenter_id = 1
+sequence_bits = 12
+worker_id_bits = 5
+datacenter_id_bits = 5
+timestamp_bits = 41
+epoch = 1735689600000
+max_sequence = 4095
+max_worker_id = 31
+max_datacenter_id = 31
+
+[user_rpc.rpc_cfg]
+addr = ["http://[::1]:50052"]
+
+[tcp]
+nodelay = false
+
+[batch]
+max_batch_size = 500
+"#,
+        )
+        .unwrap();
+
+        let loaded = ServerConfig::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.id_generator.worker_id.get(), 3);
+        assert!(!loaded.tcp.nodelay);
+        assert_eq!(loaded.tcp.backlog, TcpConfig::default().backlog);
+        assert_eq!(loaded.batch.max_batch_size, 500);
+        assert_eq!(
+            loaded.batch.max_concurrent_ops,
+            BatchConfig::default().max_concurrent_ops
+        );
+        assert_eq!(
+            loaded.id_generator.total_generated_batch,
+            default_total_generated_batch()
+        );
+        assert_eq!(loaded.log_request_id, default_log_request_id());
+        assert!(!loaded.maintenance.enabled);
+    }
+
+    #[test]
+    fn test_id_generator_config_with_env() {
+        let env: BTreeMap<&str, &str> = [
+            ("TINYID_SEQUENCE_BITS", "10"),
+            ("TINYID_WORKER_ID_BITS", "9"),
+            ("TINYID_WORKER_ID", "300"),
+            ("TINYID_EPOCH", " 1700000000000 "),
+            ("TINYID_ENVIRONMENT", "prod"),
+            ("TINYID_ENVIRONMENT_BITS", "2"),
+            // 让出环境位后总位数仍为 63
+            ("TINYID_TIMESTAMP_BITS", "39"),
+        ]
+        .into_iter()
+        .collect();
+        let lookup = |key: &str| env.get(key).map(|v| v.to_string());
+
+        // 环境变量覆盖文件中的值，未设置的字段保持不变
+        let file = IdGeneratorConfig {
+            datacenter_id: DatacenterId::new(5, 7).unwrap(),
+            ..IdGeneratorConfig::default()
+        };
+        let cfg = file.with_env_from(lookup).unwrap();
+        assert_eq!(cfg.sequence_bits, 10);
+        assert_eq!(cfg.max_sequence, 1023);
+        assert_eq!(cfg.max_worker_id, 511);
+        assert_eq!(cfg.worker_id.get(), 300);
+        assert_eq!(cfg.datacenter_id.get(), 5);
+        assert_eq!(cfg.epoch, 1_700_000_000_000);
+        assert_eq!(cfg.environment, Some(Environment::Prod));
+        assert_eq!(cfg.environment_bits, 2);
+        assert_eq!(cfg.timestamp_bits, 39);
+
+        // 未设置任何变量时等同于默认配置
+        let cfg = IdGeneratorConfig::default()
+            .with_env_from(|_| None)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&cfg).unwrap(),
+            serde_json::to_value(IdGeneratorConfig::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_id_generator_config_with_env_rejects_invalid() {
+        let parse_err = IdGeneratorConfig::default()
+            .with_env_from(|key| (key == "TINYID_EPOCH").then(|| "soon".to_string()))
+            .unwrap_err();
+        assert!(parse_err.to_string().contains("TINYID_EPOCH"));
+
+        // 默认 7 位工作节点ID放不下 200
+        let range_err = IdGeneratorConfig::default()
+            .with_env_from(|key| (key == "TINYID_WORKER_ID").then(|| "200".to_string()))
+            .unwrap_err();
+        assert!(range_err.to_string().contains("worker_id"));
+
+        let bits_err = IdGeneratorConfig::default()
+            .with_env_from(|key| (key == "TINYID_TIMESTAMP_BITS").then(|| "50".to_string()))
+            .unwrap_err();
+        assert!(bits_err.to_string().contains("exceeds 63"));
+    }
+
+    #[test]
+    fn test_environment_parse() {
+        assert_eq!(
+            "development".parse::<Environment>().unwrap(),
+            Environment::Dev
+        );
+        assert_eq!(
+            "Staging".parse::<Environment>().unwrap(),
+            Environment::Staging
+        );
+        assert_eq!("prod".parse::<Environment>().unwrap(), Environment::Prod);
+        assert!("qa".parse::<Environment>().is_err());
+
+        for env in [Environment::Dev, Environment::Staging, Environment::Prod] {
+            assert_eq!(Environment::from_code(env.code()), Some(env));
+        }
+        assert_eq!(Environment::from_code(3), None);
+    }
+
+    #[test]
+    fn test_bit_layout_parse() {
+        let layout: BitLayout = "41-3-7-12".parse().unwrap();
+        assert_eq!(layout.timestamp_bits, 41);
+        assert_eq!(layout.datacenter_id_bits, 3);
+        assert_eq!(layout.worker_id_bits, 7);
+        assert_eq!(layout.sequence_bits, 12);
+        assert_eq!(layout.to_string(), "41-3-7-12");
+
+        assert!("41-3-7".parse::<BitLayout>().is_err());
+        assert!("41-3-7-x".parse::<BitLayout>().is_err());
+        assert!("42-5-10-12".parse::<BitLayout>().is_err());
+        assert!("0-3-7-12".parse::<BitLayout>().is_err());
+        // 位宽之和超出 u32 时同样报错而不是溢出
+        assert!("4294967295-0-0-1".parse::<BitLayout>().is_err());
+    }
+
+    #[test]
+    fn test_id_prefix_parse() {
+        let prefix: IdPrefix = "ord_".parse().unwrap();
+        assert_eq!(prefix.as_str(), "ord_");
+        let cfg: ServerConfig = serde_json::from_value(serde_json::json!({
+            "addr": "127.0.0.1",
+            "port": 8080,
+            "id_generator": serde_json::to_value(IdGeneratorConfig::default()).unwrap(),
+            "grpc_addr": [],
+            "user_rpc": serde_json::to_value(UserRpcConfig::default()).unwrap(),
+            "id_prefix": "usr_",
+        }))
+        .unwrap();
+        assert_eq!(cfg.id_prefix.unwrap().as_str(), "usr_");
+
+        for invalid in ["", "1ord_", "ord-", "ord_with_a_very_long_name"] {
+            assert!(invalid.parse::<IdPrefix>().is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_layout_takes_precedence() {
+        let json = serde_json::json!({
+            "worker_id": 200,
+            "datacenter_id": 1,
+            "sequence_bits": 12,
+            "worker_id_bits": 7,
+            "datacenter_id_bits": 3,
+            "timestamp_bits": 41,
+            "epoch": 1735689600000u64,
+            "max_sequence": 4095,
+            "max_worker_id": 127,
+            "max_datacenter_id": 7,
+            "layout": "41-2-10-10"
+        });
+        let cfg: IdGeneratorConfig = serde_json::from_value(json).unwrap();
+        let cfg = cfg.resolve_layout();
+
+        assert_eq!(cfg.worker_id_bits, 10);
+        assert_eq!(cfg.max_worker_id, 1023);
+        assert_eq!(cfg.max_datacenter_id, 3);
+        assert_eq!(cfg.max_sequence, 1023);
+        assert!(WorkerId::new(cfg.worker_id.get(), cfg.max_worker_id).is_ok());
+
+        let bad = serde_json::json!({ "layout": "41-3-7" });
+        assert!(serde_json::from_value::<BitLayout>(bad["layout"].clone()).is_err());
+    }
+
+    #[test]
+    fn test_validate_max_matches_bits() {
+        assert!(IdGeneratorConfig::default().validate().is_ok());
+
+        // 只改位宽、保留默认 max_sequence = 4095，会溢出到 worker 字段
+        let cfg = IdGeneratorConfig {
+            sequence_bits: 10,
+            ..IdGeneratorConfig::default()
+        };
+        let err = cfg.validate().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            SharedError::ValidationError(
+                "max_sequence 4095 does not match 10 bits (expected 1023)".to_string()
+            )
+            .to_string()
+        );
+
+        let cfg = IdGeneratorConfig {
+            max_worker_id: IdGeneratorConfig::default().max_worker_id + 1,
+            ..IdGeneratorConfig::default()
+        };
+        assert!(cfg
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("max_worker_id"));
+
+        let cfg = IdGeneratorConfig {
+            timestamp_bits: u32::MAX,
+            ..IdGeneratorConfig::default()
+        };
+        assert!(cfg
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("total bits 4294967317 exceeds 63"));
+
+        // 位宽改大后遗留的旧 max_sequence 同样拒绝，否则序列号只用到一部分
+        let cfg = IdGeneratorConfig {
+            sequence_bits: 13,
+            timestamp_bits: 40,
+            ..IdGeneratorConfig::default()
+        };
+        assert_eq!(
+            cfg.validate().unwrap_err().to_string(),
+            SharedError::ValidationError(
+                "max_sequence 4095 does not match 13 bits (expected 8191)".to_string()
+            )
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_builder_matches_default() {
+        let built = IdGeneratorConfig::builder().build().unwrap();
+        assert_eq!(
+            serde_json::to_value(&built).unwrap(),
+            serde_json::to_value(IdGeneratorConfig::default()).unwrap()
+        );
+
+        let built = IdGeneratorConfig::builder()
+            .sequence_bits(10)
+            .worker_id_bits(5)
+            .datacenter_id_bits(5)
+            .epoch(1_700_000_000_000)
+            .worker_id(31)
+            .datacenter_id(2)
+            .build()
+            .unwrap();
+        assert_eq!(built.timestamp_bits, 43);
+        assert_eq!(built.max_sequence, 1023);
+        assert_eq!(built.max_worker_id, 31);
+        assert_eq!(built.max_datacenter_id, 31);
+        assert_eq!(built.epoch, 1_700_000_000_000);
+        assert_eq!((built.worker_id.get(), built.datacenter_id.get()), (31, 2));
+        assert!(built.layout.is_none());
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_layout() {
+        // 节点字段占满 63 位，时间戳没有位置
+        assert!(IdGeneratorConfig::builder()
+            .sequence_bits(31)
+            .worker_id_bits(31)
+            .datacenter_id_bits(1)
+            .build()
+            .is_err());
+        assert!(IdGeneratorConfig::builder()
+            .sequence_bits(0)
+            .build()
+            .is_err());
+        assert!(IdGeneratorConfig::builder()
+            .worker_id_bits(32)
+            .build()
+            .is_err());
+        // 节点ID超出位宽
+        assert!(IdGeneratorConfig::builder()
+            .worker_id_bits(5)
+            .worker_id(32)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_runtime_config_handle_shares_updates() {
+        let handle =
+            RuntimeConfigHandle::new(RuntimeConfig::from(&ServerConfig::default_for_test()));
+        let cloned = handle.clone();
+        let snapshot = handle.load();
+
+        cloned.store(RuntimeConfig { max_batch_size: 5 });
+        assert_eq!(handle.load().max_batch_size, 5);
+        // 旧快照不受影响
+        assert_eq!(
+            snapshot.max_batch_size,
+            BatchConfig::default().max_batch_size
+        );
+    }
+
+    #[test]
+    fn test_http_only() {
+        let cfg = ServerConfig::http_only("0.0.0.0".to_string(), 8080);
+        assert_eq!((cfg.addr.as_str(), cfg.port), ("0.0.0.0", 8080));
+        assert!(cfg.grpc_addr.is_empty());
+        assert_eq!(
+            serde_json::to_value(&cfg).unwrap(),
+            serde_json::to_value(ServerConfig::new("0.0.0.0".to_string(), 8080, vec![])).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_file_missing() {
+        let err = ServerConfig::from_file("/nonexistent/tinyid.toml").unwrap_err();
+        assert!(matches!(err, SharedError::ConfigurationError(_)));
+    }
+
+    #[test]
+    fn test_worker_id_range_check() {
+        assert_eq!(WorkerId::new(31, 31).unwrap().get(), 31);
+        let err = WorkerId::new(32, 31).unwrap_err();
+        assert!(err.to_string().contains("worker_id is too large"));
+
+        assert_eq!(DatacenterId::new(0, 7).unwrap().get(), 0);
+        let err = DatacenterId::new(8, 7).unwrap_err();
+        assert!(err.to_string().contains("datacenter_id is too large"));
+    }
+
+    #[test]
+    fn test_worker_id_source_hostname_hash() {
+        let no_env = |_: &str| None;
+        let resolve = |host: &str, max: u32| {
+            let host = host.to_string();
+            WorkerIdSource::FromHostname
+                .resolve_from(max, no_env, || Some(host))
+                .unwrap()
+                .get()
+        };
+
+        // 同一主机名总是得到同一个 worker id，且不超过最大值
+        let id = resolve("tinyid-7d9f8b6c4-x2k9p", 127);
+        assert_eq!(id, resolve("tinyid-7d9f8b6c4-x2k9p\n", 127));
+        assert!(id <= 127);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(resolve("tinyid-0", 0), 0);
+        let ids: std::collections::HashSet<_> = (0..20)
+            .map(|i| resolve(&format!("tinyid-{}", i), 127))
+            .collect();
+        assert!(ids.len() > 1);
+
+        assert!(WorkerIdSource::FromHostname
+            .resolve_from(127, no_env, || Some(" ".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_worker_id_source_env_takes_precedence() {
+        let env = |key: &str| (key == WORKER_ID_ENV).then(|| "42".to_string());
+        for source in [WorkerIdSource::FromEnv, WorkerIdSource::FromHostname] {
+            let id = source
+                .resolve_from(127, env, || Some("tinyid-0".to_string()))
+                .unwrap();
+            assert_eq!(id.get(), 42);
+        }
+
+        // from_env 没有 WORKER_ID 时报错，超出范围或非数字同样报错
+        assert!(WorkerIdSource::FromEnv
+            .resolve_from(127, |_| None, || None)
+            .is_err());
+        let too_large = |_: &str| Some("128".to_string());
+        assert!(WorkerIdSource::FromEnv
+            .resolve_from(127, too_large, || None)
+            .is_err());
+        let not_number = |_: &str| Some("pod-1".to_string());
+        assert!(WorkerIdSource::FromHostname
+            .resolve_from(127, not_number, || None)
+            .is_err());
+
+        assert_eq!(
+            "from_hostname".parse::<WorkerIdSource>().unwrap(),
+            WorkerIdSource::FromHostname
+        );
+    }
+
+    #[test]
+    fn test_id_newtypes_serialize_as_plain_numbers() {
+        let mut cfg = IdGeneratorConfig::default();
+        cfg.worker_id = WorkerId::new(3, cfg.max_worker_id).unwrap();
+
+        let json = serde_json::to_value(&cfg).unwrap();
+        assert_eq!(json["worker_id"], 3);
+        assert_eq!(json["datacenter_id"], 0);
+
+        let back: IdGeneratorConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(back.worker_id, cfg.worker_id);
+    }
+}
@@ -0,0 +1,174 @@
+//! gRPC 健康检查
+//!
+//! 服务端实现标准的 `grpc.health.v1.Health`，客户端据此构建健康感知的负载均衡 channel：
+//! 定期探测每个节点，不健康的节点从负载均衡中剔除，恢复后重新加入。
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tonic::transport::channel::Change;
+use tonic::transport::{Channel, Endpoint, Uri};
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+use crate::proto::health::health_check_response::ServingStatus;
+use crate::proto::health::health_client::HealthClient;
+use crate::proto::health::health_server::Health;
+use crate::proto::health::{HealthCheckRequest, HealthCheckResponse};
+
+/// 默认探测间隔
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// 单次探测超时，超时视为不健康
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 服务端健康状态，clone 后共享同一状态，关闭前可置为 NOT_SERVING 让客户端提前摘除
+#[derive(Debug, Clone)]
+pub struct HealthService {
+    serving: Arc<AtomicBool>,
+}
+
+impl Default for HealthService {
+    fn default() -> Self {
+        Self {
+            serving: Arc::new(AtomicBool::new(true)),
+        }
+    }
+}
+
+impl HealthService {
+    pub fn set_serving(&self, serving: bool) {
+        self.serving.store(serving, Ordering::Release);
+    }
+
+    pub fn is_serving(&self) -> bool {
+        self.serving.load(Ordering::Acquire)
+    }
+}
+
+#[tonic::async_trait]
+impl Health for HealthService {
+    async fn check(
+        &self,
+        _request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        let status = if self.is_serving() {
+            ServingStatus::Serving
+        } else {
+            ServingStatus::NotServing
+        };
+        Ok(Response::new(HealthCheckResponse {
+            status: status as i32,
+        }))
+    }
+}
+
+/// 构建健康感知的负载均衡 channel
+///
+/// 启动时所有节点都加入（与 `Channel::balance_list` 一致），之后每隔 `interval`
+/// 探测一次，剔除不健康的节点、重新加入恢复的节点。需要在 tokio 运行时中调用。
+pub fn health_checked_channel(endpoints: Vec<Endpoint>, interval: Duration) -> Channel {
+    let (channel, tx) = Channel::balance_channel::<Uri>(endpoints.len().max(1));
+    tokio::spawn(async move {
+        let mut healthy = HashSet::new();
+        let mut probes = Vec::with_capacity(endpoints.len());
+        for endpoint in endpoints {
+            let uri = endpoint.uri().clone();
+            if tx
+                .send(Change::Insert(uri.clone(), endpoint.clone()))
+                .await
+                .is_err()
+            {
+                return;
+            }
+            healthy.insert(uri.clone());
+            probes.push((
+                uri,
+                endpoint.clone(),
+                HealthClient::new(endpoint.connect_lazy()),
+            ));
+        }
+
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for (uri, endpoint, client) in probes.iter_mut() {
+                let up = probe(client, HEALTH_CHECK_TIMEOUT).await;
+                let change = match (up, healthy.contains(uri)) {
+                    (true, false) => {
+                        info!(endpoint = %uri, "endpoint healthy again, adding back");
+                        healthy.insert(uri.clone());
+                        Change::Insert(uri.clone(), endpoint.clone())
+                    }
+                    (false, true) => {
+                        warn!(endpoint = %uri, "endpoint unhealthy, evicting");
+                        healthy.remove(uri);
+                        Change::Remove(uri.clone())
+                    }
+                    _ => continue,
+                };
+                // channel 已被丢弃，停止探测
+                if tx.send(change).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    channel
+}
+
+/// 探测一次，只有返回 SERVING 才算健康
+async fn probe(client: &mut HealthClient<Channel>, timeout: Duration) -> bool {
+    let check = client.check(HealthCheckRequest::default());
+    match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(resp)) => resp.into_inner().status() == ServingStatus::Serving,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::health::health_server::HealthServer;
+
+    async fn spawn_health_server(service: HealthService) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(HealthServer::new(service))
+                .serve_with_incoming(tonic::transport::server::TcpIncoming::from(listener)),
+        );
+        format!("http://{}", addr)
+    }
+
+    fn client(addr: String) -> HealthClient<Channel> {
+        HealthClient::new(Endpoint::from_shared(addr).unwrap().connect_lazy())
+    }
+
+    #[tokio::test]
+    async fn test_probe_follows_serving_status() {
+        let service = HealthService::default();
+        let mut client = client(spawn_health_server(service.clone()).await);
+
+        assert!(probe(&mut client, HEALTH_CHECK_TIMEOUT).await);
+        service.set_serving(false);
+        assert!(!probe(&mut client, HEALTH_CHECK_TIMEOUT).await);
+        service.set_serving(true);
+        assert!(probe(&mut client, HEALTH_CHECK_TIMEOUT).await);
+    }
+
+    #[tokio::test]
+    async fn test_probe_dead_endpoint() {
+        // 绑定后立即释放，得到一个没有监听者的端口
+        let addr = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut client = client(format!("http://{}", addr));
+
+        assert!(!probe(&mut client, HEALTH_CHECK_TIMEOUT).await);
+    }
+}
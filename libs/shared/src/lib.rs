@@ -1,6 +1,10 @@
 pub mod config;
 pub mod error;
+#[cfg(feature = "grpc")]
+pub mod health;
+#[cfg(feature = "http")]
 pub mod metric;
+#[cfg(feature = "grpc")]
 pub mod proto;
 pub mod traces;
 
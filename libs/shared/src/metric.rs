@@ -1,5 +1,5 @@
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use axum::{
@@ -9,6 +9,12 @@ use axum::{
     routing::get,
     Router,
 };
+use hdrhistogram::Histogram;
+use prometheus::core::Collector;
+use prometheus::{
+    Gauge, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+    TEXT_FORMAT,
+};
 use serde_json::json;
 use tokio::net::TcpListener;
 use tracing::info;
@@ -28,14 +34,27 @@ pub struct MetricsConfig {
     pub enable_detailed_metrics: bool,
 }
 
+/// HTTP 服务 metrics 的默认端口
+pub const DEFAULT_METRICS_PORT: u16 = 9090;
+
+/// gRPC 服务 metrics 的默认端口，与 HTTP 服务部署在同一台机器上时不会抢占端口
+pub const DEFAULT_GRPC_METRICS_PORT: u16 = 9091;
+
 impl Default for MetricsConfig {
     fn default() -> Self {
+        Self::with_default_port(DEFAULT_METRICS_PORT)
+    }
+}
+
+impl MetricsConfig {
+    /// 从环境变量读取配置，未设置 `METRICS_PORT` 时使用 `port`
+    pub fn with_default_port(port: u16) -> Self {
         Self {
             address: std::env::var("METRICS_ADDRESS").unwrap_or_else(|_| "0.0.0.0".to_string()),
             port: std::env::var("METRICS_PORT")
-                .unwrap_or_else(|_| "9090".to_string())
-                .parse()
-                .unwrap_or(9090),
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(port),
             metrics_path: std::env::var("METRICS_PATH").unwrap_or_else(|_| "/metrics".to_string()),
             health_path: std::env::var("HEALTH_PATH").unwrap_or_else(|_| "/health".to_string()),
             enable_detailed_metrics: std::env::var("ENABLE_DETAILED_METRICS")
@@ -59,8 +78,32 @@ pub struct AppMetrics {
     pub failed_requests: Arc<std::sync::atomic::AtomicU64>,
     /// 生成的 ID 总数
     pub generated_ids: Arc<std::sync::atomic::AtomicU64>,
-    /// 平均响应时间（毫秒）
-    pub avg_response_time_ms: Arc<std::sync::atomic::AtomicU64>,
+    /// 响应时间累计（毫秒），除以 `response_samples` 得到平均响应时间
+    pub response_time_sum_ms: Arc<std::sync::atomic::AtomicU64>,
+    /// 计入平均响应时间的样本数，每次 `record_success`/`record_failure` 加一
+    pub response_samples: Arc<std::sync::atomic::AtomicU64>,
+    /// 响应时间分位数，每次抓取 `/metrics` 后清空
+    pub latency: Arc<LatencyRecorder>,
+    /// 最大响应时间（毫秒）
+    pub max_response_time_ms: Arc<std::sync::atomic::AtomicU64>,
+    /// 超过慢请求阈值的请求数（不受日志采样影响）
+    pub slow_requests: Arc<std::sync::atomic::AtomicU64>,
+    /// ID 生成 CAS 失败重试总数，由 `IDGenerator` 共享写入
+    pub cas_retries: Arc<std::sync::atomic::AtomicU64>,
+    /// ID 生成时序列号耗尽或时钟回拨而等待的次数，由 `IDGenerator` 共享写入
+    pub sequence_waits: Arc<std::sync::atomic::AtomicU64>,
+    /// 最近一个采样周期的每秒生成数（f64 位模式，见 `record_generator_sample`）
+    pub generator_ids_per_second: Arc<std::sync::atomic::AtomicU64>,
+    /// 最近一个采样周期内平均每个 ID 的 CAS 重试次数（f64 位模式）
+    pub generator_cas_retries_per_id: Arc<std::sync::atomic::AtomicU64>,
+    /// 最近一个采样周期的序列号利用率，即实际速率 / 理论容量（f64 位模式）
+    pub generator_sequence_utilization: Arc<std::sync::atomic::AtomicU64>,
+    /// 时钟回拨累计次数
+    pub clock_backwards: Arc<std::sync::atomic::AtomicU64>,
+    /// 按路由模板和状态码统计的 HTTP 请求数
+    pub requests_by_route: IntCounterVec,
+    /// gRPC 调用指标，与上面的 HTTP 请求计数分开统计
+    pub grpc: Arc<GrpcMetrics>,
 }
 
 impl Default for AppMetrics {
@@ -71,7 +114,26 @@ impl Default for AppMetrics {
             successful_requests: Arc::new(std::sync::atomic::AtomicU64::new(0)),
             failed_requests: Arc::new(std::sync::atomic::AtomicU64::new(0)),
             generated_ids: Arc::new(std::sync::atomic::AtomicU64::new(0)),
-            avg_response_time_ms: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            response_time_sum_ms: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            response_samples: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            latency: Arc::new(LatencyRecorder::default()),
+            max_response_time_ms: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            slow_requests: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            cas_retries: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            sequence_waits: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            generator_ids_per_second: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            generator_cas_retries_per_id: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            generator_sequence_utilization: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            clock_backwards: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            requests_by_route: IntCounterVec::new(
+                Opts::new(
+                    "tinyid_requests_by_route_total",
+                    "Total number of HTTP requests by route and status code",
+                ),
+                &["route", "status"],
+            )
+            .expect("route requests metric options are valid"),
+            grpc: Arc::new(GrpcMetrics::default()),
         }
     }
 }
@@ -83,6 +145,15 @@ impl AppMetrics {
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
     }
 
+    /// 按路由模板（如 `/decode/{id}`）和状态码记录一次请求
+    ///
+    /// 路由应当是模板而不是实际路径，否则带参数的路径会产生无限多的序列。
+    pub fn record_route(&self, route: &str, status: u16) {
+        self.requests_by_route
+            .with_label_values(&[route, status.to_string().as_str()])
+            .inc();
+    }
+
     /// 记录成功请求
     pub fn record_success(&self, response_time_ms: u64) {
         self.successful_requests
@@ -99,31 +170,368 @@ impl AppMetrics {
 
     /// 增加生成的 ID 计数
     pub fn increment_generated_ids(&self) {
+        self.add_generated_ids(1);
+    }
+
+    /// 批量生成时按实际 ID 数累加
+    pub fn add_generated_ids(&self, count: u64) {
         self.generated_ids
+            .fetch_add(count, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// 记录一次失败，不计入响应时间（如 ID 生成失败）
+    pub fn increment_failed_requests(&self) {
+        self.failed_requests
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
     }
 
-    /// 更新平均响应时间
+    /// 记录慢请求，返回累计的慢请求数
+    pub fn record_slow_request(&self) -> u64 {
+        self.slow_requests
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            + 1
+    }
+
+    /// 记录一次响应时间（微秒），用于计算 p50/p95/p99
+    ///
+    /// 大部分请求在 1ms 以内，按毫秒记录时分位数都是 0。
+    pub fn record_latency(&self, response_time_us: u64) {
+        self.latency.record(response_time_us);
+    }
+
+    /// 更新最大响应时间
+    pub fn observe_response_time(&self, response_time_ms: u64) {
+        self.max_response_time_ms
+            .fetch_max(response_time_ms, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// 累加响应时间和样本数，平均值在抓取时计算
     fn update_avg_response_time(&self, response_time_ms: u64) {
-        // 简单的移动平均算法
-        let current_avg = self
-            .avg_response_time_ms
+        self.response_time_sum_ms
+            .fetch_add(response_time_ms, std::sync::atomic::Ordering::Relaxed);
+        self.response_samples
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// 平均响应时间（毫秒），没有样本时为 0
+    ///
+    /// 以 `response_samples` 而不是 `total_requests` 作为样本数：后者在请求进入时就已累加，
+    /// 且包含未记录耗时的请求，用作除数会使平均值偏小。
+    pub fn avg_response_time_ms(&self) -> f64 {
+        let samples = self
+            .response_samples
             .load(std::sync::atomic::Ordering::Relaxed);
-        let total_requests = self
-            .total_requests
+        if samples == 0 {
+            return 0.0;
+        }
+        let sum = self
+            .response_time_sum_ms
             .load(std::sync::atomic::Ordering::Relaxed);
+        sum as f64 / samples as f64
+    }
 
-        if total_requests > 0 {
-            let new_avg = (current_avg * (total_requests - 1) + response_time_ms) / total_requests;
-            self.avg_response_time_ms
-                .store(new_avg, std::sync::atomic::Ordering::Relaxed);
-        }
+    /// 记录一次生成器健康采样，gauge 以 f64 位模式保存在原子变量中
+    pub fn record_generator_sample(
+        &self,
+        ids_per_second: f64,
+        cas_retries_per_id: f64,
+        sequence_utilization: f64,
+        clock_backwards_total: u64,
+    ) {
+        let store = |gauge: &std::sync::atomic::AtomicU64, value: f64| {
+            gauge.store(value.to_bits(), std::sync::atomic::Ordering::Relaxed)
+        };
+        store(&self.generator_ids_per_second, ids_per_second);
+        store(&self.generator_cas_retries_per_id, cas_retries_per_id);
+        store(&self.generator_sequence_utilization, sequence_utilization);
+        self.clock_backwards
+            .store(clock_backwards_total, std::sync::atomic::Ordering::Relaxed);
     }
 
     /// 获取运行时间（秒）
     pub fn uptime_seconds(&self) -> u64 {
         self.start_time.elapsed().as_secs()
     }
+
+    /// 输出 Prometheus 文本格式的全部指标，同时清空响应时间分位数（见 `LatencyRecorder`）
+    ///
+    /// 计数保存在可与生成器共享的原子变量中，每次导出时复制到新的 registry 再编码。
+    pub fn render(&self) -> String {
+        let load =
+            |value: &std::sync::atomic::AtomicU64| value.load(std::sync::atomic::Ordering::Relaxed);
+        let load_f64 = |gauge: &std::sync::atomic::AtomicU64| f64::from_bits(load(gauge));
+        let total_requests = load(&self.total_requests);
+        let successful_requests = load(&self.successful_requests);
+        let latency = self.latency.percentiles_and_reset();
+        let success_rate = if total_requests > 0 {
+            successful_requests as f64 / total_requests as f64
+        } else {
+            0.0
+        };
+
+        let registry = Registry::new();
+        for (name, help, value) in [
+            (
+                "tinyid_requests_total",
+                "Total number of HTTP requests",
+                total_requests,
+            ),
+            (
+                "tinyid_requests_successful_total",
+                "Total number of successful HTTP requests",
+                successful_requests,
+            ),
+            (
+                "tinyid_requests_failed_total",
+                "Total number of failed HTTP requests",
+                load(&self.failed_requests),
+            ),
+            (
+                "tinyid_ids_generated_total",
+                "Total number of IDs generated",
+                load(&self.generated_ids),
+            ),
+            (
+                "tinyid_slow_requests_total",
+                "Total number of requests slower than the threshold",
+                load(&self.slow_requests),
+            ),
+            (
+                "tinyid_cas_retries_total",
+                "Total number of CAS retries during ID generation",
+                load(&self.cas_retries),
+            ),
+            (
+                "tinyid_clock_backwards_total",
+                "Total number of generate calls that observed the clock moving backwards",
+                load(&self.clock_backwards),
+            ),
+        ] {
+            let counter = IntCounter::new(name, help).expect("counter options are valid");
+            counter.inc_by(value);
+            register(&registry, counter);
+        }
+        for (name, help, value) in [
+            (
+                "tinyid_response_time_avg_ms",
+                "Average response time in milliseconds",
+                self.avg_response_time_ms(),
+            ),
+            (
+                "tinyid_response_time_max_ms",
+                "Maximum response time in milliseconds",
+                load(&self.max_response_time_ms) as f64,
+            ),
+            (
+                "tinyid_response_time_p50_ms",
+                "Median response time in milliseconds since the last scrape",
+                latency.p50 as f64 / 1_000.0,
+            ),
+            (
+                "tinyid_response_time_p95_ms",
+                "95th percentile response time in milliseconds since the last scrape",
+                latency.p95 as f64 / 1_000.0,
+            ),
+            (
+                "tinyid_response_time_p99_ms",
+                "99th percentile response time in milliseconds since the last scrape",
+                latency.p99 as f64 / 1_000.0,
+            ),
+            (
+                "tinyid_sequence_waits_total",
+                "Total number of waits for the next millisecond during ID generation",
+                load(&self.sequence_waits) as f64,
+            ),
+            (
+                "tinyid_generator_ids_per_second",
+                "IDs generated per second in the last sampling interval",
+                load_f64(&self.generator_ids_per_second),
+            ),
+            (
+                "tinyid_generator_cas_retries_per_id",
+                "Average CAS retries per ID in the last sampling interval",
+                load_f64(&self.generator_cas_retries_per_id),
+            ),
+            (
+                "tinyid_generator_sequence_utilization",
+                "Generation rate divided by theoretical capacity",
+                load_f64(&self.generator_sequence_utilization),
+            ),
+            (
+                "tinyid_uptime_seconds",
+                "Service uptime in seconds",
+                self.uptime_seconds() as f64,
+            ),
+            ("tinyid_success_rate", "Request success rate", success_rate),
+        ] {
+            let gauge = Gauge::new(name, help).expect("gauge options are valid");
+            gauge.set(value);
+            register(&registry, gauge);
+        }
+        register(&registry, self.requests_by_route.clone());
+        self.grpc.register(&registry);
+        encode(&registry)
+    }
+}
+
+/// 响应时间直方图可记录的最大值（微秒，即 60s），超出的样本按最大值记录
+const LATENCY_MAX_US: u64 = 60_000_000;
+
+/// 基于 HDR 直方图的响应时间记录器
+///
+/// `/metrics` 每次抓取时读取分位数并清空，报告的是两次抓取之间的延迟分布；
+/// 因此只应有一个抓取方，否则各抓取方看到的都是部分样本。
+#[derive(Debug)]
+pub struct LatencyRecorder {
+    histogram: Mutex<Histogram<u64>>,
+}
+
+/// 响应时间分位数（微秒），没有样本时均为 0
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+}
+
+impl Default for LatencyRecorder {
+    fn default() -> Self {
+        // 3 位有效数字：2048µs 以内精确到 1µs，更大的值误差在 0.1% 以内
+        let histogram =
+            Histogram::new_with_max(LATENCY_MAX_US, 3).expect("latency histogram bounds are valid");
+        Self {
+            histogram: Mutex::new(histogram),
+        }
+    }
+}
+
+impl LatencyRecorder {
+    pub fn record(&self, response_time_us: u64) {
+        self.histogram
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .saturating_record(response_time_us);
+    }
+
+    /// 当前的分位数，不清空
+    pub fn percentiles(&self) -> LatencyPercentiles {
+        let histogram = self.histogram.lock().unwrap_or_else(|e| e.into_inner());
+        Self::read(&histogram)
+    }
+
+    /// 读取分位数并清空，开始新的统计周期
+    pub fn percentiles_and_reset(&self) -> LatencyPercentiles {
+        let mut histogram = self.histogram.lock().unwrap_or_else(|e| e.into_inner());
+        let percentiles = Self::read(&histogram);
+        histogram.reset();
+        percentiles
+    }
+
+    fn read(histogram: &Histogram<u64>) -> LatencyPercentiles {
+        LatencyPercentiles {
+            p50: histogram.value_at_quantile(0.50),
+            p95: histogram.value_at_quantile(0.95),
+            p99: histogram.value_at_quantile(0.99),
+        }
+    }
+}
+
+/// gRPC 耗时直方图的桶上界（秒）
+const GRPC_DURATION_BUCKETS: [f64; 10] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0,
+];
+
+/// gRPC 调用指标：按 (service, method) 统计各状态码的调用数和耗时直方图
+#[derive(Debug)]
+pub struct GrpcMetrics {
+    requests: IntCounterVec,
+    duration: HistogramVec,
+}
+
+impl Default for GrpcMetrics {
+    fn default() -> Self {
+        let requests = IntCounterVec::new(
+            Opts::new(
+                "tinyid_grpc_requests_total",
+                "Total number of gRPC calls by method and status code",
+            ),
+            &["service", "method", "code"],
+        )
+        .expect("grpc requests metric options are valid");
+        let duration = HistogramVec::new(
+            HistogramOpts::new(
+                "tinyid_grpc_duration_seconds",
+                "gRPC call duration in seconds",
+            )
+            .buckets(GRPC_DURATION_BUCKETS.to_vec()),
+            &["service", "method"],
+        )
+        .expect("grpc duration metric options are valid");
+        Self { requests, duration }
+    }
+}
+
+impl GrpcMetrics {
+    /// 记录一次已结束的 gRPC 调用
+    pub fn record_call(&self, service: &str, method: &str, code: i32, duration: Duration) {
+        self.requests
+            .with_label_values(&[service, method, code.to_string().as_str()])
+            .inc();
+        self.duration
+            .with_label_values(&[service, method])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// 指定方法和状态码的调用数
+    pub fn requests(&self, service: &str, method: &str, code: i32) -> u64 {
+        // 通过 collect 读取，避免 with_label_values 为查询不存在的组合创建新序列
+        let code = code.to_string();
+        let wanted = [
+            ("service", service),
+            ("method", method),
+            ("code", code.as_str()),
+        ];
+        self.requests
+            .collect()
+            .iter()
+            .flat_map(|family| family.get_metric())
+            .find(|metric| {
+                wanted.iter().all(|&(name, value)| {
+                    metric
+                        .get_label()
+                        .iter()
+                        .any(|label| label.name() == name && label.value() == value)
+                })
+            })
+            .map_or(0, |metric| metric.get_counter().get_value() as u64)
+    }
+
+    /// 注册到导出用的 registry
+    pub fn register(&self, registry: &Registry) {
+        register(registry, self.requests.clone());
+        register(registry, self.duration.clone());
+    }
+
+    /// 输出 Prometheus 文本格式的 `tinyid_grpc_requests_total` 和 `tinyid_grpc_duration_seconds`
+    pub fn render(&self) -> String {
+        let registry = Registry::new();
+        self.register(&registry);
+        encode(&registry)
+    }
+}
+
+/// 注册指标；指标名在本文件中固定，重复注册属于编程错误
+fn register(registry: &Registry, collector: impl Collector + 'static) {
+    registry
+        .register(Box::new(collector))
+        .expect("each metric is registered once");
+}
+
+/// 编码为 Prometheus 文本格式
+fn encode(registry: &Registry) -> String {
+    TextEncoder::new()
+        .encode_to_string(&registry.gather())
+        .expect("registered metrics are always encodable")
 }
 
 /// Metrics 服务器
@@ -148,18 +556,7 @@ impl MetricsServer {
 
     /// 启动 metrics 服务器
     pub async fn start(&self) -> Result<()> {
-        let addr = format!("{}:{}", self.config.address, self.config.port);
-        let listener = TcpListener::bind(&addr).await?;
-
-        info!("Metrics server listening on {}", addr);
-
-        let app = self.create_router();
-
-        axum::serve(listener, app)
-            .await
-            .map_err(|e| anyhow::anyhow!("Metrics server error: {}", e))?;
-
-        Ok(())
+        self.start_with_shutdown(std::future::pending()).await
     }
 
     /// 带优雅关闭的启动方式
@@ -167,11 +564,26 @@ impl MetricsServer {
         &self,
         shutdown_signal: impl std::future::Future<Output = ()> + Send + 'static,
     ) -> Result<()> {
-        let addr = format!("{}:{}", self.config.address, self.config.port);
-        let listener = TcpListener::bind(&addr).await?;
+        let listener = self.bind().await?;
+        self.serve_with_shutdown(listener, shutdown_signal).await
+    }
 
+    /// 只绑定端口，调用方可以在启动主服务前决定绑定失败时是否退出
+    pub async fn bind(&self) -> Result<TcpListener> {
+        let addr = format!("{}:{}", self.config.address, self.config.port);
+        let listener = TcpListener::bind(&addr)
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to bind metrics server on {}: {}", addr, e))?;
         info!("Metrics server listening on {}", addr);
+        Ok(listener)
+    }
 
+    /// 在已绑定的端口上提供服务，直到收到关闭信号
+    pub async fn serve_with_shutdown(
+        &self,
+        listener: TcpListener,
+        shutdown_signal: impl std::future::Future<Output = ()> + Send + 'static,
+    ) -> Result<()> {
         let app = self.create_router();
 
         axum::serve(listener, app)
@@ -195,70 +607,10 @@ impl MetricsServer {
 
 /// Prometheus 格式的指标处理器
 async fn metrics_handler(State(metrics): State<Arc<AppMetrics>>) -> impl IntoResponse {
-    let total_requests = metrics
-        .total_requests
-        .load(std::sync::atomic::Ordering::Relaxed);
-    let successful_requests = metrics
-        .successful_requests
-        .load(std::sync::atomic::Ordering::Relaxed);
-    let failed_requests = metrics
-        .failed_requests
-        .load(std::sync::atomic::Ordering::Relaxed);
-    let generated_ids = metrics
-        .generated_ids
-        .load(std::sync::atomic::Ordering::Relaxed);
-    let avg_response_time = metrics
-        .avg_response_time_ms
-        .load(std::sync::atomic::Ordering::Relaxed);
-    let uptime = metrics.uptime_seconds();
-
-    // 生成 Prometheus 格式的指标
-    let prometheus_metrics = format!(
-        r#"# HELP tinyid_requests_total Total number of HTTP requests
-# TYPE tinyid_requests_total counter
-tinyid_requests_total {{}} {}
-
-# HELP tinyid_requests_successful_total Total number of successful HTTP requests  
-# TYPE tinyid_requests_successful_total counter
-tinyid_requests_successful_total {{}} {}
-
-# HELP tinyid_requests_failed_total Total number of failed HTTP requests
-# TYPE tinyid_requests_failed_total counter
-tinyid_requests_failed_total {{}} {}
-
-# HELP tinyid_ids_generated_total Total number of IDs generated
-# TYPE tinyid_ids_generated_total counter
-tinyid_ids_generated_total {{}} {}
-
-# HELP tinyid_response_time_avg_ms Average response time in milliseconds
-# TYPE tinyid_response_time_avg_ms gauge
-tinyid_response_time_avg_ms {{}} {}
-
-# HELP tinyid_uptime_seconds Service uptime in seconds
-# TYPE tinyid_uptime_seconds gauge
-tinyid_uptime_seconds {{}} {}
-
-# HELP tinyid_success_rate Request success rate
-# TYPE tinyid_success_rate gauge
-tinyid_success_rate {{}} {}
-"#,
-        total_requests,
-        successful_requests,
-        failed_requests,
-        generated_ids,
-        avg_response_time,
-        uptime,
-        if total_requests > 0 {
-            successful_requests as f64 / total_requests as f64
-        } else {
-            0.0
-        }
-    );
-
     Response::builder()
         .status(StatusCode::OK)
-        .header("Content-Type", "text/plain; version=0.0.4")
-        .body(prometheus_metrics)
+        .header("Content-Type", TEXT_FORMAT)
+        .body(metrics.render())
         .unwrap()
 }
 
@@ -301,7 +653,70 @@ pub fn init_metrics_with_config(config: MetricsConfig) -> Result<(MetricsServer,
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tokio::time::{sleep, Duration};
+
+    #[test]
+    fn test_avg_response_time() {
+        let metrics = AppMetrics::default();
+        let avg = || metrics.avg_response_time_ms();
+
+        // 先进入的请求不影响平均值的样本数
+        metrics.increment_request();
+        metrics.increment_request();
+        let mut averages = Vec::new();
+        for (i, ms) in [10, 20, 30, 41].into_iter().enumerate() {
+            if i % 2 == 0 {
+                metrics.record_success(ms);
+            } else {
+                metrics.record_failure(ms);
+            }
+            averages.push(avg());
+        }
+        // 不截断为整数毫秒
+        assert_eq!(averages, vec![10.0, 15.0, 20.0, 25.25]);
+        assert_eq!(
+            metrics
+                .response_samples
+                .load(std::sync::atomic::Ordering::Relaxed),
+            4
+        );
+
+        // 只计失败数的调用不计入平均值
+        metrics.increment_failed_requests();
+        metrics.record_success(99);
+        assert_eq!(avg(), 40.0);
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let metrics = AppMetrics::default();
+        assert_eq!(metrics.latency.percentiles(), LatencyPercentiles::default());
+
+        // 亚毫秒级的请求也能区分开
+        for us in (10..=1_000).step_by(10) {
+            metrics.record_latency(us);
+        }
+        let p = metrics.latency.percentiles();
+        assert!((490..=510).contains(&p.p50), "{:?}", p);
+        assert!((940..=960).contains(&p.p95), "{:?}", p);
+        assert!((980..=1_000).contains(&p.p99), "{:?}", p);
+
+        // 抓取后清空，下一个周期只反映新的样本：2% 的慢请求只出现在 p99
+        let _ = metrics.latency.percentiles_and_reset();
+        for _ in 0..980 {
+            metrics.record_latency(500);
+        }
+        for _ in 0..20 {
+            metrics.record_latency(1_000_000);
+        }
+        let p = metrics.latency.percentiles_and_reset();
+        assert_eq!((p.p50, p.p95), (500, 500));
+        assert!((999_000..=1_001_000).contains(&p.p99), "{:?}", p);
+        assert_eq!(metrics.latency.percentiles(), LatencyPercentiles::default());
+
+        // 超过上限的样本按上限记录
+        metrics.record_latency(LATENCY_MAX_US * 10);
+        assert!(metrics.latency.percentiles().p99 >= LATENCY_MAX_US);
+    }
 
     #[test]
     fn test_app_metrics() {
@@ -339,6 +754,150 @@ mod tests {
                 .load(std::sync::atomic::Ordering::Relaxed),
             1
         );
+
+        assert_eq!(metrics.record_slow_request(), 1);
+        assert_eq!(metrics.record_slow_request(), 2);
+
+        metrics.observe_response_time(300);
+        metrics.observe_response_time(120);
+        assert_eq!(
+            metrics
+                .max_response_time_ms
+                .load(std::sync::atomic::Ordering::Relaxed),
+            300
+        );
+    }
+
+    #[test]
+    fn test_render_is_valid_exposition_format() {
+        use prometheus_parse::{Scrape, Value};
+
+        let metrics = AppMetrics::default();
+        metrics.increment_request();
+        metrics.increment_request();
+        metrics.record_success(10);
+        metrics.record_failure(30);
+        metrics.add_generated_ids(5);
+        metrics.record_latency(250);
+        metrics
+            .grpc
+            .record_call("svc", "Call", 0, Duration::from_millis(2));
+
+        let text = metrics.render();
+        assert!(!text.contains("{}"), "{}", text);
+        let scrape = Scrape::parse(text.lines().map(|l| Ok(l.to_string()))).unwrap();
+
+        // 每个样本都有 HELP 和 TYPE，直方图的 _sum/_count 归属于同名指标
+        for sample in &scrape.samples {
+            let family = sample
+                .metric
+                .strip_suffix("_sum")
+                .or_else(|| sample.metric.strip_suffix("_count"))
+                .unwrap_or(&sample.metric);
+            assert!(scrape.docs.contains_key(family), "{}", sample.metric);
+            assert!(
+                text.contains(&format!("# TYPE {} ", family)),
+                "{}",
+                sample.metric
+            );
+        }
+        let value = |name: &str| {
+            scrape
+                .samples
+                .iter()
+                .find(|s| s.metric == name)
+                .map(|s| s.value.clone())
+        };
+        assert_eq!(value("tinyid_requests_total"), Some(Value::Counter(2.0)));
+        assert_eq!(
+            value("tinyid_requests_failed_total"),
+            Some(Value::Counter(1.0))
+        );
+        assert_eq!(
+            value("tinyid_ids_generated_total"),
+            Some(Value::Counter(5.0))
+        );
+        assert_eq!(
+            value("tinyid_response_time_avg_ms"),
+            Some(Value::Gauge(20.0))
+        );
+        assert_eq!(value("tinyid_success_rate"), Some(Value::Gauge(0.5)));
+        // 分位数按微秒记录，导出时换算为毫秒
+        assert_eq!(
+            value("tinyid_response_time_p50_ms"),
+            Some(Value::Gauge(0.25))
+        );
+        assert!(matches!(
+            value("tinyid_grpc_duration_seconds"),
+            Some(Value::Histogram(_))
+        ));
+        // 保持原有的指标名
+        for name in [
+            "tinyid_requests_successful_total",
+            "tinyid_response_time_max_ms",
+            "tinyid_response_time_p99_ms",
+            "tinyid_slow_requests_total",
+            "tinyid_cas_retries_total",
+            "tinyid_sequence_waits_total",
+            "tinyid_generator_ids_per_second",
+            "tinyid_generator_cas_retries_per_id",
+            "tinyid_generator_sequence_utilization",
+            "tinyid_clock_backwards_total",
+            "tinyid_uptime_seconds",
+            "tinyid_grpc_requests_total",
+        ] {
+            assert!(value(name).is_some(), "missing {}", name);
+        }
+    }
+
+    #[test]
+    fn test_grpc_metrics_render() {
+        let metrics = AppMetrics::default();
+        let service = "id_generator.v1.IdGeneratorService";
+        metrics
+            .grpc
+            .record_call(service, "GenerateId", 0, Duration::from_micros(300));
+        metrics
+            .grpc
+            .record_call(service, "GenerateId", 0, Duration::from_millis(20));
+        metrics
+            .grpc
+            .record_call(service, "GenerateId", 4, Duration::from_secs(2));
+        assert_eq!(metrics.grpc.requests(service, "GenerateId", 0), 2);
+        assert_eq!(metrics.grpc.requests(service, "GenerateId", 4), 1);
+        assert_eq!(metrics.grpc.requests(service, "GenerateIds", 0), 0);
+
+        let text = metrics.grpc.render();
+        // 标签按名称排序输出
+        let labels = format!("method=\"GenerateId\",service=\"{}\"", service);
+        for line in [
+            format!("tinyid_grpc_requests_total{{code=\"0\",{}}} 2", labels),
+            format!("tinyid_grpc_requests_total{{code=\"4\",{}}} 1", labels),
+            format!(
+                "tinyid_grpc_duration_seconds_bucket{{{},le=\"0.0005\"}} 1",
+                labels
+            ),
+            format!(
+                "tinyid_grpc_duration_seconds_bucket{{{},le=\"0.025\"}} 2",
+                labels
+            ),
+            format!(
+                "tinyid_grpc_duration_seconds_bucket{{{},le=\"1\"}} 2",
+                labels
+            ),
+            format!(
+                "tinyid_grpc_duration_seconds_bucket{{{},le=\"+Inf\"}} 3",
+                labels
+            ),
+            format!("tinyid_grpc_duration_seconds_count{{{}}} 3", labels),
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing {:?} in\n{}",
+                line,
+                text
+            );
+        }
     }
 
     #[tokio::test]
@@ -348,6 +907,9 @@ mod tests {
         assert_eq!(config.port, 9090);
         assert_eq!(config.metrics_path, "/metrics");
         assert_eq!(config.health_path, "/health");
+
+        let config = MetricsConfig::with_default_port(DEFAULT_GRPC_METRICS_PORT);
+        assert_eq!(config.port, 9091);
     }
 
     #[tokio::test]
@@ -366,4 +928,17 @@ mod tests {
             1
         );
     }
+
+    #[tokio::test]
+    async fn test_bind_reports_port_in_use() {
+        let occupied = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server = MetricsServer::new(MetricsConfig {
+            address: "127.0.0.1".to_string(),
+            port: occupied.local_addr().unwrap().port(),
+            ..MetricsConfig::default()
+        });
+
+        let err = server.bind().await.unwrap_err();
+        assert!(err.to_string().contains("failed to bind metrics server"));
+    }
 }
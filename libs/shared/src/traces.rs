@@ -2,18 +2,22 @@ use std::env;
 use std::sync::Once;
 
 use anyhow::Result;
-use opentelemetry::global;
-use opentelemetry::trace::TracerProvider;
-use opentelemetry::KeyValue;
+#[cfg(feature = "otel")]
+use opentelemetry::{global, trace::TracerProvider, KeyValue};
+#[cfg(feature = "otel")]
 use opentelemetry_otlp::SpanExporter;
-use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider, Tracer};
-use opentelemetry_sdk::Resource;
-use tracing::{error, info, warn, Subscriber};
-use tracing_opentelemetry::OpenTelemetryLayer;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracerProvider},
+    Resource,
+};
+use tracing::{error, info, Subscriber};
 use tracing_subscriber::{
     filter::EnvFilter,
     fmt::{self, format::FmtSpan},
     layer::SubscriberExt,
+    registry::LookupSpan,
     util::SubscriberInitExt,
     Layer, Registry,
 };
@@ -39,6 +43,8 @@ pub struct TracingConfig {
     pub console_output: bool,
     /// 是否启用JSON格式
     pub json_format: bool,
+    /// 禁用 span 导出，只保留 fmt 日志（本地开发时避免 stdout exporter 刷屏）
+    pub disable_span_export: bool,
 }
 
 impl Default for TracingConfig {
@@ -61,11 +67,16 @@ impl Default for TracingConfig {
                 .unwrap_or_else(|_| "true".to_string())
                 .parse()
                 .unwrap_or(true),
+            disable_span_export: env::var("DISABLE_SPAN_EXPORT")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
         }
     }
 }
 
 /// 初始化 OpenTelemetry tracer
+#[cfg(feature = "otel")]
 fn init_opentelemetry(
     config: &TracingConfig,
 ) -> Result<opentelemetry_sdk::trace::SdkTracerProvider> {
@@ -85,7 +96,14 @@ fn init_opentelemetry(
     let sampler = Sampler::AlwaysOn;
 
     // 创建 tracer provider
-    let tracer_provider = if let Some(otlp_endpoint) = &config.otlp_endpoint {
+    let tracer_provider = if config.disable_span_export {
+        info!("Span export disabled, only fmt logs will be emitted");
+        // 不挂载任何 exporter，span 仍然会生成以便 trace_id 传播
+        SdkTracerProvider::builder()
+            .with_resource(resource)
+            .with_sampler(sampler)
+            .build()
+    } else if let Some(otlp_endpoint) = &config.otlp_endpoint {
         info!("Initializing OTLP tracer with endpoint: {}", otlp_endpoint);
 
         // 创建 OTLP HTTP exporter
@@ -133,14 +151,8 @@ pub fn init_tracing_with_config(config: TracingConfig) -> Result<TracingCleanup>
 fn try_init_tracing(config: &TracingConfig, cleanup: &mut TracingCleanup) -> Result<()> {
     info!("Initializing tracing with config: {:?}", config);
 
-    // 1. 初始化 OpenTelemetry
-    let tracer_provider = init_opentelemetry(config)?;
-    cleanup.tracer_provider = Some(tracer_provider.clone());
-
-    // 2. 创建 OpenTelemetry layer
-    let trace_layer = tracing_opentelemetry::layer()
-        .with_error_records_to_exceptions(true)
-        .with_tracer(tracer_provider.tracer("tinyid"));
+    // 1. 初始化 OpenTelemetry 并创建 layer（未启用 otel feature 时为空）
+    let trace_layer = otel_layer(config, cleanup)?;
 
     // 3. 创建环境过滤器
     let env_filter =
@@ -184,21 +196,63 @@ fn try_init_tracing(config: &TracingConfig, cleanup: &mut TracingCleanup) -> Res
         "Tracing initialized successfully"
     );
 
-    // 设置全局 tracer provider
+    Ok(())
+}
+
+/// 创建 OpenTelemetry layer，并设置全局 tracer provider 和 W3C traceparent 传播器
+#[cfg(feature = "otel")]
+fn otel_layer<S>(
+    config: &TracingConfig,
+    cleanup: &mut TracingCleanup,
+) -> Result<Option<impl Layer<S>>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let tracer_provider = init_opentelemetry(config)?;
+    cleanup.tracer_provider = Some(tracer_provider.clone());
     global::set_tracer_provider(tracer_provider.clone());
+    // 未设置时全局传播器为 noop，上游传入的 traceparent 会被忽略，每个请求都新开一条 trace
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    Ok(Some(
+        tracing_opentelemetry::layer()
+            .with_error_records_to_exceptions(true)
+            .with_tracer(tracer_provider.tracer("tinyid")),
+    ))
+}
 
-    Ok(())
+/// 未启用 otel feature：只保留 fmt 日志
+#[cfg(not(feature = "otel"))]
+fn otel_layer<S>(
+    _config: &TracingConfig,
+    _cleanup: &mut TracingCleanup,
+) -> Result<Option<impl Layer<S>>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    info!("Built without the otel feature, only fmt logs will be emitted");
+    Ok(None::<tracing_subscriber::layer::Identity>)
 }
 
 /// 清理资源的结构体
 #[derive(Default)]
 pub struct TracingCleanup {
+    #[cfg(feature = "otel")]
     tracer_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
 }
 
 impl TracingCleanup {
+    /// 获取可在运行时手动 flush 的句柄（例如 admin 接口）
+    pub fn flusher(&self) -> TelemetryFlusher {
+        TelemetryFlusher {
+            #[cfg(feature = "otel")]
+            tracer_provider: self.tracer_provider.clone(),
+        }
+    }
+
     /// 执行清理操作
     pub fn cleanup(self) {
+        #[cfg(feature = "otel")]
         if let Some(provider) = self.tracer_provider {
             if let Err(e) = provider.shutdown() {
                 error!("Failed to shutdown tracer provider: {:?}", e);
@@ -209,6 +263,34 @@ impl TracingCleanup {
     }
 }
 
+/// 手动 flush 句柄，立即导出 batch exporter 中缓存的 span，而不必等待批处理定时器
+#[derive(Clone, Default)]
+pub struct TelemetryFlusher {
+    #[cfg(feature = "otel")]
+    tracer_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl TelemetryFlusher {
+    /// 是否持有 tracer provider（tracing 未初始化或未启用 otel feature 时为空操作）
+    pub fn is_enabled(&self) -> bool {
+        #[cfg(feature = "otel")]
+        return self.tracer_provider.is_some();
+        #[cfg(not(feature = "otel"))]
+        false
+    }
+
+    /// 强制导出缓存的 span
+    pub fn force_flush(&self) -> Result<()> {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = &self.tracer_provider {
+            provider
+                .force_flush()
+                .map_err(|e| anyhow::anyhow!("failed to flush tracer provider: {:?}", e))?;
+        }
+        Ok(())
+    }
+}
+
 /// 兼容性函数，保持旧接口
 pub fn init_logs() {
     INIT.call_once(|| {
@@ -219,6 +301,7 @@ pub fn init_logs() {
 }
 
 // 示例函数：使用instrument宏自动创建span
+#[cfg(test)]
 #[tracing::instrument]
 fn generate_id_with_span() -> u64 {
     info!("Starting ID generation");
@@ -232,6 +315,7 @@ fn generate_id_with_span() -> u64 {
 }
 
 // 示例函数：手动创建span
+#[cfg(test)]
 fn process_request_with_manual_span(request_id: &str) {
     let span = tracing::info_span!("process_request", request_id = request_id);
     let _enter = span.enter();
@@ -261,9 +345,15 @@ mod tests {
 
     use crate::init_env;
 
+    /// 测试中不依赖外部 collector
+    fn init_test_env() {
+        init_env();
+        std::env::set_var("DISABLE_SPAN_EXPORT", "true");
+    }
+
     #[test]
     fn test_init_logs() {
-        init_env();
+        init_test_env();
         init_logs();
 
         // 创建根span
@@ -286,7 +376,7 @@ mod tests {
     #[test]
     #[instrument]
     fn test_instrumented_function() {
-        init_env();
+        init_test_env();
         init_logs();
 
         info!("this is an instrumented function log");
@@ -295,7 +385,7 @@ mod tests {
 
     #[test]
     fn test_span_hierarchy() {
-        init_env();
+        init_test_env();
         init_logs();
 
         // 测试span层次结构
@@ -330,7 +420,7 @@ mod tests {
 
     #[test]
     fn test_instrumented_functions() {
-        init_env();
+        init_test_env();
         init_logs();
 
         // 测试使用instrument宏的函数
@@ -340,4 +430,38 @@ mod tests {
         // 测试手动创建span的函数
         process_request_with_manual_span("req-123");
     }
+
+    #[cfg(feature = "otel")]
+    #[test]
+    fn test_disable_span_export() {
+        let config = TracingConfig {
+            otlp_endpoint: None,
+            disable_span_export: true,
+            ..TracingConfig::default()
+        };
+        let provider = init_opentelemetry(&config).unwrap();
+
+        let tracer = provider.tracer("test");
+        opentelemetry::trace::Tracer::in_span(&tracer, "noop", |_| {});
+        assert!(provider.shutdown().is_ok());
+    }
+
+    #[cfg(feature = "otel")]
+    #[test]
+    fn test_flusher() {
+        assert!(!TelemetryFlusher::default().is_enabled());
+        assert!(TelemetryFlusher::default().force_flush().is_ok());
+
+        let config = TracingConfig {
+            disable_span_export: true,
+            ..TracingConfig::default()
+        };
+        let cleanup = TracingCleanup {
+            tracer_provider: Some(init_opentelemetry(&config).unwrap()),
+        };
+        let flusher = cleanup.flusher();
+        assert!(flusher.is_enabled());
+        assert!(flusher.force_flush().is_ok());
+        cleanup.cleanup();
+    }
 }